@@ -20,7 +20,7 @@ class DatabaseAdmin {
 
     async loadEnvConfig() {
         try {
-            const response = await fetch(`${this.apiBaseUrl}/config/env`);
+            const response = await authFetch(`${this.apiBaseUrl}/config/env`);
             if (response.ok) {
                 this.envConfig = await response.json();
                 console.log('Loaded env config:', this.envConfig);
@@ -206,7 +206,7 @@ class DatabaseAdmin {
             for (const endpoint of endpoints) {
                 try {
                     this.addLog(`🔍 Trying endpoint: ${endpoint}`);
-                    const response = await fetch(`${this.apiBaseUrl.replace('/api', '')}${endpoint}`);
+                    const response = await authFetch(`${this.apiBaseUrl.replace('/api', '')}${endpoint}`);
                     
                     if (response.ok) {
                         const data = await response.json();
@@ -404,15 +404,16 @@ class DatabaseAdmin {
         this.addLog(`📡 Making request to: ${url}`);
         
         const defaultOptions = {
+            ...options,
             headers: {
                 'Content-Type': 'application/json',
-                'Accept': 'application/json'
-            },
-            ...options
+                'Accept': 'application/json',
+                ...(options.headers || {})
+            }
         };
 
         try {
-            const response = await fetch(url, defaultOptions);
+            const response = await authFetch(url, defaultOptions);
             
             this.addLog(`📥 Response status: ${response.status} ${response.statusText}`);
             
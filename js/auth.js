@@ -0,0 +1,101 @@
+// API login for MemberCommons pages
+//
+// Keeps the bearer token from /api/auth/login in localStorage.authToken and
+// sends it with every API request. When the API answers 401 the token is
+// dropped, a login form is shown and the request is sent again once signed
+// in. Only use authFetch for MemberCommons API URLs, never for external
+// hosts, so the token stays with the API.
+
+const AUTH_API_BASE = 'http://localhost:8081/api';
+
+// Authorization header for the API token saved at login, if any
+function authHeaders() {
+    const token = localStorage.getItem('authToken');
+    return token ? { 'Authorization': `Bearer ${token}` } : {};
+}
+
+function clearAuthToken() {
+    localStorage.removeItem('authToken');
+}
+
+let loginPromise = null;
+
+// Show the login form; resolves once a token has been saved. Requests that
+// fail at the same time wait for the same form.
+function showLoginForm() {
+    if (loginPromise) {
+        return loginPromise;
+    }
+
+    loginPromise = new Promise((resolve) => {
+        const overlay = document.createElement('div');
+        overlay.id = 'auth-login-overlay';
+        overlay.style.cssText = 'position:fixed;inset:0;z-index:10000;display:flex;align-items:center;' +
+            'justify-content:center;background:rgba(0,0,0,0.4);font-family:inherit;';
+        overlay.innerHTML = `
+            <form style="background:#FFFFFF;border-radius:12px;padding:24px;width:320px;max-width:90vw;
+                         box-shadow:0 4px 6px -1px rgba(0,0,0,0.1);display:flex;flex-direction:column;gap:12px;">
+                <h3 style="margin:0;font-size:18px;color:#1A1A1A;">Sign in</h3>
+                <input name="username" type="text" placeholder="User name or email" autocomplete="username" required
+                       style="padding:8px 10px;border:1px solid #D1D5DB;border-radius:8px;">
+                <input name="password" type="password" placeholder="Password" autocomplete="current-password" required
+                       style="padding:8px 10px;border:1px solid #D1D5DB;border-radius:8px;">
+                <div class="auth-login-error" style="color:#EF4444;font-size:13px;min-height:16px;"></div>
+                <button type="submit" style="padding:8px 10px;border:none;border-radius:8px;background:#3B82F6;
+                                             color:#FFFFFF;cursor:pointer;">Sign in</button>
+            </form>`;
+
+        const form = overlay.querySelector('form');
+        const error = overlay.querySelector('.auth-login-error');
+        form.addEventListener('submit', async (event) => {
+            event.preventDefault();
+            error.textContent = '';
+            try {
+                const response = await fetch(`${AUTH_API_BASE}/auth/login`, {
+                    method: 'POST',
+                    headers: { 'Content-Type': 'application/json' },
+                    body: JSON.stringify({
+                        username: form.elements.username.value,
+                        password: form.elements.password.value
+                    })
+                });
+                const result = await response.json();
+                if (!response.ok || !result.success) {
+                    error.textContent = result.error || `Login failed (HTTP ${response.status})`;
+                    return;
+                }
+                localStorage.setItem('authToken', result.token);
+                overlay.remove();
+                loginPromise = null;
+                resolve();
+            } catch (e) {
+                error.textContent = `Login failed: ${e.message}`;
+            }
+        });
+
+        document.body.appendChild(overlay);
+        form.elements.username.focus();
+    });
+    return loginPromise;
+}
+
+// fetch() for API URLs: adds the token and signs in again on 401
+async function authFetch(url, options = {}) {
+    const send = () => fetch(url, {
+        ...options,
+        headers: { ...(options.headers || {}), ...authHeaders() }
+    });
+
+    const response = await send();
+    if (response.status !== 401) {
+        return response;
+    }
+    clearAuthToken();
+    await showLoginForm();
+    return send();
+}
+
+function logout() {
+    clearAuthToken();
+    showLoginForm();
+}
@@ -9,7 +9,7 @@ async function apiCall(endpoint, method = 'GET', data = null) {
         const options = {
             method: method,
             headers: {
-                'Content-Type': 'application/json',
+                'Content-Type': 'application/json'
             }
         };
         
@@ -17,7 +17,8 @@ async function apiCall(endpoint, method = 'GET', data = null) {
             options.body = JSON.stringify(data);
         }
         
-        const response = await fetch(`${API_BASE}${endpoint}`, options);
+        // authFetch comes from js/auth.js, which pages load before this file
+        const response = await authFetch(`${API_BASE}${endpoint}`, options);
         
         if (!response.ok) {
             throw new Error(`HTTP error! status: ${response.status}`);
@@ -433,7 +433,7 @@ async function fetchExternalAPI(url, options = {}, forceCorsProxy = false, API_B
     // Use Rust backend proxy
     try {
         // Use Rust backend to fetch the external URL
-        const proxyResponse = await fetch(`${API_BASE}/proxy`, {
+        const proxyResponse = await authFetch(`${API_BASE}/proxy`, {
             method: 'POST',
             headers: {
                 'Content-Type': 'application/json',
@@ -884,7 +884,7 @@ async function loadUnifiedData(url, options = {}) {
                 
                 // Fallback to backend proxy
                 try {
-                    const proxyResponse = await fetch(`${API_BASE}/google/fetch-csv`, {
+                    const proxyResponse = await authFetch(`${API_BASE}/google/fetch-csv`, {
                         method: 'POST',
                         headers: {
                             'Content-Type': 'application/json'
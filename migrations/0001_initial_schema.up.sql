@@ -0,0 +1,322 @@
+-- Core CRM schema (previously created by `init-db`)
+
+-- Create users table
+CREATE TABLE IF NOT EXISTS users (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_name VARCHAR(60),
+    first_name VARCHAR(30),
+    last_name VARCHAR(30),
+    email VARCHAR(100),
+    status VARCHAR(100),
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create accounts table
+CREATE TABLE IF NOT EXISTS accounts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(150),
+    account_type VARCHAR(50),
+    industry VARCHAR(50),
+    phone_office VARCHAR(100),
+    website VARCHAR(255),
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(36),
+    modified_user_id VARCHAR(36)
+);
+
+-- Create contacts table
+CREATE TABLE IF NOT EXISTS contacts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    salutation VARCHAR(255),
+    first_name VARCHAR(100),
+    last_name VARCHAR(100),
+    title VARCHAR(100),
+    department VARCHAR(255),
+    account_id UUID REFERENCES accounts(id),
+    phone_work VARCHAR(100),
+    phone_mobile VARCHAR(100),
+    email VARCHAR(100),
+    primary_address_street VARCHAR(150),
+    primary_address_city VARCHAR(100),
+    primary_address_state VARCHAR(100),
+    primary_address_postalcode VARCHAR(20),
+    primary_address_country VARCHAR(255),
+    description TEXT,
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(36),
+    modified_user_id VARCHAR(36)
+);
+
+-- Create projects table
+CREATE TABLE IF NOT EXISTS projects (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(50),
+    description TEXT,
+    status VARCHAR(50),
+    priority VARCHAR(255),
+    estimated_start_date DATE,
+    estimated_end_date DATE,
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(36),
+    modified_user_id VARCHAR(36)
+);
+
+-- Create opportunities table
+CREATE TABLE IF NOT EXISTS opportunities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(50),
+    account_id UUID REFERENCES accounts(id),
+    opportunity_type VARCHAR(255),
+    lead_source VARCHAR(50),
+    amount DECIMAL(26,6),
+    currency_id VARCHAR(36),
+    date_closed DATE,
+    sales_stage VARCHAR(255),
+    probability DECIMAL(3,0),
+    description TEXT,
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(36),
+    modified_user_id VARCHAR(36)
+);
+
+-- Create activities table
+CREATE TABLE IF NOT EXISTS activities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255),
+    date_due TIMESTAMP WITH TIME ZONE,
+    date_start TIMESTAMP WITH TIME ZONE,
+    parent_type VARCHAR(255),
+    parent_id UUID,
+    status VARCHAR(100),
+    priority VARCHAR(255),
+    description TEXT,
+    contact_id UUID REFERENCES contacts(id),
+    account_id UUID REFERENCES accounts(id),
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(36),
+    modified_user_id VARCHAR(36)
+);
+
+-- Create leads table
+CREATE TABLE IF NOT EXISTS leads (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    salutation VARCHAR(255),
+    first_name VARCHAR(100),
+    last_name VARCHAR(100),
+    title VARCHAR(100),
+    company VARCHAR(100),
+    phone_work VARCHAR(100),
+    phone_mobile VARCHAR(100),
+    email VARCHAR(100),
+    status VARCHAR(100),
+    lead_source VARCHAR(100),
+    description TEXT,
+    converted BOOLEAN DEFAULT false,
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(36),
+    modified_user_id VARCHAR(36)
+);
+
+-- Create campaigns table
+CREATE TABLE IF NOT EXISTS campaigns (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(50),
+    campaign_type VARCHAR(100),
+    status VARCHAR(100),
+    start_date DATE,
+    end_date DATE,
+    budget DECIMAL(26,6),
+    expected_cost DECIMAL(26,6),
+    actual_cost DECIMAL(26,6),
+    expected_revenue DECIMAL(26,6),
+    objective TEXT,
+    content TEXT,
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(36),
+    modified_user_id VARCHAR(36)
+);
+
+-- Create documents table
+CREATE TABLE IF NOT EXISTS documents (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    document_name VARCHAR(255),
+    filename VARCHAR(255),
+    file_ext VARCHAR(100),
+    file_mime_type VARCHAR(100),
+    revision VARCHAR(100),
+    category_id VARCHAR(100),
+    subcategory_id VARCHAR(100),
+    status VARCHAR(100),
+    description TEXT,
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(36),
+    modified_user_id VARCHAR(36)
+);
+
+-- Create events table
+CREATE TABLE IF NOT EXISTS events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255),
+    date_start TIMESTAMP WITH TIME ZONE,
+    date_end TIMESTAMP WITH TIME ZONE,
+    duration_hours INTEGER,
+    duration_minutes INTEGER,
+    location VARCHAR(255),
+    description TEXT,
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(36),
+    modified_user_id VARCHAR(36)
+);
+
+-- Create products table
+CREATE TABLE IF NOT EXISTS products (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(50),
+    product_code VARCHAR(50),
+    category VARCHAR(100),
+    manufacturer VARCHAR(50),
+    cost DECIMAL(26,6),
+    price DECIMAL(26,6),
+    description TEXT,
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(36),
+    modified_user_id VARCHAR(36)
+);
+
+-- Create roles table
+CREATE TABLE IF NOT EXISTS roles (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(150),
+    description TEXT,
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(36),
+    modified_user_id VARCHAR(36)
+);
+
+-- Create calls table
+CREATE TABLE IF NOT EXISTS calls (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(50),
+    date_start TIMESTAMP WITH TIME ZONE,
+    date_end TIMESTAMP WITH TIME ZONE,
+    duration_hours INTEGER,
+    duration_minutes INTEGER,
+    status VARCHAR(100),
+    direction VARCHAR(100),
+    parent_type VARCHAR(255),
+    parent_id UUID,
+    contact_id UUID REFERENCES contacts(id),
+    account_id UUID REFERENCES accounts(id),
+    description TEXT,
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(36),
+    modified_user_id VARCHAR(36)
+);
+
+-- Create surveyquestionoptions table
+CREATE TABLE IF NOT EXISTS surveyquestionoptions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(50),
+    survey_question_id UUID,
+    sort_order INTEGER,
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(36),
+    modified_user_id VARCHAR(36)
+);
+
+-- Create tags table
+CREATE TABLE IF NOT EXISTS tags (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255),
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create taggables table (polymorphic relationship)
+CREATE TABLE IF NOT EXISTS taggables (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tag_id UUID REFERENCES tags(id),
+    taggable_type VARCHAR(100),
+    taggable_id UUID,
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(tag_id, taggable_type, taggable_id)
+);
+
+-- Relationship tables
+
+-- User roles relationship
+CREATE TABLE IF NOT EXISTS users_roles (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID REFERENCES users(id),
+    role_id UUID REFERENCES roles(id),
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, role_id)
+);
+
+-- Account contacts relationship
+CREATE TABLE IF NOT EXISTS accounts_contacts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID REFERENCES accounts(id),
+    contact_id UUID REFERENCES contacts(id),
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(account_id, contact_id)
+);
+
+-- Account opportunities relationship
+CREATE TABLE IF NOT EXISTS accounts_opportunities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID REFERENCES accounts(id),
+    opportunity_id UUID REFERENCES opportunities(id),
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(account_id, opportunity_id)
+);
+
+-- Contact opportunities relationship
+CREATE TABLE IF NOT EXISTS contacts_opportunities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    contact_id UUID REFERENCES contacts(id),
+    opportunity_id UUID REFERENCES opportunities(id),
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(contact_id, opportunity_id)
+);
+
+-- Campaign leads relationship
+CREATE TABLE IF NOT EXISTS campaigns_leads (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    campaign_id UUID REFERENCES campaigns(id),
+    lead_id UUID REFERENCES leads(id),
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(campaign_id, lead_id)
+);
+
+-- Project contacts relationship
+CREATE TABLE IF NOT EXISTS projects_contacts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    project_id UUID REFERENCES projects(id),
+    contact_id UUID REFERENCES contacts(id),
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(project_id, contact_id)
+);
+
+-- Project accounts relationship
+CREATE TABLE IF NOT EXISTS projects_accounts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    project_id UUID REFERENCES projects(id),
+    account_id UUID REFERENCES accounts(id),
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(project_id, account_id)
+);
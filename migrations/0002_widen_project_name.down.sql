@@ -0,0 +1,2 @@
+-- Longer names are cut to fit the original column width
+ALTER TABLE projects ALTER COLUMN name TYPE VARCHAR(50) USING left(name, 50);
@@ -0,0 +1,2 @@
+-- Project names from the DFC sheet and DemocracyLab regularly exceed 50 characters
+ALTER TABLE projects ALTER COLUMN name TYPE VARCHAR(255);
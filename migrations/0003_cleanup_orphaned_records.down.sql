@@ -0,0 +1,2 @@
+-- Removed orphans cannot be restored; nothing to undo
+SELECT 1;
@@ -0,0 +1,120 @@
+-- Clean up orphaned references (was admin/sql/cleanup_orphaned_records.sql)
+--
+-- Runs before the foreign key migration so the constraints can be added.
+-- Covers the same columns as 0004: rows whose parent would cascade are
+-- deleted, other references are set to NULL. Tables or columns that do not
+-- exist in this database, or whose type does not match the referenced id
+-- (e.g. VARCHAR created_by against UUID users.id), are left alone.
+DO $$
+DECLARE
+    fk RECORD;
+BEGIN
+    FOR fk IN SELECT * FROM (VALUES
+        ('accounts', 'modified_user_id', 'users', 'fk_accounts_modified_user', 'SET NULL'),
+        ('accounts', 'created_by', 'users', 'fk_accounts_created_by', 'SET NULL'),
+        ('accounts', 'assigned_user_id', 'users', 'fk_accounts_assigned_user', 'SET NULL'),
+        ('accounts', 'parent_id', 'accounts', 'fk_accounts_parent', 'SET NULL'),
+        ('accounts', 'campaign_id', 'campaigns', 'fk_accounts_campaign', 'SET NULL'),
+        ('accounts_audit', 'parent_id', 'accounts', 'fk_accounts_audit_parent', 'CASCADE'),
+        ('accounts_bugs', 'account_id', 'accounts', 'fk_accounts_bugs_account', 'CASCADE'),
+        ('accounts_bugs', 'bug_id', 'bugs', 'fk_accounts_bugs_bug', 'CASCADE'),
+        ('accounts_cases', 'account_id', 'accounts', 'fk_accounts_cases_account', 'CASCADE'),
+        ('accounts_cases', 'case_id', 'cases', 'fk_accounts_cases_case', 'CASCADE'),
+        ('accounts_contacts', 'account_id', 'accounts', 'fk_accounts_contacts_account', 'CASCADE'),
+        ('accounts_contacts', 'contact_id', 'contacts', 'fk_accounts_contacts_contact', 'CASCADE'),
+        ('accounts_opportunities', 'account_id', 'accounts', 'fk_accounts_opportunities_account', 'CASCADE'),
+        ('accounts_opportunities', 'opportunity_id', 'opportunities', 'fk_accounts_opportunities_opportunity', 'CASCADE'),
+        ('accounts_cstm', 'id_c', 'accounts', 'fk_accounts_cstm_parent', 'CASCADE'),
+        ('acl_actions', 'modified_user_id', 'users', 'fk_acl_actions_modified_user', 'SET NULL'),
+        ('acl_actions', 'created_by', 'users', 'fk_acl_actions_created_by', 'SET NULL'),
+        ('acl_roles', 'modified_user_id', 'users', 'fk_acl_roles_modified_user', 'SET NULL'),
+        ('acl_roles', 'created_by', 'users', 'fk_acl_roles_created_by', 'SET NULL'),
+        ('acl_roles_actions', 'role_id', 'acl_roles', 'fk_acl_roles_actions_role', 'CASCADE'),
+        ('acl_roles_actions', 'action_id', 'acl_actions', 'fk_acl_roles_actions_action', 'CASCADE'),
+        ('acl_roles_users', 'role_id', 'acl_roles', 'fk_acl_roles_users_role', 'CASCADE'),
+        ('acl_roles_users', 'user_id', 'users', 'fk_acl_roles_users_user', 'CASCADE'),
+        ('alerts', 'modified_user_id', 'users', 'fk_alerts_modified_user', 'SET NULL'),
+        ('alerts', 'created_by', 'users', 'fk_alerts_created_by', 'SET NULL'),
+        ('alerts', 'assigned_user_id', 'users', 'fk_alerts_assigned_user', 'SET NULL'),
+        ('alerts', 'reminder_id', 'reminders', 'fk_alerts_reminder', 'CASCADE'),
+        ('contacts', 'modified_user_id', 'users', 'fk_contacts_modified_user', 'SET NULL'),
+        ('contacts', 'created_by', 'users', 'fk_contacts_created_by', 'SET NULL'),
+        ('contacts', 'assigned_user_id', 'users', 'fk_contacts_assigned_user', 'SET NULL'),
+        ('contacts', 'account_id', 'accounts', 'fk_contacts_account', 'SET NULL'),
+        ('contacts', 'reports_to_id', 'contacts', 'fk_contacts_reports_to', 'SET NULL'),
+        ('contacts', 'campaign_id', 'campaigns', 'fk_contacts_campaign', 'SET NULL'),
+        ('opportunities', 'modified_user_id', 'users', 'fk_opportunities_modified_user', 'SET NULL'),
+        ('opportunities', 'created_by', 'users', 'fk_opportunities_created_by', 'SET NULL'),
+        ('opportunities', 'assigned_user_id', 'users', 'fk_opportunities_assigned_user', 'SET NULL'),
+        ('opportunities', 'campaign_id', 'campaigns', 'fk_opportunities_campaign', 'SET NULL'),
+        ('cases', 'modified_user_id', 'users', 'fk_cases_modified_user', 'SET NULL'),
+        ('cases', 'created_by', 'users', 'fk_cases_created_by', 'SET NULL'),
+        ('cases', 'assigned_user_id', 'users', 'fk_cases_assigned_user', 'SET NULL'),
+        ('leads', 'modified_user_id', 'users', 'fk_leads_modified_user', 'SET NULL'),
+        ('leads', 'created_by', 'users', 'fk_leads_created_by', 'SET NULL'),
+        ('leads', 'assigned_user_id', 'users', 'fk_leads_assigned_user', 'SET NULL'),
+        ('leads', 'campaign_id', 'campaigns', 'fk_leads_campaign', 'SET NULL'),
+        ('campaigns', 'modified_user_id', 'users', 'fk_campaigns_modified_user', 'SET NULL'),
+        ('campaigns', 'created_by', 'users', 'fk_campaigns_created_by', 'SET NULL'),
+        ('campaigns', 'assigned_user_id', 'users', 'fk_campaigns_assigned_user', 'SET NULL'),
+        ('notes', 'modified_user_id', 'users', 'fk_notes_modified_user', 'SET NULL'),
+        ('notes', 'created_by', 'users', 'fk_notes_created_by', 'SET NULL'),
+        ('notes', 'assigned_user_id', 'users', 'fk_notes_assigned_user', 'SET NULL'),
+        ('notes', 'contact_id', 'contacts', 'fk_notes_contact', 'CASCADE'),
+        ('tasks', 'modified_user_id', 'users', 'fk_tasks_modified_user', 'SET NULL'),
+        ('tasks', 'created_by', 'users', 'fk_tasks_created_by', 'SET NULL'),
+        ('tasks', 'assigned_user_id', 'users', 'fk_tasks_assigned_user', 'SET NULL'),
+        ('tasks', 'contact_id', 'contacts', 'fk_tasks_contact', 'SET NULL'),
+        ('calls', 'modified_user_id', 'users', 'fk_calls_modified_user', 'SET NULL'),
+        ('calls', 'created_by', 'users', 'fk_calls_created_by', 'SET NULL'),
+        ('calls', 'assigned_user_id', 'users', 'fk_calls_assigned_user', 'SET NULL'),
+        ('meetings', 'modified_user_id', 'users', 'fk_meetings_modified_user', 'SET NULL'),
+        ('meetings', 'created_by', 'users', 'fk_meetings_created_by', 'SET NULL'),
+        ('meetings', 'assigned_user_id', 'users', 'fk_meetings_assigned_user', 'SET NULL'),
+        ('emails', 'modified_user_id', 'users', 'fk_emails_modified_user', 'SET NULL'),
+        ('emails', 'created_by', 'users', 'fk_emails_created_by', 'SET NULL'),
+        ('emails', 'assigned_user_id', 'users', 'fk_emails_assigned_user', 'SET NULL'),
+        ('project', 'modified_user_id', 'users', 'fk_project_modified_user', 'SET NULL'),
+        ('project', 'created_by', 'users', 'fk_project_created_by', 'SET NULL'),
+        ('project', 'assigned_user_id', 'users', 'fk_project_assigned_user', 'SET NULL'),
+        ('project_task', 'modified_user_id', 'users', 'fk_project_task_modified_user', 'SET NULL'),
+        ('project_task', 'created_by', 'users', 'fk_project_task_created_by', 'SET NULL'),
+        ('project_task', 'assigned_user_id', 'users', 'fk_project_task_assigned_user', 'SET NULL'),
+        ('project_task', 'project_id', 'project', 'fk_project_task_project', 'CASCADE'),
+        ('documents', 'modified_user_id', 'users', 'fk_documents_modified_user', 'SET NULL'),
+        ('documents', 'created_by', 'users', 'fk_documents_created_by', 'SET NULL'),
+        ('documents', 'assigned_user_id', 'users', 'fk_documents_assigned_user', 'SET NULL'),
+        ('users_password_link', 'user_id', 'users', 'fk_users_password_link_user', 'CASCADE'),
+        ('users_signatures', 'user_id', 'users', 'fk_users_signatures_user', 'CASCADE'),
+        ('vcals', 'user_id', 'users', 'fk_vcals_user', 'CASCADE'),
+        ('email_templates', 'modified_user_id', 'users', 'fk_email_templates_modified_user', 'SET NULL'),
+        ('email_templates', 'created_by', 'users', 'fk_email_templates_created_by', 'SET NULL'),
+        ('email_templates', 'assigned_user_id', 'users', 'fk_email_templates_assigned_user', 'SET NULL')
+    ) AS v(tbl, col, ref_table, constraint_name, on_delete)
+    LOOP
+        IF EXISTS (
+            SELECT 1
+            FROM information_schema.columns c
+            JOIN information_schema.columns r
+              ON r.table_schema = c.table_schema
+             AND r.table_name = fk.ref_table
+             AND r.column_name = 'id'
+            WHERE c.table_schema = current_schema()
+              AND c.table_name = fk.tbl
+              AND c.column_name = fk.col
+              AND c.udt_name = r.udt_name
+        ) THEN
+            IF fk.on_delete = 'CASCADE' THEN
+                EXECUTE format(
+                    'DELETE FROM %I t WHERE t.%I IS NOT NULL AND NOT EXISTS (SELECT 1 FROM %I p WHERE p.id = t.%I)',
+                    fk.tbl, fk.col, fk.ref_table, fk.col
+                );
+            ELSE
+                EXECUTE format(
+                    'UPDATE %I t SET %I = NULL WHERE t.%I IS NOT NULL AND NOT EXISTS (SELECT 1 FROM %I p WHERE p.id = t.%I)',
+                    fk.tbl, fk.col, fk.col, fk.ref_table, fk.col
+                );
+            END IF;
+        END IF;
+    END LOOP;
+END $$;
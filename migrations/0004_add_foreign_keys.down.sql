@@ -0,0 +1,81 @@
+-- Drop the constraints added by 0004; those already declared by the core schema are untouched
+ALTER TABLE IF EXISTS accounts DROP CONSTRAINT IF EXISTS fk_accounts_modified_user;
+ALTER TABLE IF EXISTS accounts DROP CONSTRAINT IF EXISTS fk_accounts_created_by;
+ALTER TABLE IF EXISTS accounts DROP CONSTRAINT IF EXISTS fk_accounts_assigned_user;
+ALTER TABLE IF EXISTS accounts DROP CONSTRAINT IF EXISTS fk_accounts_parent;
+ALTER TABLE IF EXISTS accounts DROP CONSTRAINT IF EXISTS fk_accounts_campaign;
+ALTER TABLE IF EXISTS accounts_audit DROP CONSTRAINT IF EXISTS fk_accounts_audit_parent;
+ALTER TABLE IF EXISTS accounts_bugs DROP CONSTRAINT IF EXISTS fk_accounts_bugs_account;
+ALTER TABLE IF EXISTS accounts_bugs DROP CONSTRAINT IF EXISTS fk_accounts_bugs_bug;
+ALTER TABLE IF EXISTS accounts_cases DROP CONSTRAINT IF EXISTS fk_accounts_cases_account;
+ALTER TABLE IF EXISTS accounts_cases DROP CONSTRAINT IF EXISTS fk_accounts_cases_case;
+ALTER TABLE IF EXISTS accounts_contacts DROP CONSTRAINT IF EXISTS fk_accounts_contacts_account;
+ALTER TABLE IF EXISTS accounts_contacts DROP CONSTRAINT IF EXISTS fk_accounts_contacts_contact;
+ALTER TABLE IF EXISTS accounts_opportunities DROP CONSTRAINT IF EXISTS fk_accounts_opportunities_account;
+ALTER TABLE IF EXISTS accounts_opportunities DROP CONSTRAINT IF EXISTS fk_accounts_opportunities_opportunity;
+ALTER TABLE IF EXISTS accounts_cstm DROP CONSTRAINT IF EXISTS fk_accounts_cstm_parent;
+ALTER TABLE IF EXISTS acl_actions DROP CONSTRAINT IF EXISTS fk_acl_actions_modified_user;
+ALTER TABLE IF EXISTS acl_actions DROP CONSTRAINT IF EXISTS fk_acl_actions_created_by;
+ALTER TABLE IF EXISTS acl_roles DROP CONSTRAINT IF EXISTS fk_acl_roles_modified_user;
+ALTER TABLE IF EXISTS acl_roles DROP CONSTRAINT IF EXISTS fk_acl_roles_created_by;
+ALTER TABLE IF EXISTS acl_roles_actions DROP CONSTRAINT IF EXISTS fk_acl_roles_actions_role;
+ALTER TABLE IF EXISTS acl_roles_actions DROP CONSTRAINT IF EXISTS fk_acl_roles_actions_action;
+ALTER TABLE IF EXISTS acl_roles_users DROP CONSTRAINT IF EXISTS fk_acl_roles_users_role;
+ALTER TABLE IF EXISTS acl_roles_users DROP CONSTRAINT IF EXISTS fk_acl_roles_users_user;
+ALTER TABLE IF EXISTS alerts DROP CONSTRAINT IF EXISTS fk_alerts_modified_user;
+ALTER TABLE IF EXISTS alerts DROP CONSTRAINT IF EXISTS fk_alerts_created_by;
+ALTER TABLE IF EXISTS alerts DROP CONSTRAINT IF EXISTS fk_alerts_assigned_user;
+ALTER TABLE IF EXISTS alerts DROP CONSTRAINT IF EXISTS fk_alerts_reminder;
+ALTER TABLE IF EXISTS contacts DROP CONSTRAINT IF EXISTS fk_contacts_modified_user;
+ALTER TABLE IF EXISTS contacts DROP CONSTRAINT IF EXISTS fk_contacts_created_by;
+ALTER TABLE IF EXISTS contacts DROP CONSTRAINT IF EXISTS fk_contacts_assigned_user;
+ALTER TABLE IF EXISTS contacts DROP CONSTRAINT IF EXISTS fk_contacts_account;
+ALTER TABLE IF EXISTS contacts DROP CONSTRAINT IF EXISTS fk_contacts_reports_to;
+ALTER TABLE IF EXISTS contacts DROP CONSTRAINT IF EXISTS fk_contacts_campaign;
+ALTER TABLE IF EXISTS opportunities DROP CONSTRAINT IF EXISTS fk_opportunities_modified_user;
+ALTER TABLE IF EXISTS opportunities DROP CONSTRAINT IF EXISTS fk_opportunities_created_by;
+ALTER TABLE IF EXISTS opportunities DROP CONSTRAINT IF EXISTS fk_opportunities_assigned_user;
+ALTER TABLE IF EXISTS opportunities DROP CONSTRAINT IF EXISTS fk_opportunities_campaign;
+ALTER TABLE IF EXISTS cases DROP CONSTRAINT IF EXISTS fk_cases_modified_user;
+ALTER TABLE IF EXISTS cases DROP CONSTRAINT IF EXISTS fk_cases_created_by;
+ALTER TABLE IF EXISTS cases DROP CONSTRAINT IF EXISTS fk_cases_assigned_user;
+ALTER TABLE IF EXISTS leads DROP CONSTRAINT IF EXISTS fk_leads_modified_user;
+ALTER TABLE IF EXISTS leads DROP CONSTRAINT IF EXISTS fk_leads_created_by;
+ALTER TABLE IF EXISTS leads DROP CONSTRAINT IF EXISTS fk_leads_assigned_user;
+ALTER TABLE IF EXISTS leads DROP CONSTRAINT IF EXISTS fk_leads_campaign;
+ALTER TABLE IF EXISTS campaigns DROP CONSTRAINT IF EXISTS fk_campaigns_modified_user;
+ALTER TABLE IF EXISTS campaigns DROP CONSTRAINT IF EXISTS fk_campaigns_created_by;
+ALTER TABLE IF EXISTS campaigns DROP CONSTRAINT IF EXISTS fk_campaigns_assigned_user;
+ALTER TABLE IF EXISTS notes DROP CONSTRAINT IF EXISTS fk_notes_modified_user;
+ALTER TABLE IF EXISTS notes DROP CONSTRAINT IF EXISTS fk_notes_created_by;
+ALTER TABLE IF EXISTS notes DROP CONSTRAINT IF EXISTS fk_notes_assigned_user;
+ALTER TABLE IF EXISTS notes DROP CONSTRAINT IF EXISTS fk_notes_contact;
+ALTER TABLE IF EXISTS tasks DROP CONSTRAINT IF EXISTS fk_tasks_modified_user;
+ALTER TABLE IF EXISTS tasks DROP CONSTRAINT IF EXISTS fk_tasks_created_by;
+ALTER TABLE IF EXISTS tasks DROP CONSTRAINT IF EXISTS fk_tasks_assigned_user;
+ALTER TABLE IF EXISTS tasks DROP CONSTRAINT IF EXISTS fk_tasks_contact;
+ALTER TABLE IF EXISTS calls DROP CONSTRAINT IF EXISTS fk_calls_modified_user;
+ALTER TABLE IF EXISTS calls DROP CONSTRAINT IF EXISTS fk_calls_created_by;
+ALTER TABLE IF EXISTS calls DROP CONSTRAINT IF EXISTS fk_calls_assigned_user;
+ALTER TABLE IF EXISTS meetings DROP CONSTRAINT IF EXISTS fk_meetings_modified_user;
+ALTER TABLE IF EXISTS meetings DROP CONSTRAINT IF EXISTS fk_meetings_created_by;
+ALTER TABLE IF EXISTS meetings DROP CONSTRAINT IF EXISTS fk_meetings_assigned_user;
+ALTER TABLE IF EXISTS emails DROP CONSTRAINT IF EXISTS fk_emails_modified_user;
+ALTER TABLE IF EXISTS emails DROP CONSTRAINT IF EXISTS fk_emails_created_by;
+ALTER TABLE IF EXISTS emails DROP CONSTRAINT IF EXISTS fk_emails_assigned_user;
+ALTER TABLE IF EXISTS project DROP CONSTRAINT IF EXISTS fk_project_modified_user;
+ALTER TABLE IF EXISTS project DROP CONSTRAINT IF EXISTS fk_project_created_by;
+ALTER TABLE IF EXISTS project DROP CONSTRAINT IF EXISTS fk_project_assigned_user;
+ALTER TABLE IF EXISTS project_task DROP CONSTRAINT IF EXISTS fk_project_task_modified_user;
+ALTER TABLE IF EXISTS project_task DROP CONSTRAINT IF EXISTS fk_project_task_created_by;
+ALTER TABLE IF EXISTS project_task DROP CONSTRAINT IF EXISTS fk_project_task_assigned_user;
+ALTER TABLE IF EXISTS project_task DROP CONSTRAINT IF EXISTS fk_project_task_project;
+ALTER TABLE IF EXISTS documents DROP CONSTRAINT IF EXISTS fk_documents_modified_user;
+ALTER TABLE IF EXISTS documents DROP CONSTRAINT IF EXISTS fk_documents_created_by;
+ALTER TABLE IF EXISTS documents DROP CONSTRAINT IF EXISTS fk_documents_assigned_user;
+ALTER TABLE IF EXISTS users_password_link DROP CONSTRAINT IF EXISTS fk_users_password_link_user;
+ALTER TABLE IF EXISTS users_signatures DROP CONSTRAINT IF EXISTS fk_users_signatures_user;
+ALTER TABLE IF EXISTS vcals DROP CONSTRAINT IF EXISTS fk_vcals_user;
+ALTER TABLE IF EXISTS email_templates DROP CONSTRAINT IF EXISTS fk_email_templates_modified_user;
+ALTER TABLE IF EXISTS email_templates DROP CONSTRAINT IF EXISTS fk_email_templates_created_by;
+ALTER TABLE IF EXISTS email_templates DROP CONSTRAINT IF EXISTS fk_email_templates_assigned_user;
@@ -0,0 +1,2 @@
+-- The admin role is left in place because it may have existed before this migration
+ALTER TABLE users DROP COLUMN IF EXISTS password_hash;
@@ -0,0 +1,7 @@
+-- Password hashes for API login (argon2 PHC strings)
+ALTER TABLE users ADD COLUMN IF NOT EXISTS password_hash VARCHAR(255);
+
+-- Role required for the configuration, admin and SQL endpoints
+INSERT INTO roles (id, name, description)
+SELECT gen_random_uuid(), 'admin', 'Full access, including configuration and SQL endpoints'
+WHERE NOT EXISTS (SELECT 1 FROM roles WHERE lower(name) = 'admin');
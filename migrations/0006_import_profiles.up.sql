@@ -0,0 +1,14 @@
+-- Saved column mappings for spreadsheet imports
+CREATE TABLE IF NOT EXISTS import_profiles (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(150) NOT NULL UNIQUE,
+    description TEXT,
+    table_name VARCHAR(100) NOT NULL,
+    sheet_name VARCHAR(255),
+    -- Source column header -> target column
+    column_mappings JSONB NOT NULL DEFAULT '{}'::jsonb,
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    date_modified TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(36),
+    modified_user_id VARCHAR(36)
+);
@@ -0,0 +1,41 @@
+-- One row per import run, and the records each run inserted or updated
+CREATE TABLE IF NOT EXISTS import_jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- excel, data or democracylab
+    source VARCHAR(50) NOT NULL,
+    table_name VARCHAR(100) NOT NULL,
+    file_name VARCHAR(255),
+    -- SHA-256 of the uploaded file or posted records
+    file_hash VARCHAR(64),
+    profile VARCHAR(150),
+    column_mappings JSONB,
+    options JSONB NOT NULL DEFAULT '{}'::jsonb,
+    -- running, completed, failed, discarded, dry_run or rolled_back
+    status VARCHAR(20) NOT NULL DEFAULT 'running',
+    records_processed INTEGER NOT NULL DEFAULT 0,
+    records_inserted INTEGER NOT NULL DEFAULT 0,
+    records_updated INTEGER NOT NULL DEFAULT 0,
+    records_skipped INTEGER NOT NULL DEFAULT 0,
+    records_rejected INTEGER NOT NULL DEFAULT 0,
+    errors JSONB NOT NULL DEFAULT '[]'::jsonb,
+    created_by VARCHAR(36),
+    started_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    finished_at TIMESTAMP WITH TIME ZONE,
+    rolled_back_at TIMESTAMP WITH TIME ZONE,
+    rolled_back_by VARCHAR(36)
+);
+
+CREATE INDEX IF NOT EXISTS idx_import_jobs_started_at ON import_jobs (started_at DESC);
+
+CREATE TABLE IF NOT EXISTS import_job_rows (
+    job_id UUID NOT NULL REFERENCES import_jobs(id) ON DELETE CASCADE,
+    row_number INTEGER NOT NULL,
+    record_id UUID NOT NULL,
+    -- insert or update
+    action VARCHAR(10) NOT NULL,
+    -- The record as it was before an update
+    previous JSONB,
+    PRIMARY KEY (job_id, row_number)
+);
+
+CREATE INDEX IF NOT EXISTS idx_import_job_rows_record_id ON import_job_rows (record_id);
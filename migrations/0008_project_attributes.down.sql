@@ -0,0 +1,24 @@
+-- Pack the attributes back into the description before dropping them
+UPDATE projects SET description = NULLIF(concat_ws(E'\n\n',
+    NULLIF(description, ''),
+    'Department: ' || department,
+    'Region: ' || region,
+    'Country: ' || country,
+    'Framework: ' || framework,
+    'NAICS Sector: ' || naics_sector,
+    'Profile URL: ' || profile_url
+), '')
+WHERE COALESCE(department, region, country, framework, naics_sector, profile_url) IS NOT NULL;
+
+DROP INDEX IF EXISTS idx_projects_name_region_department;
+
+ALTER TABLE projects
+    DROP COLUMN IF EXISTS fiscal_year,
+    DROP COLUMN IF EXISTS project_number,
+    DROP COLUMN IF EXISTS committed_amount,
+    DROP COLUMN IF EXISTS country,
+    DROP COLUMN IF EXISTS region,
+    DROP COLUMN IF EXISTS department,
+    DROP COLUMN IF EXISTS framework,
+    DROP COLUMN IF EXISTS naics_sector,
+    DROP COLUMN IF EXISTS profile_url;
@@ -0,0 +1,31 @@
+-- DFC project attributes, previously packed into the description as
+-- "Region: ..." lines separated by blank lines
+ALTER TABLE projects
+    ADD COLUMN IF NOT EXISTS fiscal_year VARCHAR(20),
+    ADD COLUMN IF NOT EXISTS project_number VARCHAR(50),
+    ADD COLUMN IF NOT EXISTS committed_amount DECIMAL(26,6),
+    ADD COLUMN IF NOT EXISTS country VARCHAR(100),
+    ADD COLUMN IF NOT EXISTS region VARCHAR(100),
+    ADD COLUMN IF NOT EXISTS department VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS framework VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS naics_sector VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS profile_url VARCHAR(500);
+
+-- Fiscal year, project number and committed amount were never stored, so
+-- only the packed attributes can be recovered
+UPDATE projects SET
+    department = left(substring(description from '(?:^|\n\n)Department: ([^\n]*)'), 255),
+    region = left(substring(description from '(?:^|\n\n)Region: ([^\n]*)'), 100),
+    country = left(substring(description from '(?:^|\n\n)Country: ([^\n]*)'), 100),
+    framework = left(substring(description from '(?:^|\n\n)Framework: ([^\n]*)'), 255),
+    naics_sector = left(substring(description from '(?:^|\n\n)NAICS Sector: ([^\n]*)'), 255),
+    profile_url = left(substring(description from '(?:^|\n\n)Profile URL: ([^\n]*)'), 500),
+    description = NULLIF(btrim(regexp_replace(
+        description,
+        '(^|\n\n)(Department|Region|Country|Framework|NAICS Sector|Profile URL): [^\n]*(?=\n|$)',
+        '',
+        'g'
+    ), E'\n'), '')
+WHERE description ~ '(^|\n\n)(Department|Region|Country|Framework|NAICS Sector|Profile URL): ';
+
+CREATE INDEX IF NOT EXISTS idx_projects_name_region_department ON projects (name, region, department);
@@ -0,0 +1,10 @@
+-- Rows an import job rejected, as they were given, for the rejected-rows download
+CREATE TABLE IF NOT EXISTS import_job_rejections (
+    job_id UUID NOT NULL REFERENCES import_jobs(id) ON DELETE CASCADE,
+    row_number INTEGER NOT NULL,
+    -- [column, value] pairs in the order the row gave them
+    cells JSONB NOT NULL,
+    -- [{column, code, message}]
+    errors JSONB NOT NULL,
+    PRIMARY KEY (job_id, row_number)
+);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_projects_name_trgm;
+DROP INDEX IF EXISTS idx_accounts_name_trgm;
+DROP FUNCTION IF EXISTS normalize_name(TEXT);
@@ -0,0 +1,17 @@
+-- Normalised names and trigram indexes for duplicate detection
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Lower case, apostrophes and periods removed, other punctuation as spaces
+-- and trailing legal suffixes dropped (never the first word). Keep in step
+-- with duplicates::normalize_name.
+CREATE OR REPLACE FUNCTION normalize_name(name TEXT) RETURNS TEXT
+LANGUAGE sql IMMUTABLE PARALLEL SAFE AS $$
+    SELECT regexp_replace(
+        btrim(regexp_replace(regexp_replace(lower(name), '[''’.]', '', 'g'), '[^[:alnum:]]+', ' ', 'g')),
+        '( (inc|incorporated|llc|ltd|limited|corp|corporation|co|company|plc|gmbh|ag|sa|bv|pty|llp|lp))+$',
+        ''
+    )
+$$;
+
+CREATE INDEX IF NOT EXISTS idx_accounts_name_trgm ON accounts USING gin (normalize_name(name) gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_projects_name_trgm ON projects USING gin (normalize_name(name) gin_trgm_ops);
@@ -0,0 +1,9 @@
+DROP INDEX IF EXISTS idx_projects_source_id;
+
+ALTER TABLE projects
+    DROP COLUMN IF EXISTS source,
+    DROP COLUMN IF EXISTS source_id,
+    DROP COLUMN IF EXISTS source_modified,
+    DROP COLUMN IF EXISTS skills,
+    DROP COLUMN IF EXISTS location,
+    DROP COLUMN IF EXISTS organization;
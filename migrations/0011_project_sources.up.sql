@@ -0,0 +1,13 @@
+-- Where a project was synced from, so later syncs update it instead of
+-- adding a copy, and the attributes DemocracyLab projects carry
+ALTER TABLE projects
+    ADD COLUMN IF NOT EXISTS source VARCHAR(50),
+    ADD COLUMN IF NOT EXISTS source_id VARCHAR(100),
+    -- When the source last changed the project, for incremental syncs
+    ADD COLUMN IF NOT EXISTS source_modified TIMESTAMP WITH TIME ZONE,
+    -- Comma-separated
+    ADD COLUMN IF NOT EXISTS skills TEXT,
+    ADD COLUMN IF NOT EXISTS location VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS organization VARCHAR(255);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_projects_source_id ON projects (source, source_id) WHERE source_id IS NOT NULL;
@@ -0,0 +1,8 @@
+-- Event participants, e.g. imported from a Meetup sign-up sheet
+CREATE TABLE IF NOT EXISTS events_contacts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    event_id UUID REFERENCES events(id),
+    contact_id UUID REFERENCES contacts(id),
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(event_id, contact_id)
+);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS url_import_snapshots;
+DROP TABLE IF EXISTS url_import_sources;
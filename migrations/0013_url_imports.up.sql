@@ -0,0 +1,39 @@
+-- Spreadsheets imported from a URL (a Google Sheets tab or a CSV file),
+-- saved so they can be pulled again, optionally on a schedule
+CREATE TABLE IF NOT EXISTS url_import_sources (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(150) NOT NULL UNIQUE,
+    url TEXT NOT NULL,
+    -- Google Sheets tab
+    gid VARCHAR(30),
+    table_name VARCHAR(100) NOT NULL,
+    profile VARCHAR(150),
+    column_mappings JSONB,
+    -- Column that identifies a row when pulls are compared
+    key_column VARCHAR(255),
+    options JSONB NOT NULL DEFAULT '{}'::jsonb,
+    -- Minutes between scheduled pulls; NULL pulls only on request
+    schedule_minutes INTEGER,
+    last_pulled_at TIMESTAMP WITH TIME ZONE,
+    date_entered TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(36)
+);
+
+-- Every pull of a URL as the CSV it returned, to show what changed between pulls
+CREATE TABLE IF NOT EXISTS url_import_snapshots (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- The CSV URL that was fetched
+    url TEXT NOT NULL,
+    source_id UUID REFERENCES url_import_sources(id) ON DELETE SET NULL,
+    job_id UUID REFERENCES import_jobs(id) ON DELETE SET NULL,
+    -- SHA-256 of the CSV
+    content_hash VARCHAR(64) NOT NULL,
+    headers JSONB NOT NULL,
+    -- Data rows as arrays of cell text
+    rows JSONB NOT NULL,
+    row_count INTEGER NOT NULL,
+    created_by VARCHAR(36),
+    fetched_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_url_import_snapshots_url ON url_import_snapshots (url, fetched_at DESC);
@@ -0,0 +1,19 @@
+-- Audit log of calls made through /api/proxy, including refused ones
+CREATE TABLE IF NOT EXISTS proxy_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id VARCHAR(36),
+    method VARCHAR(10) NOT NULL,
+    url TEXT NOT NULL,
+    -- Host and resolved addresses of the last hop, after redirects
+    host VARCHAR(255),
+    addresses TEXT[],
+    status_code INTEGER,
+    response_bytes BIGINT,
+    error TEXT,
+    -- Refused by the allowlist or the address checks
+    blocked BOOLEAN NOT NULL DEFAULT FALSE,
+    duration_ms INTEGER,
+    requested_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_proxy_requests_requested_at ON proxy_requests (requested_at DESC);
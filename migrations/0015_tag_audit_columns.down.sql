@@ -0,0 +1,3 @@
+ALTER TABLE tags
+    DROP COLUMN IF EXISTS created_by,
+    DROP COLUMN IF EXISTS modified_user_id;
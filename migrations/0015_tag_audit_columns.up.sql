@@ -0,0 +1,5 @@
+-- Tags are stamped with who created and last changed them, like the other
+-- entity tables
+ALTER TABLE tags
+    ADD COLUMN IF NOT EXISTS created_by VARCHAR(36),
+    ADD COLUMN IF NOT EXISTS modified_user_id VARCHAR(36);
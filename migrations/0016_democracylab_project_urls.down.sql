@@ -0,0 +1,8 @@
+-- Pack the links of projects imported before syncs back into the description
+UPDATE projects SET
+    description = NULLIF(concat_ws(E'\n\n', NULLIF(description, ''), 'Project URL: ' || profile_url), ''),
+    profile_url = NULL
+WHERE created_by = 'democracylab-import' AND source_id IS NULL AND profile_url IS NOT NULL;
+
+UPDATE projects SET source = NULL
+WHERE created_by = 'democracylab-import' AND source_id IS NULL AND source = 'democracylab';
@@ -0,0 +1,16 @@
+-- Projects imported from DemocracyLab before syncs kept the project link
+-- as a "Project URL: ..." line in the description; it belongs in
+-- profile_url. They are marked as DemocracyLab projects so the next sync
+-- can claim them by name.
+UPDATE projects SET
+    profile_url = COALESCE(profile_url, left(substring(description from '(?:^|\n\n)Project URL: ([^\n]*)'), 500)),
+    description = NULLIF(btrim(regexp_replace(
+        description,
+        '(^|\n\n)Project URL: [^\n]*(?=\n|$)',
+        '',
+        'g'
+    ), E'\n'), '')
+WHERE description ~ '(^|\n\n)Project URL: ';
+
+UPDATE projects SET source = 'democracylab'
+WHERE created_by = 'democracylab-import' AND source IS NULL;
@@ -0,0 +1,395 @@
+// src/auth.rs
+//
+// Username/password login that issues a JWT, plus the middleware that
+// requires one on every protected route. Roles come from the `users`,
+// `roles` and `users_roles` tables; the admin check re-reads them on each
+// request so revoking the role takes effect before the token expires.
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::ErrorUnauthorized,
+    middleware::Next,
+    web, Error, FromRequest, HttpMessage, HttpRequest, HttpResponse, Result,
+};
+use anyhow::{bail, Context};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Pool, Postgres, Row};
+use std::future::{ready, Ready};
+use std::io::{BufRead, IsTerminal};
+use std::sync::{Arc, OnceLock};
+use uuid::Uuid;
+
+use crate::ApiState;
+
+pub const ADMIN_ROLE: &str = "admin";
+
+/// Tables that only admins may read or change through the generic endpoints,
+/// since editing them is how roles are granted
+pub const ADMIN_TABLES: &[&str] = &["roles", "users_roles"];
+
+/// Signing keys and token lifetime, built once at startup
+pub struct JwtKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    expiry_hours: i64,
+}
+
+impl JwtKeys {
+    pub fn new(secret: &str, expiry_hours: i64) -> Self {
+        JwtKeys {
+            encoding: EncodingKey::from_secret(secret.as_bytes()),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+            expiry_hours,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: Uuid,
+    pub name: String,
+    pub roles: Vec<String>,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+impl Claims {
+    /// Value stored in `created_by` / `modified_user_id`
+    pub fn user_id(&self) -> String {
+        self.sub.to_string()
+    }
+}
+
+// Handlers behind `require_auth` can take `Claims` as an argument
+impl FromRequest for Claims {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<Claims>()
+                .cloned()
+                .ok_or_else(|| ErrorUnauthorized("Authentication required")),
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    /// User name or email address
+    username: String,
+    password: String,
+}
+
+fn unauthorized(error: &str) -> HttpResponse {
+    HttpResponse::Unauthorized().json(json!({
+        "success": false,
+        "error": error
+    }))
+}
+
+pub fn hash_password(password: &str) -> anyhow::Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))
+}
+
+fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
+
+/// Hash of a password no one has, for logins that have nothing to verify
+fn dummy_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    DUMMY_HASH.get_or_init(|| hash_password("no password is set for this login").unwrap_or_default())
+}
+
+async fn user_roles(pool: &Pool<Postgres>, user_id: Uuid) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT r.name
+        FROM roles r
+        JOIN users_roles ur ON ur.role_id = r.id
+        WHERE ur.user_id = $1 AND r.name IS NOT NULL
+        ORDER BY r.name
+        "#
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+fn has_role(roles: &[String], role: &str) -> bool {
+    roles.iter().any(|r| r.eq_ignore_ascii_case(role))
+}
+
+/// Whether the user currently holds the admin role
+pub async fn is_admin(pool: &Pool<Postgres>, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    Ok(has_role(&user_roles(pool, user_id).await?, ADMIN_ROLE))
+}
+
+// Exchange a user name (or email) and password for a bearer token
+pub async fn login(
+    data: web::Data<Arc<ApiState>>,
+    req: web::Json<LoginRequest>,
+) -> Result<HttpResponse> {
+    let user = sqlx::query(
+        r#"
+        SELECT id, user_name, status, password_hash
+        FROM users
+        WHERE lower(user_name) = lower($1) OR lower(email) = lower($1)
+        ORDER BY (lower(user_name) = lower($1)) DESC
+        LIMIT 1
+        "#
+    )
+    .bind(req.username.trim())
+    .fetch_optional(&data.db)
+    .await;
+
+    let user = match user {
+        Ok(user) => user,
+        Err(e) => {
+            println!("Login query failed: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "error": "Login is unavailable"
+            })));
+        }
+    };
+
+    // Unknown users and users without a password are checked against a
+    // dummy hash, so the response time does not reveal which names exist.
+    // Hashing is deliberately slow, keep it off the async workers
+    let hash = user.as_ref().and_then(|row| row.get::<Option<String>, _>("password_hash"));
+    let password = req.password.clone();
+    let valid = web::block(move || match hash {
+        Some(hash) => verify_password(&password, &hash),
+        None => {
+            verify_password(&password, dummy_hash());
+            false
+        }
+    })
+    .await?;
+    let (Some(user), true) = (user, valid) else {
+        return Ok(unauthorized("Invalid username or password"));
+    };
+
+    let id: Uuid = user.get("id");
+    let user_name: Option<String> = user.get("user_name");
+    let status: Option<String> = user.get("status");
+    if status.as_deref().is_some_and(|s| s.eq_ignore_ascii_case("inactive")) {
+        return Ok(unauthorized("Account is inactive"));
+    }
+
+    let roles = match user_roles(&data.db, id).await {
+        Ok(roles) => roles,
+        Err(e) => {
+            println!("Failed to load roles for {}: {}", id, e);
+            return Ok(HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "error": "Login is unavailable"
+            })));
+        }
+    };
+
+    let now = Utc::now();
+    let expires_at = now + Duration::hours(data.jwt.expiry_hours);
+    let claims = Claims {
+        sub: id,
+        name: user_name.unwrap_or_default(),
+        roles,
+        iat: now.timestamp(),
+        exp: expires_at.timestamp(),
+    };
+
+    match jsonwebtoken::encode(&Header::default(), &claims, &data.jwt.encoding) {
+        Ok(token) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "token": token,
+            "token_type": "Bearer",
+            "expires_at": expires_at,
+            "user": {
+                "id": claims.sub,
+                "user_name": claims.name,
+                "roles": claims.roles
+            }
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "success": false,
+            "error": format!("Failed to issue token: {}", e)
+        }))),
+    }
+}
+
+// Return the claims of the current token
+pub async fn current_user(claims: Claims) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": {
+            "id": claims.sub,
+            "user_name": claims.name,
+            "roles": claims.roles,
+            "expires_at": chrono::DateTime::from_timestamp(claims.exp, 0)
+        }
+    })))
+}
+
+/// Middleware: reject requests without a valid `Authorization: Bearer` token
+/// and make the token's `Claims` available to handlers
+pub async fn require_auth(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let Some(state) = req.app_data::<web::Data<Arc<ApiState>>>().cloned() else {
+        return Err(ErrorUnauthorized("Authentication is not configured"));
+    };
+
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+
+    let Some(token) = token else {
+        return Ok(req.into_response(unauthorized("Authentication required")).map_into_right_body());
+    };
+
+    match jsonwebtoken::decode::<Claims>(token, &state.jwt.decoding, &Validation::default()) {
+        Ok(decoded) => {
+            req.extensions_mut().insert(decoded.claims);
+            next.call(req).await.map(ServiceResponse::map_into_left_body)
+        }
+        Err(_) => Ok(req.into_response(unauthorized("Invalid or expired token")).map_into_right_body()),
+    }
+}
+
+/// Middleware: only let through users that currently hold the admin role.
+/// Must run inside `require_auth`.
+pub async fn require_admin(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let Some(state) = req.app_data::<web::Data<Arc<ApiState>>>().cloned() else {
+        return Err(ErrorUnauthorized("Authentication is not configured"));
+    };
+    let Some(user_id) = req.extensions().get::<Claims>().map(|claims| claims.sub) else {
+        return Ok(req.into_response(unauthorized("Authentication required")).map_into_right_body());
+    };
+
+    match is_admin(&state.db, user_id).await {
+        Ok(true) => next.call(req).await.map(ServiceResponse::map_into_left_body),
+        Ok(false) => Ok(req
+            .into_response(HttpResponse::Forbidden().json(json!({
+                "success": false,
+                "error": "Admin role required"
+            })))
+            .map_into_right_body()),
+        Err(e) => {
+            println!("Failed to check roles for {}: {}", user_id, e);
+            Ok(req
+                .into_response(HttpResponse::InternalServerError().json(json!({
+                    "success": false,
+                    "error": "Could not verify permissions"
+                })))
+                .map_into_right_body())
+        }
+    }
+}
+
+/// Read a password from the terminal, or from the first line of stdin when piped
+pub fn read_password() -> anyhow::Result<String> {
+    let password = if std::io::stdin().is_terminal() {
+        dialoguer::Password::new()
+            .with_prompt("Password")
+            .with_confirmation("Confirm password", "Passwords do not match")
+            .interact()?
+    } else {
+        let mut line = String::new();
+        std::io::stdin().lock().read_line(&mut line)?;
+        line.trim_end_matches(['\r', '\n']).to_string()
+    };
+    if password.len() < 8 {
+        bail!("Password must be at least 8 characters");
+    }
+    Ok(password)
+}
+
+/// Create a user with a password, optionally granting the admin role
+pub async fn create_user(
+    pool: &Pool<Postgres>,
+    user_name: &str,
+    email: Option<&str>,
+    admin: bool,
+    password: &str,
+) -> anyhow::Result<()> {
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE lower(user_name) = lower($1))")
+        .bind(user_name)
+        .fetch_one(pool)
+        .await?;
+    if exists {
+        bail!("User '{}' already exists", user_name);
+    }
+
+    let hash = hash_password(password)?;
+    let mut tx = pool.begin().await?;
+    let id: Uuid = sqlx::query_scalar(
+        "INSERT INTO users (id, user_name, email, status, password_hash) VALUES ($1, $2, $3, 'Active', $4) RETURNING id"
+    )
+    .bind(Uuid::new_v4())
+    .bind(user_name)
+    .bind(email)
+    .bind(hash)
+    .fetch_one(&mut *tx)
+    .await
+    .context("Failed to create user")?;
+
+    if admin {
+        let granted = sqlx::query(
+            r#"
+            INSERT INTO users_roles (user_id, role_id)
+            SELECT $1, id FROM roles WHERE lower(name) = $2
+            ORDER BY date_entered
+            LIMIT 1
+            "#
+        )
+        .bind(id)
+        .bind(ADMIN_ROLE)
+        .execute(&mut *tx)
+        .await?;
+        if granted.rows_affected() == 0 {
+            bail!("No '{}' role found. Run 'cargo run -- migrate up' first.", ADMIN_ROLE);
+        }
+    }
+    tx.commit().await?;
+
+    println!("Created user '{}'{}", user_name, if admin { " with the admin role" } else { "" });
+    Ok(())
+}
+
+/// Replace the password of an existing user
+pub async fn set_password(pool: &Pool<Postgres>, user_name: &str, password: &str) -> anyhow::Result<()> {
+    let hash = hash_password(password)?;
+    let updated = sqlx::query("UPDATE users SET password_hash = $1, date_modified = CURRENT_TIMESTAMP WHERE lower(user_name) = lower($2)")
+        .bind(hash)
+        .bind(user_name)
+        .execute(pool)
+        .await?;
+    if updated.rows_affected() == 0 {
+        bail!("User '{}' not found", user_name);
+    }
+    println!("Password updated for '{}'", user_name);
+    Ok(())
+}
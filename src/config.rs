@@ -0,0 +1,530 @@
+// src/config.rs
+//
+// Server configuration, resolved from layered sources where later ones win:
+// built-in defaults < config.toml < .env < environment variables < CLI flags.
+// Everything is validated up front and all problems are reported together,
+// so a bad value fails at startup instead of on first use.
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgConnectOptions;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use url::Url;
+
+use crate::connections::{self, ConnectionSettings};
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// Values from `.env.example` that mean "not configured"
+const PLACEHOLDER_GEMINI_KEYS: &[&str] = &["dummy_key", "get-key-at-aistudio.google.com"];
+const PLACEHOLDER_JWT_SECRET: &str = "replace-with-a-long-random-string";
+const PLACEHOLDER_GOOGLE_SERVICE_KEY: &str = "path/to/service-account-key.json";
+
+const REDACTED: &str = "********";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Config {
+    pub database_url: String,
+    /// None when no real key is configured; the Gemini endpoints then report it
+    pub gemini_api_key: Option<String>,
+    pub server_host: String,
+    pub server_port: u16,
+    /// Spreadsheet used by /api/recommendations
+    pub excel_file_path: String,
+    /// Secret used to sign API tokens. A random one is generated at startup if unset.
+    pub jwt_secret: Option<String>,
+    pub jwt_expiry_hours: i64,
+    /// Origins allowed by CORS, "*" allows any
+    pub allowed_origins: Vec<String>,
+    /// Maximum rows returned by /api/db/query
+    pub query_row_limit: usize,
+    /// statement_timeout applied to /api/db/query
+    pub query_timeout_secs: u64,
+    /// statement_timeout applied to CSV/NDJSON/XLSX exports
+    pub export_timeout_secs: u64,
+    /// Staging directory for files uploaded to /api/import/uploads
+    pub upload_dir: PathBuf,
+    /// Largest accepted upload, in megabytes
+    pub upload_max_mb: u64,
+    /// Uploads are deleted this long after they arrive
+    pub upload_ttl_minutes: u64,
+    /// Site whose project API `sync democracylab` reads
+    pub democracylab_base_url: String,
+    /// Sync DemocracyLab projects this often while the server runs; off when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub democracylab_sync_minutes: Option<u64>,
+    /// Service account key file used to read Google Sheets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub google_service_key: Option<PathBuf>,
+    /// Google Sheets API root, replaced to test against a local stub
+    pub google_sheets_base_url: String,
+    /// Hosts /api/proxy may reach, with their subdomains; empty allows any
+    /// public host
+    pub proxy_allowed_hosts: Vec<String>,
+    /// Largest response /api/proxy passes on, in megabytes
+    pub proxy_max_response_mb: u64,
+    /// Extra named database connections (`[connections.NAME]` in config.toml)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub connections: BTreeMap<String, ConnectionSettings>,
+    /// Config file that was read, if any
+    #[serde(skip)]
+    pub config_file: Option<PathBuf>,
+    /// Problems that do not stop the server, e.g. a missing spreadsheet
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+/// Flags that override every other source
+#[derive(Debug, Default, clap::Args)]
+pub struct CliOverrides {
+    /// Path to the TOML config file [default: config.toml]
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Database URL, overrides DATABASE_URL and the COMMONS_*/DB_* variables
+    #[arg(long, global = true, value_name = "URL")]
+    pub database_url: Option<String>,
+    /// Address the API server listens on
+    #[arg(long, global = true)]
+    pub host: Option<String>,
+    /// Port the API server listens on
+    #[arg(long, global = true)]
+    pub port: Option<u16>,
+}
+
+/// One source of settings. Unset values fall through to the layer below.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigLayer {
+    database_url: Option<String>,
+    gemini_api_key: Option<String>,
+    server_host: Option<String>,
+    server_port: Option<u16>,
+    excel_file_path: Option<String>,
+    jwt_secret: Option<String>,
+    jwt_expiry_hours: Option<i64>,
+    allowed_origins: Option<Vec<String>>,
+    query_row_limit: Option<usize>,
+    query_timeout_secs: Option<u64>,
+    export_timeout_secs: Option<u64>,
+    upload_dir: Option<PathBuf>,
+    upload_max_mb: Option<u64>,
+    upload_ttl_minutes: Option<u64>,
+    democracylab_base_url: Option<String>,
+    democracylab_sync_minutes: Option<u64>,
+    google_service_key: Option<PathBuf>,
+    google_sheets_base_url: Option<String>,
+    proxy_allowed_hosts: Option<Vec<String>>,
+    proxy_max_response_mb: Option<u64>,
+    #[serde(default)]
+    connections: BTreeMap<String, ConnectionSettings>,
+}
+
+impl ConfigLayer {
+    /// Values from `top` win over values in `self`
+    fn overlay(self, top: ConfigLayer) -> ConfigLayer {
+        let mut connections = self.connections;
+        connections.extend(top.connections);
+        ConfigLayer {
+            database_url: top.database_url.or(self.database_url),
+            gemini_api_key: top.gemini_api_key.or(self.gemini_api_key),
+            server_host: top.server_host.or(self.server_host),
+            server_port: top.server_port.or(self.server_port),
+            excel_file_path: top.excel_file_path.or(self.excel_file_path),
+            jwt_secret: top.jwt_secret.or(self.jwt_secret),
+            jwt_expiry_hours: top.jwt_expiry_hours.or(self.jwt_expiry_hours),
+            allowed_origins: top.allowed_origins.or(self.allowed_origins),
+            query_row_limit: top.query_row_limit.or(self.query_row_limit),
+            query_timeout_secs: top.query_timeout_secs.or(self.query_timeout_secs),
+            export_timeout_secs: top.export_timeout_secs.or(self.export_timeout_secs),
+            upload_dir: top.upload_dir.or(self.upload_dir),
+            upload_max_mb: top.upload_max_mb.or(self.upload_max_mb),
+            upload_ttl_minutes: top.upload_ttl_minutes.or(self.upload_ttl_minutes),
+            democracylab_base_url: top.democracylab_base_url.or(self.democracylab_base_url),
+            democracylab_sync_minutes: top.democracylab_sync_minutes.or(self.democracylab_sync_minutes),
+            google_service_key: top.google_service_key.or(self.google_service_key),
+            google_sheets_base_url: top.google_sheets_base_url.or(self.google_sheets_base_url),
+            proxy_allowed_hosts: top.proxy_allowed_hosts.or(self.proxy_allowed_hosts),
+            proxy_max_response_mb: top.proxy_max_response_mb.or(self.proxy_max_response_mb),
+            connections,
+        }
+    }
+
+    /// Environment variables, including those loaded from .env
+    fn from_env(errors: &mut Vec<String>) -> ConfigLayer {
+        ConfigLayer {
+            database_url: database_url_from_env(errors),
+            gemini_api_key: env_string("GEMINI_API_KEY"),
+            server_host: env_string("SERVER_HOST"),
+            server_port: env_parse("SERVER_PORT", "a port number", errors),
+            excel_file_path: env_string("EXCEL_FILE_PATH").or_else(|| env_string("PROJECTS_FILE_PATH")),
+            jwt_secret: env_string("JWT_SECRET"),
+            jwt_expiry_hours: env_parse("JWT_EXPIRY_HOURS", "a whole number of hours", errors),
+            allowed_origins: env_string("ALLOWED_ORIGINS").map(|origins| {
+                origins.split(',').map(|o| o.trim().to_string()).filter(|o| !o.is_empty()).collect()
+            }),
+            query_row_limit: env_parse("QUERY_ROW_LIMIT", "a whole number", errors),
+            query_timeout_secs: env_parse("QUERY_TIMEOUT_SECS", "a whole number of seconds", errors),
+            export_timeout_secs: env_parse("EXPORT_TIMEOUT_SECS", "a whole number of seconds", errors),
+            upload_dir: env_string("UPLOAD_DIR").map(PathBuf::from),
+            upload_max_mb: env_parse("UPLOAD_MAX_MB", "a whole number of megabytes", errors),
+            upload_ttl_minutes: env_parse("UPLOAD_TTL_MINUTES", "a whole number of minutes", errors),
+            democracylab_base_url: env_string("DEMOCRACYLAB_BASE_URL"),
+            democracylab_sync_minutes: env_parse("DEMOCRACYLAB_SYNC_MINUTES", "a whole number of minutes", errors),
+            google_service_key: env_string("GOOGLE_SERVICE_KEY").map(PathBuf::from),
+            google_sheets_base_url: env_string("GOOGLE_SHEETS_BASE_URL"),
+            proxy_allowed_hosts: env_string("PROXY_ALLOWED_HOSTS").map(|hosts| {
+                hosts.split(',').map(|h| h.trim().to_string()).filter(|h| !h.is_empty()).collect()
+            }),
+            proxy_max_response_mb: env_parse("PROXY_MAX_RESPONSE_MB", "a whole number of megabytes", errors),
+            connections: BTreeMap::new(),
+        }
+    }
+
+    fn from_cli(cli: &CliOverrides) -> ConfigLayer {
+        ConfigLayer {
+            database_url: cli.database_url.clone(),
+            server_host: cli.host.clone(),
+            server_port: cli.port,
+            ..Default::default()
+        }
+    }
+}
+
+/// An environment variable, treating empty values as unset
+fn env_string(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|value| !value.trim().is_empty())
+}
+
+fn env_parse<T: FromStr>(key: &str, expected: &str, errors: &mut Vec<String>) -> Option<T> {
+    let value = env_string(key)?;
+    match value.trim().parse() {
+        Ok(parsed) => Some(parsed),
+        Err(_) => {
+            errors.push(format!("{} must be {}, got '{}'", key, expected, value));
+            None
+        }
+    }
+}
+
+/// `{PREFIX}_HOST/_PORT/_NAME/_USER/_PASSWORD` as a URL, when all are set
+fn component_url(prefix: &str, errors: &mut Vec<String>) -> Option<String> {
+    let var = |suffix: &str| env_string(&format!("{}_{}", prefix, suffix));
+    let (host, port, name, user, password) = (var("HOST")?, var("PORT")?, var("NAME")?, var("USER")?, var("PASSWORD")?);
+    let ssl_mode = var("SSL_MODE").unwrap_or_else(|| "require".to_string());
+
+    let Ok(port) = port.parse::<u16>() else {
+        errors.push(format!("{}_PORT must be a port number, got '{}'", prefix, port));
+        return None;
+    };
+    // Built with Url so special characters in the credentials are escaped
+    let mut url = match Url::parse(&format!("postgres://{}:{}/", host, port)) {
+        Ok(url) => url,
+        Err(e) => {
+            errors.push(format!("{}_HOST is not a valid host name: {}", prefix, e));
+            return None;
+        }
+    };
+    url.set_path(&name);
+    let _ = url.set_username(&user);
+    let _ = url.set_password(Some(&password));
+    url.query_pairs_mut().append_pair("sslmode", &ssl_mode);
+    Some(url.to_string())
+}
+
+/// COMMONS_* first, then DB_*, then DATABASE_URL
+fn database_url_from_env(errors: &mut Vec<String>) -> Option<String> {
+    component_url("COMMONS", errors)
+        .or_else(|| component_url("DB", errors))
+        .or_else(|| env_string("DATABASE_URL"))
+}
+
+fn default_server_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_server_port() -> u16 {
+    8081
+}
+
+fn default_excel_file_path() -> String {
+    "preferences/projects/DFC-ActiveProjects.xlsx".to_string()
+}
+
+fn default_jwt_expiry_hours() -> i64 {
+    24
+}
+
+fn default_query_row_limit() -> usize {
+    1000
+}
+
+fn default_query_timeout_secs() -> u64 {
+    30
+}
+
+fn default_export_timeout_secs() -> u64 {
+    600
+}
+
+fn default_upload_dir() -> PathBuf {
+    std::env::temp_dir().join("membercommons-uploads")
+}
+
+fn default_upload_max_mb() -> u64 {
+    25
+}
+
+fn default_upload_ttl_minutes() -> u64 {
+    60
+}
+
+fn default_democracylab_base_url() -> String {
+    "https://www.democracylab.org".to_string()
+}
+
+fn default_google_sheets_base_url() -> String {
+    "https://sheets.googleapis.com/".to_string()
+}
+
+fn default_proxy_max_response_mb() -> u64 {
+    5
+}
+
+fn default_allowed_origins() -> Vec<String> {
+    vec![
+        "http://localhost:8887".to_string(),
+        "http://127.0.0.1:8887".to_string(),
+        "http://localhost:3000".to_string(),
+    ]
+}
+
+fn check_origin(origin: &str) -> Result<(), String> {
+    if origin == "*" {
+        return Ok(());
+    }
+    let url = Url::parse(origin).map_err(|e| format!("'{}' is not a valid origin: {}", origin, e))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+        return Err(format!("'{}' must be an http(s) origin such as http://localhost:8887", origin));
+    }
+    if url.path() != "/" || url.query().is_some() || origin.ends_with('/') {
+        return Err(format!("'{}' must not include a path or trailing slash", origin));
+    }
+    Ok(())
+}
+
+/// A bare host name such as `api.example.org`, lower-cased
+fn check_proxy_host(host: &str) -> Result<String, String> {
+    let host = host.trim().trim_start_matches("*.").to_lowercase();
+    match Url::parse(&format!("http://{}/", host)) {
+        Ok(url) if url.host_str() == Some(host.as_str()) && url.port().is_none() => Ok(host),
+        _ => Err(format!("'{}' must be a host name without scheme, port or path", host)),
+    }
+}
+
+fn require_positive<T: PartialOrd + Default + std::fmt::Display>(name: &str, value: T, errors: &mut Vec<String>) -> T {
+    if value <= T::default() {
+        errors.push(format!("{} must be greater than zero, got {}", name, value));
+    }
+    value
+}
+
+fn redact_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(mut parsed) => {
+            if parsed.password().is_some() {
+                let _ = parsed.set_password(Some(REDACTED));
+            }
+            parsed.to_string()
+        }
+        Err(_) => REDACTED.to_string(),
+    }
+}
+
+impl Config {
+    /// Resolve every layer and validate the result
+    pub fn load(cli: &CliOverrides) -> anyhow::Result<Self> {
+        // .env never overrides variables that are already set
+        dotenv::dotenv().ok();
+
+        let mut errors = Vec::new();
+        let path = cli.config.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_FILE));
+        let (file_layer, config_file) = match std::fs::read_to_string(&path) {
+            Ok(text) => match toml::from_str::<ConfigLayer>(&text) {
+                Ok(layer) => (layer, Some(path)),
+                Err(e) => {
+                    let line = e.span().map(|span| text[..span.start].lines().count().max(1)).unwrap_or(1);
+                    errors.push(format!("{} line {}: {}", path.display(), line, e.message().trim()));
+                    (ConfigLayer::default(), Some(path))
+                }
+            },
+            // Only a file named with --config has to exist
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && cli.config.is_none() => (ConfigLayer::default(), None),
+            Err(e) => {
+                errors.push(format!("Cannot read {}: {}", path.display(), e));
+                (ConfigLayer::default(), None)
+            }
+        };
+
+        let layer = file_layer
+            .overlay(ConfigLayer::from_env(&mut errors))
+            .overlay(ConfigLayer::from_cli(cli));
+        let config = Self::resolve(layer, config_file, &mut errors);
+
+        if !errors.is_empty() {
+            bail!("Invalid configuration:\n  - {}", errors.join("\n  - "));
+        }
+        Ok(config)
+    }
+
+    fn resolve(layer: ConfigLayer, config_file: Option<PathBuf>, errors: &mut Vec<String>) -> Self {
+        let mut warnings = Vec::new();
+
+        let database_url = match layer.database_url {
+            Some(url) => {
+                if let Err(e) = PgConnectOptions::from_str(&url) {
+                    errors.push(format!("database_url is not a valid Postgres URL: {}", e));
+                }
+                url
+            }
+            None => {
+                errors.push(
+                    "No database configured. Set DATABASE_URL, the COMMONS_* or DB_* variables, database_url in config.toml or --database-url".to_string()
+                );
+                String::new()
+            }
+        };
+
+        let gemini_api_key = layer
+            .gemini_api_key
+            .filter(|key| !PLACEHOLDER_GEMINI_KEYS.contains(&key.as_str()));
+        if gemini_api_key.is_none() {
+            warnings.push("GEMINI_API_KEY is not set, Gemini analysis is disabled".to_string());
+        }
+
+        let jwt_secret = layer.jwt_secret;
+        match jwt_secret.as_deref() {
+            Some(PLACEHOLDER_JWT_SECRET) => errors.push("JWT_SECRET still has the placeholder value from .env.example".to_string()),
+            Some(secret) if secret.len() < 32 => warnings.push("JWT_SECRET is shorter than 32 characters".to_string()),
+            Some(_) => {}
+            None => warnings.push("JWT_SECRET is not set, a random secret is used and tokens end with each restart".to_string()),
+        }
+
+        let server_port = layer.server_port.unwrap_or_else(default_server_port);
+        if server_port == 0 {
+            errors.push("server_port must not be 0".to_string());
+        }
+
+        let excel_file_path = layer.excel_file_path.unwrap_or_else(default_excel_file_path);
+        if !std::path::Path::new(&excel_file_path).exists() {
+            warnings.push(format!("Projects spreadsheet {} not found, /api/recommendations will fail", excel_file_path));
+        }
+
+        let allowed_origins = layer.allowed_origins.unwrap_or_else(default_allowed_origins);
+        for origin in &allowed_origins {
+            if let Err(e) = check_origin(origin) {
+                errors.push(format!("allowed_origins: {}", e));
+            }
+        }
+
+        let democracylab_base_url = layer.democracylab_base_url.unwrap_or_else(default_democracylab_base_url);
+        match Url::parse(&democracylab_base_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") && url.host().is_some() => {}
+            _ => errors.push(format!("democracylab_base_url must be an http(s) URL, got '{}'", democracylab_base_url)),
+        }
+        // 0 turns the schedule off like leaving it unset
+        let democracylab_sync_minutes = layer.democracylab_sync_minutes.filter(|minutes| *minutes > 0);
+
+        let google_service_key = layer
+            .google_service_key
+            .filter(|path| path.as_os_str() != PLACEHOLDER_GOOGLE_SERVICE_KEY);
+        match &google_service_key {
+            Some(path) if !path.exists() => {
+                warnings.push(format!("Google service key {} not found, Google Sheets imports will fail", path.display()));
+            }
+            Some(_) => {}
+            None => warnings.push("GOOGLE_SERVICE_KEY is not set, Google Sheets imports are disabled".to_string()),
+        }
+        let google_sheets_base_url = layer.google_sheets_base_url.unwrap_or_else(default_google_sheets_base_url);
+        match Url::parse(&google_sheets_base_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") && url.host().is_some() => {}
+            _ => errors.push(format!("google_sheets_base_url must be an http(s) URL, got '{}'", google_sheets_base_url)),
+        }
+
+        let mut proxy_allowed_hosts = Vec::new();
+        for host in layer.proxy_allowed_hosts.unwrap_or_default() {
+            match check_proxy_host(&host) {
+                Ok(host) => proxy_allowed_hosts.push(host),
+                Err(e) => errors.push(format!("proxy_allowed_hosts: {}", e)),
+            }
+        }
+        if proxy_allowed_hosts.is_empty() {
+            warnings.push("PROXY_ALLOWED_HOSTS is not set, /api/proxy reaches any public host".to_string());
+        }
+
+        for (name, settings) in &layer.connections {
+            if let Err(e) = connections::validate_settings(settings) {
+                errors.push(format!("connections.{}: {}", name, e));
+            }
+        }
+
+        Config {
+            database_url,
+            gemini_api_key,
+            server_host: layer.server_host.unwrap_or_else(default_server_host),
+            server_port,
+            excel_file_path,
+            jwt_secret,
+            jwt_expiry_hours: require_positive("jwt_expiry_hours", layer.jwt_expiry_hours.unwrap_or_else(default_jwt_expiry_hours), errors),
+            allowed_origins,
+            query_row_limit: require_positive("query_row_limit", layer.query_row_limit.unwrap_or_else(default_query_row_limit), errors),
+            query_timeout_secs: require_positive("query_timeout_secs", layer.query_timeout_secs.unwrap_or_else(default_query_timeout_secs), errors),
+            export_timeout_secs: require_positive("export_timeout_secs", layer.export_timeout_secs.unwrap_or_else(default_export_timeout_secs), errors),
+            upload_dir: layer.upload_dir.unwrap_or_else(default_upload_dir),
+            upload_max_mb: require_positive("upload_max_mb", layer.upload_max_mb.unwrap_or_else(default_upload_max_mb), errors),
+            upload_ttl_minutes: require_positive("upload_ttl_minutes", layer.upload_ttl_minutes.unwrap_or_else(default_upload_ttl_minutes), errors),
+            democracylab_base_url,
+            democracylab_sync_minutes,
+            google_service_key,
+            google_sheets_base_url,
+            proxy_allowed_hosts,
+            proxy_max_response_mb: require_positive("proxy_max_response_mb", layer.proxy_max_response_mb.unwrap_or_else(default_proxy_max_response_mb), errors),
+            connections: layer.connections,
+            config_file,
+            warnings,
+        }
+    }
+
+    /// The resolved configuration as TOML, with passwords, keys and secrets masked
+    pub fn redacted_toml(&self) -> anyhow::Result<String> {
+        let mut config = self.clone();
+        config.database_url = redact_url(&config.database_url);
+        config.gemini_api_key = config.gemini_api_key.map(|_| REDACTED.to_string());
+        config.jwt_secret = config.jwt_secret.map(|_| REDACTED.to_string());
+        for settings in config.connections.values_mut() {
+            settings.url = settings.url.as_deref().map(redact_url);
+            settings.password = settings.password.as_ref().map(|_| REDACTED.to_string());
+        }
+        Ok(toml::to_string_pretty(&config)?)
+    }
+}
+
+/// `config check`: print the resolved configuration and any warnings
+pub fn print_check(config: &Config) -> anyhow::Result<()> {
+    match &config.config_file {
+        Some(path) => println!("# Config file: {}", path.display()),
+        None => println!("# No config file, using defaults and environment variables"),
+    }
+    println!("# Secrets are redacted\n");
+    println!("{}", config.redacted_toml()?);
+
+    if config.warnings.is_empty() {
+        println!("Configuration OK");
+    } else {
+        println!("Configuration OK with {} warning(s):", config.warnings.len());
+        for warning in &config.warnings {
+            println!("  - {}", warning);
+        }
+    }
+    Ok(())
+}
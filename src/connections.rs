@@ -0,0 +1,410 @@
+// src/connections.rs
+//
+// Named database connections that the admin endpoints can target with
+// `?connection=NAME`. Connections are read once at startup from
+// `{PREFIX}_HOST/_PORT/_NAME/_USER/_PASSWORD/_SSL_MODE` variables, from any
+// `*_URL` variable holding a Postgres URL and from `[connections.NAME]`
+// tables in config.toml. Each one gets a small pool that is created on first
+// use and then reused, and the result of the latest check is kept for
+// `/api/connections`.
+use actix_web::{web, HttpResponse, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions, PgSslMode};
+use sqlx::{Pool, Postgres};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::ApiState;
+
+/// Component prefixes listed first, in this order
+const KNOWN_PREFIXES: &[&str] = &["COMMONS", "EXIOBASE", "DB"];
+
+/// Pool size used unless `{PREFIX}_MAX_CONNECTIONS` or `max_connections` says otherwise
+const DEFAULT_MAX_CONNECTIONS: u32 = 3;
+
+/// How long a request waits for a connection before giving up
+const ACQUIRE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Idle connections are closed after this long
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A connection defined in config.toml, either as a URL or as components
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectionSettings {
+    pub url: Option<String>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub name: Option<String>,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub ssl_mode: Option<String>,
+    pub display_name: Option<String>,
+    pub max_connections: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Health {
+    /// "unknown" until the connection has been checked
+    pub status: &'static str,
+    pub last_checked: Option<DateTime<Utc>>,
+    pub latency_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health {
+            status: "unknown",
+            last_checked: None,
+            latency_ms: None,
+            last_error: None,
+            consecutive_failures: 0,
+        }
+    }
+}
+
+/// Display details of a connection. Never includes the password.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionSummary {
+    pub server: String,
+    pub database: String,
+    pub username: String,
+    pub port: u16,
+    pub ssl: bool,
+}
+
+pub struct Connection {
+    pub name: String,
+    pub display_name: String,
+    /// Where the definition came from: "env", "env_url" or "config.toml"
+    pub source: &'static str,
+    /// Whether this is the server's main database
+    pub is_default: bool,
+    options: PgConnectOptions,
+    max_connections: u32,
+    pool: OnceLock<Pool<Postgres>>,
+    health: Mutex<Health>,
+}
+
+impl Connection {
+    fn new(name: String, display_name: String, source: &'static str, options: PgConnectOptions, max_connections: u32) -> Self {
+        Connection {
+            name,
+            display_name,
+            source,
+            is_default: false,
+            options,
+            max_connections,
+            pool: OnceLock::new(),
+            health: Mutex::new(Health::default()),
+        }
+    }
+
+    /// The shared pool, created (without connecting) on first use
+    pub fn pool(&self) -> Pool<Postgres> {
+        self.pool
+            .get_or_init(|| {
+                println!("Opening connection pool for {}", self.name);
+                PgPoolOptions::new()
+                    .max_connections(self.max_connections)
+                    .acquire_timeout(ACQUIRE_TIMEOUT)
+                    .idle_timeout(IDLE_TIMEOUT)
+                    .connect_lazy_with(self.options.clone())
+            })
+            .clone()
+    }
+
+    pub fn summary(&self) -> ConnectionSummary {
+        let port = self.options.get_port();
+        ConnectionSummary {
+            server: format!("{}:{}", self.options.get_host(), port),
+            database: self.options.get_database().unwrap_or_default().to_string(),
+            username: self.options.get_username().to_string(),
+            port,
+            ssl: matches!(
+                self.options.get_ssl_mode(),
+                PgSslMode::Require | PgSslMode::VerifyCa | PgSslMode::VerifyFull
+            ),
+        }
+    }
+
+    pub fn health(&self) -> Health {
+        self.health.lock().unwrap().clone()
+    }
+
+    /// Run `SELECT 1` and record the outcome
+    pub async fn check(&self) -> Health {
+        let started = Instant::now();
+        let result = sqlx::query("SELECT 1").execute(&self.pool()).await;
+
+        let mut health = self.health.lock().unwrap();
+        health.last_checked = Some(Utc::now());
+        match result {
+            Ok(_) => {
+                health.status = "healthy";
+                health.latency_ms = Some(started.elapsed().as_millis() as u64);
+                health.last_error = None;
+                health.consecutive_failures = 0;
+            }
+            Err(e) => {
+                health.status = "unhealthy";
+                health.latency_ms = None;
+                health.last_error = Some(e.to_string());
+                health.consecutive_failures += 1;
+            }
+        }
+        health.clone()
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let pool = self.pool.get();
+        json!({
+            "name": self.name,
+            "display_name": self.display_name,
+            "source": self.source,
+            "default": self.is_default,
+            "config": self.summary(),
+            "pool": {
+                "open": pool.is_some(),
+                "size": pool.map(|p| p.size()).unwrap_or(0),
+                "idle": pool.map(|p| p.num_idle()).unwrap_or(0),
+                "max_connections": pool.map(|p| p.options().get_max_connections()).unwrap_or(self.max_connections)
+            },
+            "health": self.health()
+        })
+    }
+}
+
+pub struct ConnectionRegistry {
+    connections: Vec<Arc<Connection>>,
+}
+
+fn display_name_for_prefix(prefix: &str) -> String {
+    match prefix {
+        "COMMONS" => "MemberCommons Database (Default)".to_string(),
+        "EXIOBASE" => "EXIOBASE Database".to_string(),
+        _ => format!("{} Database", prefix.replace('_', " ")),
+    }
+}
+
+fn display_name_for_url_key(key: &str) -> String {
+    match key {
+        "DATABASE_URL" => "MemberCommons Database (Default)".to_string(),
+        "EXIOBASE_URL" => "EXIOBASE Database".to_string(),
+        _ => {
+            let name = key.trim_end_matches("_URL").replace('_', " ");
+            let words: Vec<String> = name
+                .split_whitespace()
+                .map(|word| {
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+                        None => String::new(),
+                    }
+                })
+                .collect();
+            format!("{} Database", words.join(" "))
+        }
+    }
+}
+
+fn is_postgres_url(value: &str) -> bool {
+    value.starts_with("postgres://") || value.starts_with("postgresql://")
+}
+
+fn max_connections_for(prefix: &str, env: &HashMap<String, String>) -> u32 {
+    env.get(&format!("{}_MAX_CONNECTIONS", prefix))
+        .and_then(|n| n.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_MAX_CONNECTIONS)
+}
+
+fn component_options(
+    host: &str,
+    port: &str,
+    name: &str,
+    user: &str,
+    password: &str,
+    ssl_mode: Option<&str>,
+) -> Result<PgConnectOptions, String> {
+    let port: u16 = port.parse().map_err(|_| format!("invalid port '{}'", port))?;
+    let ssl_mode = PgSslMode::from_str(ssl_mode.unwrap_or("require")).map_err(|e| e.to_string())?;
+    Ok(PgConnectOptions::new()
+        .host(host)
+        .port(port)
+        .database(name)
+        .username(user)
+        .password(password)
+        .ssl_mode(ssl_mode))
+}
+
+/// Options for a `{PREFIX}_*` group when all five required variables are set
+fn options_from_env(prefix: &str, env: &HashMap<String, String>) -> Option<Result<PgConnectOptions, String>> {
+    let var = |suffix: &str| env.get(&format!("{}_{}", prefix, suffix));
+    let (host, port, name, user, password) = (var("HOST")?, var("PORT")?, var("NAME")?, var("USER")?, var("PASSWORD")?);
+    Some(component_options(host, port, name, user, password, var("SSL_MODE").map(String::as_str)))
+}
+
+fn options_from_settings(settings: &ConnectionSettings) -> Result<PgConnectOptions, String> {
+    if let Some(url) = &settings.url {
+        return PgConnectOptions::from_str(url).map_err(|e| e.to_string());
+    }
+    match (&settings.host, &settings.name, &settings.user) {
+        (Some(host), Some(name), Some(user)) => component_options(
+            host,
+            &settings.port.unwrap_or(5432).to_string(),
+            name,
+            user,
+            settings.password.as_deref().unwrap_or_default(),
+            settings.ssl_mode.as_deref(),
+        ),
+        _ => Err("expected either url or host, name and user".to_string()),
+    }
+}
+
+/// Check a config.toml connection without opening it
+pub fn validate_settings(settings: &ConnectionSettings) -> Result<(), String> {
+    options_from_settings(settings).map(|_| ())
+}
+
+fn same_database(a: &PgConnectOptions, b: &PgConnectOptions) -> bool {
+    a.get_host() == b.get_host()
+        && a.get_port() == b.get_port()
+        && a.get_database() == b.get_database()
+        && a.get_username() == b.get_username()
+}
+
+impl ConnectionRegistry {
+    /// Collect connections from the environment and config.toml. Entries for
+    /// the main database share `default_pool` instead of opening another one.
+    pub fn load(
+        settings: &BTreeMap<String, ConnectionSettings>,
+        database_url: &str,
+        default_pool: &Pool<Postgres>,
+    ) -> Self {
+        let env: HashMap<String, String> = std::env::vars().collect();
+        let mut connections: Vec<Connection> = Vec::new();
+        let mut add = |connection: Result<Connection, String>, name: &str| match connection {
+            Ok(connection) => {
+                connections.retain(|c| c.name != connection.name);
+                connections.push(connection);
+            }
+            Err(e) => println!("Warning: ignoring database connection {}: {}", name, e),
+        };
+
+        // {PREFIX}_HOST groups, known prefixes first
+        let mut prefixes: Vec<String> = KNOWN_PREFIXES.iter().map(|p| p.to_string()).collect();
+        let mut other_prefixes: Vec<String> = env
+            .keys()
+            .filter_map(|key| key.strip_suffix("_HOST"))
+            .filter(|prefix| !KNOWN_PREFIXES.contains(prefix))
+            .map(str::to_string)
+            .collect();
+        other_prefixes.sort();
+        prefixes.extend(other_prefixes);
+        for prefix in &prefixes {
+            if let Some(options) = options_from_env(prefix, &env) {
+                let connection = options.map(|options| {
+                    Connection::new(prefix.clone(), display_name_for_prefix(prefix), "env", options, max_connections_for(prefix, &env))
+                });
+                add(connection, prefix);
+            }
+        }
+
+        // Full URLs, named by their variable
+        let mut url_keys: Vec<&String> = env
+            .iter()
+            .filter(|(key, value)| key.ends_with("_URL") && is_postgres_url(value))
+            .map(|(key, _)| key)
+            .collect();
+        url_keys.sort();
+        for key in url_keys {
+            let connection = PgConnectOptions::from_str(&env[key]).map_err(|e| e.to_string()).map(|options| {
+                let prefix = key.trim_end_matches("_URL");
+                Connection::new(key.clone(), display_name_for_url_key(key), "env_url", options, max_connections_for(prefix, &env))
+            });
+            add(connection, key);
+        }
+
+        // config.toml entries override environment ones of the same name
+        for (name, settings) in settings {
+            let connection = options_from_settings(settings).map(|options| {
+                let display_name = settings.display_name.clone().unwrap_or_else(|| display_name_for_prefix(name));
+                let max_connections = settings.max_connections.filter(|n| *n > 0).unwrap_or(DEFAULT_MAX_CONNECTIONS);
+                Connection::new(name.clone(), display_name, "config.toml", options, max_connections)
+            });
+            add(connection, name);
+        }
+
+        let default_options = PgConnectOptions::from_str(database_url).ok();
+        for connection in &mut connections {
+            if default_options.as_ref().is_some_and(|default| same_database(default, &connection.options)) {
+                connection.is_default = true;
+                let _ = connection.pool.set(default_pool.clone());
+            }
+        }
+
+        ConnectionRegistry {
+            connections: connections.into_iter().map(Arc::new).collect(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<Connection>> {
+        self.connections.iter().find(|c| c.name == name).cloned()
+    }
+
+    pub fn all(&self) -> &[Arc<Connection>] {
+        &self.connections
+    }
+
+    /// Pool for `?connection=NAME`, or the main pool when no name is given
+    pub fn pool_for(&self, name: Option<&String>, default_pool: &Pool<Postgres>) -> Result<Pool<Postgres>, String> {
+        match name {
+            None => Ok(default_pool.clone()),
+            Some(name) => self
+                .get(name)
+                .map(|connection| connection.pool())
+                .ok_or_else(|| format!("Connection '{}' is not configured", name)),
+        }
+    }
+}
+
+// List configured connections with pool usage and health.
+// `?check=true` checks every connection first.
+pub async fn list_connections(
+    data: web::Data<Arc<ApiState>>,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    if query.get("check").is_some_and(|v| v == "true" || v == "1") {
+        futures_util::future::join_all(data.connections.all().iter().map(|c| c.check())).await;
+    }
+
+    let connections: Vec<serde_json::Value> = data.connections.all().iter().map(|c| c.to_json()).collect();
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": connections
+    })))
+}
+
+// Check one connection now
+pub async fn check_connection(data: web::Data<Arc<ApiState>>, path: web::Path<String>) -> Result<HttpResponse> {
+    let name = path.into_inner();
+    let Some(connection) = data.connections.get(&name) else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": format!("Connection '{}' is not configured", name)
+        })));
+    };
+
+    let health = connection.check().await;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": health.status == "healthy",
+        "data": connection.to_json()
+    })))
+}
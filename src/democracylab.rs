@@ -0,0 +1,531 @@
+// src/democracylab.rs
+//
+// DemocracyLab projects. Projects arrive either pushed by the browser to
+// `/api/import/democracylab` or pulled by the server, which pages through the
+// DemocracyLab project API newest-first, on a schedule or with
+// `sync democracylab`. Each project keeps its DemocracyLab ID in
+// `source_id`, so a sync updates the projects it created before, and only
+// projects changed since the last sync are fetched unless a full sync is
+// asked for.
+use anyhow::{bail, Context};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::entities;
+use crate::import_batch::{ConflictStrategy, DuplicateCheck, ImportOptions, ImportRow, RowRecord};
+use crate::import_jobs::NewJob;
+use crate::import_tasks::ImportRun;
+use crate::ApiState;
+
+/// `projects.source` of projects from DemocracyLab
+pub const SOURCE: &str = "democracylab";
+
+/// `created_by` of records written by scheduled and CLI syncs
+pub const SYNC_USER: &str = "democracylab-sync";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A tag such as a skill or organisation, as a name or as DemocracyLab's
+/// tag object
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Tag {
+    Name(String),
+    Object {
+        display_name: Option<String>,
+        tag_name: Option<String>,
+    },
+}
+
+impl Tag {
+    fn name(&self) -> Option<&str> {
+        match self {
+            Tag::Name(name) => Some(name),
+            Tag::Object { display_name, tag_name } => display_name.as_deref().or(tag_name.as_deref()),
+        }
+    }
+}
+
+/// An open role on a project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Position {
+    #[serde(rename = "roleTag")]
+    pub role: Option<Tag>,
+}
+
+/// A project as the DemocracyLab API lists it. Only `project_name` is required.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DemocracyLabProject {
+    #[serde(rename = "project_id", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    #[serde(rename = "project_name")]
+    pub name: String,
+    #[serde(rename = "project_description")]
+    pub description: Option<String>,
+    #[serde(rename = "project_url")]
+    pub url: Option<String>,
+    #[serde(rename = "project_location", default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(rename = "project_city", default, skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    #[serde(rename = "project_state", default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(rename = "project_country", default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(rename = "project_organization", default, skip_serializing_if = "Vec::is_empty")]
+    pub organizations: Vec<Tag>,
+    #[serde(rename = "project_technologies", default, skip_serializing_if = "Vec::is_empty")]
+    pub technologies: Vec<Tag>,
+    #[serde(rename = "project_positions", default, skip_serializing_if = "Vec::is_empty")]
+    pub positions: Vec<Position>,
+    #[serde(rename = "project_date_modified", default, skip_serializing_if = "Option::is_none")]
+    pub date_modified: Option<String>,
+}
+
+fn join_names<'a>(tags: impl Iterator<Item = &'a Tag>) -> Option<String> {
+    let mut names: Vec<&str> = Vec::new();
+    for name in tags.filter_map(Tag::name).map(str::trim).filter(|name| !name.is_empty()) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    (!names.is_empty()).then(|| names.join(", "))
+}
+
+impl DemocracyLabProject {
+    /// DemocracyLab's ID, which is a number in its API
+    pub fn source_id(&self) -> Option<String> {
+        match self.id.as_ref()? {
+            Value::String(id) if !id.trim().is_empty() => Some(id.trim().to_string()),
+            Value::Number(id) => Some(id.to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn modified(&self) -> Option<DateTime<Utc>> {
+        self.date_modified
+            .as_deref()
+            .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+            .map(|date| date.with_timezone(&Utc))
+    }
+
+    fn location(&self) -> Option<String> {
+        self.location.clone().filter(|l| !l.trim().is_empty()).or_else(|| {
+            let parts: Vec<&str> = [&self.city, &self.state, &self.country]
+                .into_iter()
+                .flatten()
+                .map(|part| part.trim())
+                .filter(|part| !part.is_empty())
+                .collect();
+            (!parts.is_empty()).then(|| parts.join(", "))
+        })
+    }
+
+    /// Technologies used and roles wanted
+    fn skills(&self) -> Option<String> {
+        join_names(self.technologies.iter().chain(self.positions.iter().filter_map(|p| p.role.as_ref())))
+    }
+
+    /// The project as a `projects` record
+    pub fn to_record(&self) -> Map<String, Value> {
+        let mut record = Map::new();
+        record.insert("name".to_string(), json!(self.name));
+        if let Some(description) = self.description.as_deref().filter(|d| !d.trim().is_empty()) {
+            record.insert("description".to_string(), json!(description));
+        }
+        record.insert("status".to_string(), json!("Active")); // Default status
+        let attributes = [
+            ("profile_url", self.url.clone()),
+            ("location", self.location()),
+            ("country", self.country.clone()),
+            ("skills", self.skills()),
+            ("organization", join_names(self.organizations.iter())),
+        ];
+        for (column, value) in attributes {
+            if let Some(value) = value.filter(|v| !v.trim().is_empty()) {
+                record.insert(column.to_string(), json!(value));
+            }
+        }
+        if let Some(id) = self.source_id() {
+            record.insert("source".to_string(), json!(SOURCE));
+            record.insert("source_id".to_string(), json!(id));
+            if let Some(modified) = self.modified() {
+                record.insert("source_modified".to_string(), json!(modified.to_rfc3339()));
+            }
+        }
+        record
+    }
+
+    /// The project as an import row, keeping the fields as DemocracyLab gave them
+    pub fn import_row(&self, row_number: usize) -> ImportRow {
+        let source = match serde_json::to_value(self) {
+            Ok(Value::Object(fields)) => fields.into_iter().collect(),
+            _ => Vec::new(),
+        };
+        ImportRow::new(row_number, RowRecord::Record(self.to_record())).with_source(source)
+    }
+}
+
+/// One page of `/api/projects`
+#[derive(Debug, Deserialize)]
+struct ProjectPage {
+    #[serde(default)]
+    projects: Vec<DemocracyLabProject>,
+    #[serde(rename = "numPages")]
+    num_pages: Option<u32>,
+}
+
+/// Reads the DemocracyLab project API at `base_url`, e.g.
+/// `https://www.democracylab.org` or a local mock
+pub struct Client {
+    base_url: String,
+    http: reqwest::Client,
+}
+
+impl Client {
+    pub fn new(base_url: &str) -> anyhow::Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(concat!("MemberCommons/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Client { base_url: base_url.trim_end_matches('/').to_string(), http })
+    }
+
+    /// Page `page` (from 1) of all projects, most recently modified first
+    async fn page(&self, page: u32) -> anyhow::Result<ProjectPage> {
+        let url = format!("{}/api/projects", self.base_url);
+        let response = self
+            .http
+            .get(&url)
+            .query(&[("sortField", "-project_date_modified".to_string()), ("page", page.to_string())])
+            .send()
+            .await
+            .with_context(|| format!("Cannot reach {}", url))?;
+        if !response.status().is_success() {
+            bail!("{} page {} returned {}", url, page, response.status());
+        }
+        response
+            .json::<ProjectPage>()
+            .await
+            .with_context(|| format!("{} page {} is not a DemocracyLab project list", url, page))
+    }
+
+    /// Projects modified after `since`, or all of them. Paging stops at the
+    /// first project that is not newer, or after `max_pages`.
+    pub async fn changed_projects(
+        &self,
+        since: Option<DateTime<Utc>>,
+        max_pages: Option<u32>,
+    ) -> anyhow::Result<Vec<DemocracyLabProject>> {
+        let mut projects = Vec::new();
+        let mut page = 1;
+        loop {
+            let listed = self.page(page).await?;
+            if listed.projects.is_empty() {
+                break;
+            }
+            let mut reached_since = false;
+            for project in listed.projects {
+                match (since, project.modified()) {
+                    (Some(since), Some(modified)) if modified <= since => reached_since = true,
+                    _ => projects.push(project),
+                }
+            }
+            let last_page = listed.num_pages.is_some_and(|pages| page >= pages);
+            if reached_since || last_page || max_pages.is_some_and(|max| page >= max) {
+                break;
+            }
+            page += 1;
+        }
+        Ok(projects)
+    }
+}
+
+/// What a sync is asked to do
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    /// Fetch every project, not only those changed since the last sync
+    pub full: bool,
+    pub dry_run: bool,
+    pub max_pages: Option<u32>,
+}
+
+/// When DemocracyLab last changed any project already synced
+async fn last_modified(pool: &Pool<Postgres>) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    sqlx::query_scalar::<_, Option<DateTime<Utc>>>("SELECT max(source_modified) FROM projects WHERE source = $1")
+        .bind(SOURCE)
+        .fetch_one(pool)
+        .await
+}
+
+/// Projects imported from DemocracyLab before syncs kept their ID have no
+/// `source_id`. Each fetched project that has no synced copy claims the
+/// oldest such project with the same normalised name, so the sync updates it
+/// instead of adding another. Returns the number of projects claimed.
+async fn claim_unsynced(pool: &Pool<Postgres>, projects: &[DemocracyLabProject]) -> Result<u64, sqlx::Error> {
+    let (ids, names): (Vec<String>, Vec<String>) = projects
+        .iter()
+        .filter_map(|project| project.source_id().map(|id| (id, project.name.clone())))
+        .unzip();
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    // One project claimed per name on either side, as the IDs are unique
+    let claimed = sqlx::query(
+        r#"
+        WITH incoming AS (
+            SELECT DISTINCT ON (normalize_name(t.name)) t.source_id, normalize_name(t.name) AS name_key
+            FROM unnest($2::text[], $3::text[]) AS t(source_id, name)
+            WHERE NOT EXISTS (SELECT 1 FROM projects p WHERE p.source = $1 AND p.source_id = t.source_id)
+            ORDER BY normalize_name(t.name), t.source_id
+        ),
+        unsynced AS (
+            SELECT DISTINCT ON (normalize_name(p.name)) p.id, normalize_name(p.name) AS name_key
+            FROM projects p
+            WHERE p.source_id IS NULL
+              AND (p.source IS NULL OR p.source = $1)
+              AND normalize_name(p.name) IN (SELECT name_key FROM incoming)
+            ORDER BY normalize_name(p.name), p.date_entered, p.id
+        )
+        UPDATE projects p SET source = $1, source_id = incoming.source_id
+        FROM unsynced JOIN incoming USING (name_key)
+        WHERE p.id = unsynced.id
+        "#
+    )
+    .bind(SOURCE)
+    .bind(&ids)
+    .bind(&names)
+    .execute(pool)
+    .await?;
+    Ok(claimed.rows_affected())
+}
+
+/// Fetch the projects to sync and turn them into an import, or `None` when
+/// nothing changed. Synced projects are matched on their DemocracyLab ID and
+/// overwritten with DemocracyLab's values; projects imported before syncs
+/// existed are claimed by name first (not in a dry run).
+pub async fn prepare_sync(
+    pool: &Pool<Postgres>,
+    base_url: &str,
+    options: &SyncOptions,
+) -> anyhow::Result<Option<ImportRun>> {
+    let since = if options.full { None } else { last_modified(pool).await? };
+    let projects = Client::new(base_url)?.changed_projects(since, options.max_pages).await?;
+    if projects.is_empty() {
+        return Ok(None);
+    }
+    if !options.dry_run {
+        let claimed = claim_unsynced(pool, &projects).await?;
+        if claimed > 0 {
+            println!("DemocracyLab sync: claimed {} project(s) imported before syncs", claimed);
+        }
+    }
+
+    let job = NewJob {
+        source: "democracylab_sync".to_string(),
+        file_name: Some(base_url.to_string()),
+        file_hash: serde_json::to_vec(&projects).ok().map(|bytes| NewJob::hash_of(&bytes)),
+        profile: None,
+        column_mappings: None,
+    };
+    Ok(Some(ImportRun {
+        job,
+        entity: &entities::PROJECTS,
+        user_id: SYNC_USER.to_string(),
+        rows: projects.iter().enumerate().map(|(index, project)| project.import_row(index + 1)).collect(),
+        // source_id first: the lookup filters on the first column
+        duplicates: DuplicateCheck::Columns(vec!["source_id", "source"]),
+        options: ImportOptions {
+            dry_run: options.dry_run,
+            on_conflict: ConflictStrategy::Overwrite,
+            ..Default::default()
+        },
+    }))
+}
+
+/// Sync every `minutes` while the server runs, starting one period after startup
+pub fn spawn_schedule(state: Arc<ApiState>, minutes: u64) {
+    tokio::spawn(async move {
+        let period = Duration::from_secs(minutes * 60);
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            interval.tick().await;
+            let base_url = state.config.democracylab_base_url.clone();
+            match prepare_sync(&state.db, &base_url, &SyncOptions::default()).await {
+                Ok(None) => {}
+                Ok(Some(run)) => {
+                    let options = run.options.clone();
+                    match run.execute(state.clone()).await {
+                        Ok((job_id, outcome)) => {
+                            println!("DemocracyLab sync (job {}): {}", job_id, outcome.message(entities::PROJECTS.name, &options));
+                        }
+                        Err(e) => println!("DemocracyLab sync failed: {}", e),
+                    }
+                }
+                Err(e) => println!("DemocracyLab sync failed: {:#}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Mock, Server, ServerGuard};
+    use sqlx::postgres::PgPoolOptions;
+
+    fn project(id: u64, name: &str, modified: &str) -> Value {
+        json!({"project_id": id, "project_name": name, "project_date_modified": modified})
+    }
+
+    async fn mock_page(server: &mut ServerGuard, page: u32, num_pages: u32, projects: Vec<Value>) -> Mock {
+        server
+            .mock("GET", "/api/projects")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("sortField".into(), "-project_date_modified".into()),
+                Matcher::UrlEncoded("page".into(), page.to_string()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(json!({"projects": projects, "numPages": num_pages}).to_string())
+            .create_async()
+            .await
+    }
+
+    /// The scratch database in TEST_DATABASE_URL, migrated
+    async fn test_pool() -> Pool<Postgres> {
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must name a scratch database");
+        let pool = PgPoolOptions::new().max_connections(2).connect(&url).await.unwrap();
+        crate::migrations::migrate_up(&pool, None).await.unwrap();
+        pool
+    }
+
+    fn names(projects: &[DemocracyLabProject]) -> Vec<&str> {
+        projects.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn changed_projects_reads_every_page() {
+        let mut server = Server::new_async().await;
+        let pages = [
+            mock_page(&mut server, 1, 2, vec![project(2, "Two", "2024-05-02T00:00:00Z")]).await,
+            mock_page(&mut server, 2, 2, vec![project(1, "One", "2024-05-01T00:00:00Z")]).await,
+        ];
+
+        let client = Client::new(&server.url()).unwrap();
+        let projects = client.changed_projects(None, None).await.unwrap();
+
+        assert_eq!(names(&projects), ["Two", "One"]);
+        for page in pages {
+            page.assert_async().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn changed_projects_stops_at_since() {
+        let mut server = Server::new_async().await;
+        let first = mock_page(&mut server, 1, 3, vec![
+            project(4, "Four", "2024-05-04T00:00:00Z"),
+            project(3, "Three", "2024-05-03T00:00:00Z"),
+        ])
+        .await;
+        let second = mock_page(&mut server, 2, 3, vec![
+            project(2, "Two", "2024-05-02T00:00:00Z"),
+            project(1, "One", "2024-04-01T00:00:00Z"),
+        ])
+        .await;
+        let third = mock_page(&mut server, 3, 3, vec![project(0, "Zero", "2024-03-01T00:00:00Z")])
+            .await
+            .expect(0);
+
+        let since = "2024-04-15T00:00:00Z".parse().unwrap();
+        let client = Client::new(&format!("{}/", server.url())).unwrap();
+        let projects = client.changed_projects(Some(since), None).await.unwrap();
+
+        assert_eq!(names(&projects), ["Four", "Three", "Two"]);
+        first.assert_async().await;
+        second.assert_async().await;
+        third.assert_async().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn second_sync_updates_instead_of_duplicating() {
+        let pool = test_pool().await;
+
+        // A DemocracyLab ID no other run uses
+        let id = (uuid::Uuid::new_v4().as_u128() % 1_000_000_000) as u64;
+        let options = SyncOptions { full: true, ..Default::default() };
+        let mut server = Server::new_async().await;
+
+        let old_name = format!("Old name {}", id);
+        let new_name = format!("New name {}", id);
+        let first = mock_page(&mut server, 1, 1, vec![project(id, &old_name, "2024-05-01T00:00:00Z")]).await;
+        let run = prepare_sync(&pool, &server.url(), &options).await.unwrap().expect("a project to sync");
+        let (_, outcome) = run.execute_untracked(&pool).await.unwrap();
+        assert_eq!((outcome.inserted, outcome.updated), (1, 0));
+        first.remove_async().await;
+
+        mock_page(&mut server, 1, 1, vec![project(id, &new_name, "2024-05-02T00:00:00Z")]).await;
+        let run = prepare_sync(&pool, &server.url(), &options).await.unwrap().expect("a project to sync");
+        let (_, outcome) = run.execute_untracked(&pool).await.unwrap();
+        assert_eq!((outcome.inserted, outcome.updated), (0, 1));
+
+        let names: Vec<String> = sqlx::query_scalar("SELECT name FROM projects WHERE source = $1 AND source_id = $2")
+            .bind(SOURCE)
+            .bind(id.to_string())
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(names, [new_name]);
+
+        sqlx::query("DELETE FROM projects WHERE source = $1 AND source_id = $2")
+            .bind(SOURCE)
+            .bind(id.to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn sync_claims_projects_imported_before_syncs() {
+        let pool = test_pool().await;
+
+        let id = (uuid::Uuid::new_v4().as_u128() % 1_000_000_000) as u64;
+        let name = format!("Legacy project {}", id);
+        // As the import before syncs wrote it
+        let legacy: uuid::Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO projects (id, name, status, date_entered, date_modified, created_by, modified_user_id)
+            VALUES ($1, $2, 'Active', now(), now(), 'democracylab-import', 'democracylab-import')
+            RETURNING id
+            "#
+        )
+        .bind(uuid::Uuid::new_v4())
+        .bind(format!("{}, Inc.", name))
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        let mut server = Server::new_async().await;
+        mock_page(&mut server, 1, 1, vec![project(id, &name, "2024-05-01T00:00:00Z")]).await;
+        let options = SyncOptions { full: true, ..Default::default() };
+        let run = prepare_sync(&pool, &server.url(), &options).await.unwrap().expect("a project to sync");
+        let (_, outcome) = run.execute_untracked(&pool).await.unwrap();
+        assert_eq!((outcome.inserted, outcome.updated), (0, 1));
+
+        let synced: Vec<uuid::Uuid> = sqlx::query_scalar("SELECT id FROM projects WHERE source = $1 AND source_id = $2")
+            .bind(SOURCE)
+            .bind(id.to_string())
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(synced, [legacy]);
+
+        sqlx::query("DELETE FROM projects WHERE id = $1").bind(legacy).execute(&pool).await.unwrap();
+    }
+}
@@ -0,0 +1,401 @@
+// src/duplicates.rs
+//
+// Duplicate detection and merging for accounts and projects. Names are
+// compared after normalisation (case, punctuation and legal suffixes such as
+// "Inc." or "Ltd" removed), so "Acme Inc." and "ACME, Inc" are the same
+// name, and candidates are found by trigram similarity of normalised names
+// using `pg_trgm`. Merging two records keeps the survivor, fills its empty
+// columns from the duplicate, re-points every row that referenced the
+// duplicate and deletes it.
+use actix_web::{web, HttpResponse, Result};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use sqlx::{Pool, Postgres, Transaction};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::Claims;
+use crate::entities::{self, EntityDef, FieldValue, Pagination};
+use crate::relationships::{self, RELATIONSHIPS};
+use crate::ApiState;
+
+/// Entities with duplicate-candidate and merge endpoints
+pub static MERGEABLE: &[&EntityDef] = &[&entities::ACCOUNTS, &entities::PROJECTS];
+
+/// Similarity of normalised names from which two records are candidates
+pub const DEFAULT_THRESHOLD: f32 = 0.6;
+
+/// Words dropped from the end of a name. Keep in step with `normalize_name`
+/// in migration 10.
+const LEGAL_SUFFIXES: &[&str] = &[
+    "inc", "incorporated", "llc", "ltd", "limited", "corp", "corporation", "co", "company",
+    "plc", "gmbh", "ag", "sa", "bv", "pty", "llp", "lp",
+];
+
+/// A name as it is compared: lower case, apostrophes and periods removed,
+/// other punctuation as spaces, and trailing legal suffixes dropped unless
+/// nothing else is left. Matches the SQL function `normalize_name`.
+pub fn normalize_name(name: &str) -> String {
+    let cleaned: String = name
+        .to_lowercase()
+        .chars()
+        .filter(|c| !matches!(c, '\'' | '’' | '.'))
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    let mut words: Vec<&str> = cleaned.split_whitespace().collect();
+    while words.len() > 1 && words.last().is_some_and(|w| LEGAL_SUFFIXES.contains(w)) {
+        words.pop();
+    }
+    words.join(" ")
+}
+
+fn parse_threshold(query: &HashMap<String, String>) -> Result<f32, String> {
+    match query.get("threshold") {
+        None => Ok(DEFAULT_THRESHOLD),
+        Some(raw) => raw
+            .parse::<f32>()
+            .ok()
+            .filter(|t| (0.0..=1.0).contains(t))
+            .ok_or_else(|| format!("Invalid threshold '{}', expected a number between 0 and 1", raw)),
+    }
+}
+
+/// `GET /api/{entity}/duplicates`: pairs of records whose normalised names
+/// are at least `threshold` similar (default 0.6), most similar first. With
+/// `name`, records similar to that name instead, e.g. before creating one.
+async fn duplicate_candidates(
+    entity: web::Data<&'static EntityDef>,
+    data: web::Data<Arc<ApiState>>,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    let pagination = match Pagination::from_query(&query) {
+        Ok(p) => p,
+        Err(e) => return Ok(entities::validation_failed(vec![e])),
+    };
+    let threshold = match parse_threshold(&query) {
+        Ok(threshold) => threshold,
+        Err(e) => return Ok(entities::validation_failed(vec![e])),
+    };
+    for key in query.keys() {
+        if !["limit", "offset", "threshold", "name"].contains(&key.as_str()) {
+            return Ok(entities::validation_failed(vec![format!("Unknown parameter: {}", key)]));
+        }
+    }
+
+    let result = match query.get("name") {
+        Some(name) => similar_to(&data.db, &entity, name, threshold, &pagination).await,
+        None => similar_pairs(&data.db, &entity, threshold, &pagination).await,
+    };
+    match result {
+        Ok(candidates) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "threshold": threshold,
+            "pagination": {
+                "limit": pagination.limit(),
+                "offset": pagination.offset(),
+                "returned": candidates.len()
+            },
+            "data": candidates
+        }))),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+/// Use `threshold` for the `%` operator in this transaction
+async fn set_threshold(tx: &mut Transaction<'_, Postgres>, threshold: f32) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT set_config('pg_trgm.similarity_threshold', $1, true)")
+        .bind(threshold.to_string())
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+async fn similar_pairs(
+    pool: &Pool<Postgres>,
+    entity: &EntityDef,
+    threshold: f32,
+    pagination: &Pagination,
+) -> Result<Vec<Value>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    set_threshold(&mut tx, threshold).await?;
+    let pairs = sqlx::query_scalar::<_, Value>(&format!(
+        r#"
+        SELECT jsonb_build_object(
+            'score', similarity(normalize_name(a.name), normalize_name(b.name)),
+            'same_name', normalize_name(a.name) = normalize_name(b.name),
+            'record', to_jsonb(a),
+            'candidate', to_jsonb(b)
+        )
+        FROM {table} a
+        JOIN {table} b ON a.id < b.id AND normalize_name(a.name) % normalize_name(b.name)
+        ORDER BY similarity(normalize_name(a.name), normalize_name(b.name)) DESC, a.id, b.id
+        LIMIT $1 OFFSET $2
+        "#,
+        table = entity.name,
+    ))
+    .bind(pagination.limit())
+    .bind(pagination.offset())
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.rollback().await?;
+    Ok(pairs)
+}
+
+async fn similar_to(
+    pool: &Pool<Postgres>,
+    entity: &EntityDef,
+    name: &str,
+    threshold: f32,
+    pagination: &Pagination,
+) -> Result<Vec<Value>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    set_threshold(&mut tx, threshold).await?;
+    let records = sqlx::query_scalar::<_, Value>(&format!(
+        r#"
+        SELECT jsonb_build_object(
+            'score', similarity(normalize_name(t.name), normalize_name($1)),
+            'same_name', normalize_name(t.name) = normalize_name($1),
+            'candidate', to_jsonb(t)
+        )
+        FROM {table} t
+        WHERE normalize_name(t.name) % normalize_name($1)
+        ORDER BY similarity(normalize_name(t.name), normalize_name($1)) DESC, t.id
+        LIMIT $2 OFFSET $3
+        "#,
+        table = entity.name,
+    ))
+    .bind(name)
+    .bind(pagination.limit())
+    .bind(pagination.offset())
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.rollback().await?;
+    Ok(records)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Prefer {
+    Survivor,
+    Duplicate,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergeRequest {
+    /// The record that is kept
+    pub survivor_id: Uuid,
+    /// The record that is merged into the survivor and deleted
+    pub duplicate_id: Uuid,
+    /// Whose value to keep, by column. Columns not listed keep the
+    /// survivor's value, or take the duplicate's where the survivor has none.
+    #[serde(default)]
+    pub prefer: HashMap<String, Prefer>,
+}
+
+fn is_empty(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => true,
+        Some(Value::String(s)) => s.trim().is_empty(),
+        _ => false,
+    }
+}
+
+/// The columns of the survivor that change, with the duplicate's values
+fn merged_values(
+    entity: &EntityDef,
+    survivor: &Map<String, Value>,
+    duplicate: &Map<String, Value>,
+    prefer: &HashMap<String, Prefer>,
+) -> Result<Vec<(&'static str, FieldValue)>, Vec<String>> {
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+    for field in entity.fields {
+        let take_duplicate = match prefer.get(field.name) {
+            Some(Prefer::Duplicate) => true,
+            Some(Prefer::Survivor) => false,
+            None => is_empty(survivor.get(field.name)) && !is_empty(duplicate.get(field.name)),
+        };
+        if !take_duplicate {
+            continue;
+        }
+        match field.parse(duplicate.get(field.name).unwrap_or(&Value::Null)) {
+            Ok(value) => values.push((field.name, value)),
+            Err(e) => errors.push(e),
+        }
+    }
+    if errors.is_empty() { Ok(values) } else { Err(errors) }
+}
+
+/// Single-column foreign keys to `entity`'s table, outside the join tables
+async fn referencing_columns(
+    tx: &mut Transaction<'_, Postgres>,
+    entity: &EntityDef,
+) -> Result<Vec<(String, String)>, sqlx::Error> {
+    let columns = sqlx::query_as::<_, (String, String)>(
+        r#"
+        SELECT c.conrelid::regclass::text, a.attname::text
+        FROM pg_constraint c
+        JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = c.conkey[1]
+        WHERE c.contype = 'f'
+          AND c.confrelid = to_regclass($1)
+          AND array_length(c.conkey, 1) = 1
+        ORDER BY 1, 2
+        "#
+    )
+    .bind(entity.name)
+    .fetch_all(&mut **tx)
+    .await?;
+    Ok(columns
+        .into_iter()
+        .filter(|(table, _)| !RELATIONSHIPS.iter().any(|r| r.table == table.as_str()))
+        .collect())
+}
+
+/// Point every row that references `duplicate` at `survivor`. Returns the
+/// number of rows changed per table and column.
+async fn repoint(
+    tx: &mut Transaction<'_, Postgres>,
+    entity: &EntityDef,
+    survivor: Uuid,
+    duplicate: Uuid,
+) -> Result<Map<String, Value>, sqlx::Error> {
+    let mut moved = Map::new();
+
+    let linked = relationships::relink_all(tx, entity, duplicate, survivor).await?;
+    moved.insert("relationships".to_string(), json!(linked));
+
+    for (table, column) in referencing_columns(tx, entity).await? {
+        // A survivor that pointed at the duplicate, e.g. as its parent, keeps
+        // the reference until the duplicate is deleted
+        let own_row = if table == entity.name { " AND id <> $1" } else { "" };
+        let updated = sqlx::query(&format!("UPDATE {} SET {} = $1 WHERE {} = $2{}", table, column, column, own_row))
+            .bind(survivor)
+            .bind(duplicate)
+            .execute(&mut **tx)
+            .await?
+            .rows_affected();
+        moved.insert(format!("{}.{}", table, column), json!(updated));
+    }
+
+    // Tags are polymorphic and have no foreign key
+    sqlx::query(
+        "DELETE FROM taggables d WHERE d.taggable_id = $2 AND EXISTS \
+         (SELECT 1 FROM taggables s WHERE s.taggable_id = $1 AND s.tag_id = d.tag_id AND s.taggable_type = d.taggable_type)",
+    )
+    .bind(survivor)
+    .bind(duplicate)
+    .execute(&mut **tx)
+    .await?;
+    let tagged = sqlx::query("UPDATE taggables SET taggable_id = $1 WHERE taggable_id = $2")
+        .bind(survivor)
+        .bind(duplicate)
+        .execute(&mut **tx)
+        .await?
+        .rows_affected();
+    moved.insert("taggables.taggable_id".to_string(), json!(tagged));
+
+    moved.retain(|_, count| count.as_u64() != Some(0));
+    Ok(moved)
+}
+
+enum MergeError {
+    NotFound(Uuid),
+    Invalid(Vec<String>),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for MergeError {
+    fn from(e: sqlx::Error) -> Self {
+        MergeError::Database(e)
+    }
+}
+
+async fn merge(
+    pool: &Pool<Postgres>,
+    entity: &EntityDef,
+    user_id: &str,
+    req: &MergeRequest,
+) -> std::result::Result<(Value, Map<String, Value>), MergeError> {
+    let mut tx = pool.begin().await?;
+    let mut records = sqlx::query_as::<_, (Uuid, Value)>(&format!(
+        "SELECT t.id, to_jsonb(t) FROM {} t WHERE t.id IN ($1, $2) FOR UPDATE",
+        entity.name
+    ))
+    .bind(req.survivor_id)
+    .bind(req.duplicate_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    let mut take = |id: Uuid| match records.iter().position(|(found, _)| *found == id) {
+        Some(index) => match records.swap_remove(index).1 {
+            Value::Object(record) => Ok(record),
+            _ => Err(MergeError::NotFound(id)),
+        },
+        None => Err(MergeError::NotFound(id)),
+    };
+    let survivor = take(req.survivor_id)?;
+    let duplicate = take(req.duplicate_id)?;
+
+    let values = merged_values(entity, &survivor, &duplicate, &req.prefer).map_err(MergeError::Invalid)?;
+    let moved = repoint(&mut tx, entity, req.survivor_id, req.duplicate_id).await?;
+    sqlx::query(&format!("DELETE FROM {} WHERE id = $1", entity.name))
+        .bind(req.duplicate_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let mut builder = entities::update_query(entity, req.survivor_id, user_id, values);
+    builder.push(" RETURNING to_jsonb(t)");
+    let merged = builder.build_query_scalar::<Value>().fetch_one(&mut *tx).await?;
+    tx.commit().await?;
+    Ok((merged, moved))
+}
+
+/// `POST /api/{entity}/merge`: merge `duplicate_id` into `survivor_id`
+async fn merge_records(
+    entity: web::Data<&'static EntityDef>,
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    req: web::Json<MergeRequest>,
+) -> Result<HttpResponse> {
+    if req.survivor_id == req.duplicate_id {
+        return Ok(entities::validation_failed(vec!["A record cannot be merged into itself".to_string()]));
+    }
+    let unknown: Vec<String> = req
+        .prefer
+        .keys()
+        .filter(|column| entity.field(column).is_none())
+        .map(|column| format!("Unknown field: {}", column))
+        .collect();
+    if !unknown.is_empty() {
+        return Ok(entities::validation_failed(unknown));
+    }
+
+    match merge(&data.db, &entity, &claims.user_id(), &req).await {
+        Ok((record, moved)) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": format!("{} {} merged into {}", entity.label, req.duplicate_id, req.survivor_id),
+            "data": record,
+            "repointed": moved
+        }))),
+        Err(MergeError::NotFound(id)) => Ok(entities::not_found(&entity, id)),
+        Err(MergeError::Invalid(errors)) => Ok(entities::validation_failed(errors)),
+        Err(MergeError::Database(e)) => Ok(entities::database_error(e)),
+    }
+}
+
+/// Register `/{entity}/duplicates` and `/{entity}/merge`. Must be configured
+/// before the entity routes so `/{entity}/{id}` does not match them.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    for entity in MERGEABLE {
+        cfg.service(
+            web::resource(format!("/{}/duplicates", entity.name))
+                .app_data(web::Data::new(*entity))
+                .route(web::get().to(duplicate_candidates))
+        );
+        cfg.service(
+            web::resource(format!("/{}/merge", entity.name))
+                .app_data(web::Data::new(*entity))
+                .route(web::post().to(merge_records))
+        );
+    }
+}
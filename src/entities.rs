@@ -0,0 +1,815 @@
+// src/entities.rs
+//
+// Generic list/get/create/update/delete endpoints for the CRM tables created
+// by `init_database`. Each entity is described by an `EntityDef`, which is the
+// single source for the table name, its writable columns and their types.
+use actix_web::{middleware, web, HttpResponse, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::{Postgres, QueryBuilder, Row};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::{self, Claims};
+use crate::relationships;
+use crate::ApiState;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const MAX_PAGE_SIZE: i64 = 500;
+
+/// Offset paging shared by every list endpoint.
+#[derive(Debug, Deserialize)]
+pub struct Pagination {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl Pagination {
+    pub fn from_query(query: &HashMap<String, String>) -> Result<Self, String> {
+        let parse = |key: &str| -> Result<Option<i64>, String> {
+            query.get(key)
+                .map(|v| v.parse::<i64>().map_err(|_| format!("Invalid {} '{}'", key, v)))
+                .transpose()
+        };
+        Ok(Pagination { limit: parse("limit")?, offset: parse("offset")? })
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0).max(0)
+    }
+
+    /// The `pagination` object returned alongside a page of results
+    pub fn to_json(&self, total: i64, returned: usize) -> serde_json::Value {
+        let (limit, offset) = (self.limit(), self.offset());
+        let has_more = offset + (returned as i64) < total;
+        json!({
+            "limit": limit,
+            "offset": offset,
+            "total": total,
+            "has_more": has_more,
+            "next_offset": if has_more { Some(offset + limit) } else { None }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldType {
+    Text(Option<usize>),
+    Uuid,
+    Date,
+    Timestamp,
+    Integer,
+    Decimal,
+    Boolean,
+}
+
+#[derive(Debug)]
+pub struct FieldDef {
+    pub name: &'static str,
+    pub kind: FieldType,
+    pub required: bool,
+}
+
+#[derive(Debug)]
+pub struct EntityDef {
+    pub name: &'static str,
+    pub label: &'static str,
+    pub fields: &'static [FieldDef],
+    /// Whether the table carries created_by / modified_user_id / date_modified
+    pub audited: bool,
+}
+
+const fn text(name: &'static str, max: usize) -> FieldDef {
+    FieldDef { name, kind: FieldType::Text(Some(max)), required: false }
+}
+
+const fn long_text(name: &'static str) -> FieldDef {
+    FieldDef { name, kind: FieldType::Text(None), required: false }
+}
+
+const fn required(name: &'static str, max: usize) -> FieldDef {
+    FieldDef { name, kind: FieldType::Text(Some(max)), required: true }
+}
+
+const fn field(name: &'static str, kind: FieldType) -> FieldDef {
+    FieldDef { name, kind, required: false }
+}
+
+pub static ACCOUNTS: EntityDef = EntityDef {
+    name: "accounts",
+    label: "Account",
+    audited: true,
+    fields: &[
+        required("name", 150),
+        text("account_type", 50),
+        text("industry", 50),
+        text("phone_office", 100),
+        text("website", 255),
+    ],
+};
+
+pub static CONTACTS: EntityDef = EntityDef {
+    name: "contacts",
+    label: "Contact",
+    audited: true,
+    fields: &[
+        text("salutation", 255),
+        text("first_name", 100),
+        required("last_name", 100),
+        text("title", 100),
+        text("department", 255),
+        field("account_id", FieldType::Uuid),
+        text("phone_work", 100),
+        text("phone_mobile", 100),
+        text("email", 100),
+        text("primary_address_street", 150),
+        text("primary_address_city", 100),
+        text("primary_address_state", 100),
+        text("primary_address_postalcode", 20),
+        text("primary_address_country", 255),
+        long_text("description"),
+    ],
+};
+
+pub static LEADS: EntityDef = EntityDef {
+    name: "leads",
+    label: "Lead",
+    audited: true,
+    fields: &[
+        text("salutation", 255),
+        text("first_name", 100),
+        required("last_name", 100),
+        text("title", 100),
+        text("company", 100),
+        text("phone_work", 100),
+        text("phone_mobile", 100),
+        text("email", 100),
+        text("status", 100),
+        text("lead_source", 100),
+        long_text("description"),
+        field("converted", FieldType::Boolean),
+    ],
+};
+
+pub static OPPORTUNITIES: EntityDef = EntityDef {
+    name: "opportunities",
+    label: "Opportunity",
+    audited: true,
+    fields: &[
+        required("name", 50),
+        field("account_id", FieldType::Uuid),
+        text("opportunity_type", 255),
+        text("lead_source", 50),
+        field("amount", FieldType::Decimal),
+        text("currency_id", 36),
+        field("date_closed", FieldType::Date),
+        text("sales_stage", 255),
+        field("probability", FieldType::Decimal),
+        long_text("description"),
+    ],
+};
+
+pub static ACTIVITIES: EntityDef = EntityDef {
+    name: "activities",
+    label: "Activity",
+    audited: true,
+    fields: &[
+        required("name", 255),
+        field("date_due", FieldType::Timestamp),
+        field("date_start", FieldType::Timestamp),
+        text("parent_type", 255),
+        field("parent_id", FieldType::Uuid),
+        text("status", 100),
+        text("priority", 255),
+        long_text("description"),
+        field("contact_id", FieldType::Uuid),
+        field("account_id", FieldType::Uuid),
+    ],
+};
+
+pub static CAMPAIGNS: EntityDef = EntityDef {
+    name: "campaigns",
+    label: "Campaign",
+    audited: true,
+    fields: &[
+        required("name", 50),
+        text("campaign_type", 100),
+        text("status", 100),
+        field("start_date", FieldType::Date),
+        field("end_date", FieldType::Date),
+        field("budget", FieldType::Decimal),
+        field("expected_cost", FieldType::Decimal),
+        field("actual_cost", FieldType::Decimal),
+        field("expected_revenue", FieldType::Decimal),
+        long_text("objective"),
+        long_text("content"),
+    ],
+};
+
+pub static DOCUMENTS: EntityDef = EntityDef {
+    name: "documents",
+    label: "Document",
+    audited: true,
+    fields: &[
+        required("document_name", 255),
+        text("filename", 255),
+        text("file_ext", 100),
+        text("file_mime_type", 100),
+        text("revision", 100),
+        text("category_id", 100),
+        text("subcategory_id", 100),
+        text("status", 100),
+        long_text("description"),
+    ],
+};
+
+pub static EVENTS: EntityDef = EntityDef {
+    name: "events",
+    label: "Event",
+    audited: true,
+    fields: &[
+        required("name", 255),
+        field("date_start", FieldType::Timestamp),
+        field("date_end", FieldType::Timestamp),
+        field("duration_hours", FieldType::Integer),
+        field("duration_minutes", FieldType::Integer),
+        text("location", 255),
+        long_text("description"),
+    ],
+};
+
+pub static PRODUCTS: EntityDef = EntityDef {
+    name: "products",
+    label: "Product",
+    audited: true,
+    fields: &[
+        required("name", 50),
+        text("product_code", 50),
+        text("category", 100),
+        text("manufacturer", 50),
+        field("cost", FieldType::Decimal),
+        field("price", FieldType::Decimal),
+        long_text("description"),
+    ],
+};
+
+pub static ROLES: EntityDef = EntityDef {
+    name: "roles",
+    label: "Role",
+    audited: true,
+    fields: &[
+        required("name", 150),
+        long_text("description"),
+    ],
+};
+
+pub static CALLS: EntityDef = EntityDef {
+    name: "calls",
+    label: "Call",
+    audited: true,
+    fields: &[
+        required("name", 50),
+        field("date_start", FieldType::Timestamp),
+        field("date_end", FieldType::Timestamp),
+        field("duration_hours", FieldType::Integer),
+        field("duration_minutes", FieldType::Integer),
+        text("status", 100),
+        text("direction", 100),
+        text("parent_type", 255),
+        field("parent_id", FieldType::Uuid),
+        field("contact_id", FieldType::Uuid),
+        field("account_id", FieldType::Uuid),
+        long_text("description"),
+    ],
+};
+
+pub static SURVEY_QUESTION_OPTIONS: EntityDef = EntityDef {
+    name: "surveyquestionoptions",
+    label: "Survey question option",
+    audited: true,
+    fields: &[
+        required("name", 50),
+        field("survey_question_id", FieldType::Uuid),
+        field("sort_order", FieldType::Integer),
+    ],
+};
+
+pub static TAGS: EntityDef = EntityDef {
+    name: "tags",
+    label: "Tag",
+    audited: true,
+    fields: &[
+        required("name", 255),
+    ],
+};
+
+// Projects and users have dedicated handlers (or none) but still take part
+// in relationships, so they are described here without being routed.
+pub static PROJECTS: EntityDef = EntityDef {
+    name: "projects",
+    label: "Project",
+    audited: true,
+    fields: &[
+        required("name", 255),
+        long_text("description"),
+        text("status", 50),
+        text("priority", 255),
+        field("estimated_start_date", FieldType::Date),
+        field("estimated_end_date", FieldType::Date),
+        text("fiscal_year", 20),
+        text("project_number", 50),
+        field("committed_amount", FieldType::Decimal),
+        text("country", 100),
+        text("region", 100),
+        text("department", 255),
+        text("framework", 255),
+        text("naics_sector", 255),
+        text("profile_url", 500),
+        long_text("skills"),
+        text("location", 255),
+        text("organization", 255),
+        text("source", 50),
+        text("source_id", 100),
+        field("source_modified", FieldType::Timestamp),
+    ],
+};
+
+pub static USERS: EntityDef = EntityDef {
+    name: "users",
+    label: "User",
+    audited: false,
+    fields: &[
+        text("user_name", 60),
+        text("first_name", 30),
+        text("last_name", 30),
+        text("email", 100),
+        text("status", 100),
+    ],
+};
+
+/// Entities served under `/api/{entity}`. Projects keep their own handlers.
+pub static ENTITIES: &[&EntityDef] = &[
+    &ACCOUNTS,
+    &CONTACTS,
+    &LEADS,
+    &OPPORTUNITIES,
+    &ACTIVITIES,
+    &CAMPAIGNS,
+    &DOCUMENTS,
+    &EVENTS,
+    &PRODUCTS,
+    &ROLES,
+    &CALLS,
+    &SURVEY_QUESTION_OPTIONS,
+    &TAGS,
+];
+
+impl EntityDef {
+    pub fn field(&self, name: &str) -> Option<&FieldDef> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+}
+
+/// Any CRM table with an `EntityDef`, including projects
+pub fn lookup(name: &str) -> Option<&'static EntityDef> {
+    if name == PROJECTS.name {
+        return Some(&PROJECTS);
+    }
+    ENTITIES.iter().find(|entity| entity.name == name).copied()
+}
+
+/// A request value checked against its column type, ready to bind
+#[derive(Debug, Clone)]
+pub enum FieldValue {
+    Null,
+    Text(String),
+    Uuid(Uuid),
+    Date(NaiveDate),
+    Timestamp(DateTime<Utc>),
+    Integer(i32),
+    Decimal(String),
+    Boolean(bool),
+}
+
+impl FieldDef {
+    pub fn parse(&self, value: &serde_json::Value) -> Result<FieldValue, String> {
+        if value.is_null() {
+            return if self.required {
+                Err(format!("{} is required", self.name))
+            } else {
+                Ok(FieldValue::Null)
+            };
+        }
+        match self.kind {
+            FieldType::Text(max_length) => {
+                let s = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    serde_json::Value::Number(n) => n.to_string(),
+                    serde_json::Value::Bool(b) => b.to_string(),
+                    _ => return Err(format!("{} must be a string", self.name)),
+                };
+                if self.required && s.trim().is_empty() {
+                    return Err(format!("{} is required", self.name));
+                }
+                if let Some(max) = max_length {
+                    if s.chars().count() > max {
+                        return Err(format!("{} must be at most {} characters", self.name, max));
+                    }
+                }
+                Ok(FieldValue::Text(s))
+            }
+            FieldType::Uuid => value.as_str()
+                .and_then(|s| Uuid::parse_str(s).ok())
+                .map(FieldValue::Uuid)
+                .ok_or_else(|| format!("{} must be a UUID", self.name)),
+            FieldType::Date => value.as_str()
+                .and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok())
+                .map(FieldValue::Date)
+                .ok_or_else(|| format!("{} must be a date (YYYY-MM-DD)", self.name)),
+            FieldType::Timestamp => value.as_str()
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|dt| FieldValue::Timestamp(dt.with_timezone(&Utc)))
+                .ok_or_else(|| format!("{} must be an RFC 3339 timestamp", self.name)),
+            FieldType::Integer => value.as_i64()
+                .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+                .and_then(|n| i32::try_from(n).ok())
+                .map(FieldValue::Integer)
+                .ok_or_else(|| format!("{} must be an integer", self.name)),
+            FieldType::Decimal => match value {
+                serde_json::Value::Number(n) => Ok(FieldValue::Decimal(n.to_string())),
+                serde_json::Value::String(s) if s.trim().parse::<f64>().is_ok() => {
+                    Ok(FieldValue::Decimal(s.trim().to_string()))
+                }
+                _ => Err(format!("{} must be a number", self.name)),
+            },
+            FieldType::Boolean => match value {
+                serde_json::Value::Bool(b) => Ok(FieldValue::Boolean(*b)),
+                serde_json::Value::String(s) => match s.to_lowercase().as_str() {
+                    "true" | "1" | "yes" => Ok(FieldValue::Boolean(true)),
+                    "false" | "0" | "no" => Ok(FieldValue::Boolean(false)),
+                    _ => Err(format!("{} must be a boolean", self.name)),
+                },
+                _ => Err(format!("{} must be a boolean", self.name)),
+            },
+        }
+    }
+}
+
+pub fn push_value(builder: &mut QueryBuilder<'_, Postgres>, value: FieldValue) {
+    match value {
+        FieldValue::Null => builder.push("NULL"),
+        FieldValue::Text(s) => builder.push_bind(s),
+        FieldValue::Uuid(u) => builder.push_bind(u),
+        FieldValue::Date(d) => builder.push_bind(d),
+        FieldValue::Timestamp(t) => builder.push_bind(t),
+        FieldValue::Integer(i) => builder.push_bind(i),
+        FieldValue::Decimal(s) => builder.push_bind(s).push("::numeric"),
+        FieldValue::Boolean(b) => builder.push_bind(b),
+    };
+}
+
+/// Validate a JSON body against the entity's writable columns.
+/// With `creating` set, required fields must be present.
+pub fn validate_body(
+    entity: &EntityDef,
+    body: &serde_json::Map<String, serde_json::Value>,
+    creating: bool,
+) -> Result<Vec<(&'static str, FieldValue)>, Vec<String>> {
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+
+    for key in body.keys() {
+        if entity.field(key).is_none() {
+            errors.push(format!("Unknown field: {}", key));
+        }
+    }
+    for field in entity.fields {
+        match body.get(field.name) {
+            Some(value) => match field.parse(value) {
+                Ok(parsed) => values.push((field.name, parsed)),
+                Err(e) => errors.push(e),
+            },
+            None if creating && field.required => errors.push(format!("{} is required", field.name)),
+            None => {}
+        }
+    }
+
+    if errors.is_empty() { Ok(values) } else { Err(errors) }
+}
+
+pub fn validation_failed(errors: Vec<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "error": "Validation failed",
+        "errors": errors
+    }))
+}
+
+pub fn not_found(entity: &EntityDef, id: Uuid) -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "error": format!("{} {} not found", entity.label, id)
+    }))
+}
+
+pub fn database_error(e: sqlx::Error) -> HttpResponse {
+    let code = e.as_database_error().and_then(|d| d.code()).map(|c| c.to_string());
+    match code.as_deref() {
+        // foreign_key_violation, unique_violation
+        Some("23503") | Some("23505") => HttpResponse::Conflict().json(json!({
+            "success": false,
+            "error": e.to_string()
+        })),
+        _ => HttpResponse::InternalServerError().json(json!({
+            "success": false,
+            "error": e.to_string()
+        })),
+    }
+}
+
+/// `INSERT` of one validated record, stamped with `user_id` on audited tables
+pub fn insert_query<'a>(
+    entity: &EntityDef,
+    id: Uuid,
+    user_id: &str,
+    values: Vec<(&'static str, FieldValue)>,
+) -> QueryBuilder<'a, Postgres> {
+    insert_many_query(entity, user_id, vec![(id, values)])
+}
+
+/// Multi-row `INSERT` of validated records. Columns that only some rows set
+/// take their default in the others.
+pub fn insert_many_query<'a>(
+    entity: &EntityDef,
+    user_id: &str,
+    rows: Vec<(Uuid, Vec<(&'static str, FieldValue)>)>,
+) -> QueryBuilder<'a, Postgres> {
+    let now = Utc::now();
+    let columns: Vec<&'static str> = entity.fields
+        .iter()
+        .map(|f| f.name)
+        .filter(|name| rows.iter().any(|(_, values)| values.iter().any(|(n, _)| n == name)))
+        .collect();
+
+    let mut builder = QueryBuilder::<Postgres>::new(format!("INSERT INTO {} AS t (id, date_entered", entity.name));
+    if entity.audited {
+        builder.push(", date_modified, created_by, modified_user_id");
+    }
+    for name in &columns {
+        builder.push(", ").push(*name);
+    }
+    builder.push(") VALUES ");
+    for (index, (id, mut values)) in rows.into_iter().enumerate() {
+        if index > 0 {
+            builder.push(", ");
+        }
+        builder.push("(").push_bind(id).push(", ").push_bind(now);
+        if entity.audited {
+            builder.push(", ").push_bind(now);
+            builder.push(", ").push_bind(user_id.to_string());
+            builder.push(", ").push_bind(user_id.to_string());
+        }
+        for name in &columns {
+            builder.push(", ");
+            match values.iter().position(|(n, _)| n == name) {
+                Some(position) => push_value(&mut builder, values.swap_remove(position).1),
+                None => {
+                    builder.push("DEFAULT");
+                }
+            }
+        }
+        builder.push(")");
+    }
+    builder
+}
+
+/// `UPDATE` of one record with validated values, stamped with `user_id` on
+/// audited tables. The caller must pass at least one value for tables that
+/// are not audited.
+pub fn update_query<'a>(
+    entity: &EntityDef,
+    id: Uuid,
+    user_id: &str,
+    values: Vec<(&'static str, FieldValue)>,
+) -> QueryBuilder<'a, Postgres> {
+    let mut builder = QueryBuilder::<Postgres>::new(format!("UPDATE {} AS t SET ", entity.name));
+    let mut separated = false;
+    if entity.audited {
+        builder.push("date_modified = ").push_bind(Utc::now());
+        builder.push(", modified_user_id = ").push_bind(user_id.to_string());
+        separated = true;
+    }
+    for (name, value) in values {
+        if separated {
+            builder.push(", ");
+        }
+        builder.push(format!("{} = ", name));
+        push_value(&mut builder, value);
+        separated = true;
+    }
+    builder.push(" WHERE t.id = ").push_bind(id);
+    builder
+}
+
+fn order_clause(entity: &EntityDef) -> &'static str {
+    if entity.audited {
+        " ORDER BY t.date_modified DESC NULLS LAST, t.id"
+    } else {
+        " ORDER BY t.date_entered DESC NULLS LAST, t.id"
+    }
+}
+
+// List records with paging and equality filters on any writable column
+async fn list_records(
+    entity: web::Data<&'static EntityDef>,
+    data: web::Data<Arc<ApiState>>,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    let pagination = match Pagination::from_query(&query) {
+        Ok(p) => p,
+        Err(e) => return Ok(validation_failed(vec![e])),
+    };
+
+    let mut filters = Vec::new();
+    let mut errors = Vec::new();
+    for (key, raw) in query.iter().filter(|(k, _)| k.as_str() != "limit" && k.as_str() != "offset") {
+        match entity.field(key) {
+            Some(field) => match field.parse(&serde_json::Value::String(raw.clone())) {
+                Ok(value) => filters.push((field.name, value)),
+                Err(e) => errors.push(e),
+            },
+            None => errors.push(format!("Unknown filter: {}", key)),
+        }
+    }
+    if !errors.is_empty() {
+        return Ok(validation_failed(errors));
+    }
+
+    // Filters are pushed twice (count + page), so build both queries in step
+    let mut count_query = QueryBuilder::new(format!("SELECT COUNT(*) FROM {} t WHERE 1=1", entity.name));
+    let mut list_query = QueryBuilder::new(format!("SELECT to_jsonb(t) AS record FROM {} t WHERE 1=1", entity.name));
+    for (name, value) in filters {
+        count_query.push(format!(" AND t.{} = ", name));
+        push_value(&mut count_query, value.clone());
+        list_query.push(format!(" AND t.{} = ", name));
+        push_value(&mut list_query, value);
+    }
+    list_query
+        .push(order_clause(&entity))
+        .push(" LIMIT ")
+        .push_bind(pagination.limit())
+        .push(" OFFSET ")
+        .push_bind(pagination.offset());
+
+    let total = match count_query.build_query_scalar::<i64>().fetch_one(&data.db).await {
+        Ok(total) => total,
+        Err(e) => return Ok(database_error(e)),
+    };
+    match list_query.build().fetch_all(&data.db).await {
+        Ok(rows) => {
+            let records: Vec<serde_json::Value> = rows.iter()
+                .map(|row| row.get::<serde_json::Value, _>("record"))
+                .collect();
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "pagination": pagination.to_json(total, records.len()),
+                "data": records
+            })))
+        }
+        Err(e) => Ok(database_error(e)),
+    }
+}
+
+async fn get_record(
+    entity: web::Data<&'static EntityDef>,
+    data: web::Data<Arc<ApiState>>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let result = sqlx::query_scalar::<_, serde_json::Value>(
+        &format!("SELECT to_jsonb(t) FROM {} t WHERE t.id = $1", entity.name)
+    )
+    .bind(id)
+    .fetch_optional(&data.db)
+    .await;
+
+    match result {
+        Ok(Some(record)) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": record
+        }))),
+        Ok(None) => Ok(not_found(&entity, id)),
+        Err(e) => Ok(database_error(e)),
+    }
+}
+
+async fn create_record(
+    entity: web::Data<&'static EntityDef>,
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    body: web::Json<serde_json::Map<String, serde_json::Value>>,
+) -> Result<HttpResponse> {
+    let values = match validate_body(&entity, &body, true) {
+        Ok(values) => values,
+        Err(errors) => return Ok(validation_failed(errors)),
+    };
+
+    let id = Uuid::new_v4();
+    let mut builder = insert_query(&entity, id, &claims.user_id(), values);
+    builder.push(" RETURNING to_jsonb(t)");
+
+    match builder.build_query_scalar::<serde_json::Value>().fetch_one(&data.db).await {
+        Ok(record) => Ok(HttpResponse::Created().json(json!({
+            "success": true,
+            "id": id.to_string(),
+            "message": format!("{} created successfully", entity.label),
+            "data": record
+        }))),
+        Err(e) => Ok(database_error(e)),
+    }
+}
+
+async fn update_record(
+    entity: web::Data<&'static EntityDef>,
+    data: web::Data<Arc<ApiState>>,
+    path: web::Path<Uuid>,
+    claims: Claims,
+    body: web::Json<serde_json::Map<String, serde_json::Value>>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let values = match validate_body(&entity, &body, false) {
+        Ok(values) => values,
+        Err(errors) => return Ok(validation_failed(errors)),
+    };
+    if values.is_empty() && !entity.audited {
+        return Ok(validation_failed(vec!["No fields to update".to_string()]));
+    }
+
+    let mut builder = update_query(&entity, id, &claims.user_id(), values);
+    builder.push(" RETURNING to_jsonb(t)");
+
+    match builder.build_query_scalar::<serde_json::Value>().fetch_optional(&data.db).await {
+        Ok(Some(record)) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": format!("{} updated successfully", entity.label),
+            "data": record
+        }))),
+        Ok(None) => Ok(not_found(&entity, id)),
+        Err(e) => Ok(database_error(e)),
+    }
+}
+
+async fn delete_record(
+    entity: web::Data<&'static EntityDef>,
+    data: web::Data<Arc<ApiState>>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+
+    // Links in the join tables go with the record
+    let result: Result<u64, sqlx::Error> = async {
+        let mut tx = data.db.begin().await?;
+        relationships::unlink_all(&mut tx, &entity, id).await?;
+        let deleted = sqlx::query(&format!("DELETE FROM {} WHERE id = $1", entity.name))
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+        Ok(deleted)
+    }
+    .await;
+
+    match result {
+        Ok(0) => Ok(not_found(&entity, id)),
+        Ok(_) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": format!("{} deleted successfully", entity.label)
+        }))),
+        Err(e) => Ok(database_error(e)),
+    }
+}
+
+/// Register `/{entity}` and `/{entity}/{id}` routes for every entity.
+/// Tables listed in `auth::ADMIN_TABLES` are restricted to admins.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    for entity in ENTITIES {
+        let admin_only = auth::ADMIN_TABLES.contains(&entity.name);
+        cfg.service(
+            web::scope(&format!("/{}", entity.name))
+                .wrap(middleware::Condition::new(admin_only, middleware::from_fn(auth::require_admin)))
+                .app_data(web::Data::new(*entity))
+                .route("", web::get().to(list_records))
+                .route("", web::post().to(create_record))
+                .route("/{id}", web::get().to(get_record))
+                .route("/{id}", web::patch().to(update_record))
+                .route("/{id}", web::delete().to(delete_record))
+        );
+    }
+}
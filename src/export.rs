@@ -0,0 +1,338 @@
+// src/export.rs
+//
+// CSV, NDJSON and XLSX downloads of query results and whole tables.
+// Rows are read from a cursor-like stream and encoded in chunks by a
+// background task, so large results are never held in memory at once.
+// XLSX is assembled in constant-memory mode and sent when complete.
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Result};
+use futures_util::TryStreamExt;
+use rust_xlsxwriter::{Format, Workbook};
+use serde_json::{json, Value};
+use sqlx::{Column, Executor, Pool, Postgres};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::auth::{self, Claims};
+use crate::entities;
+use crate::{pg_json, sql_safety, ApiState};
+
+/// Encoded bytes are handed to the response once a chunk reaches this size
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Rows per XLSX sheet, leaving room for the header row
+const XLSX_MAX_ROWS: u32 = 1_048_575;
+
+/// Columns never included in table exports
+const HIDDEN_COLUMNS: &[&str] = &["password_hash"];
+
+const XLSX_CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+    Xlsx,
+}
+
+impl ExportFormat {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "ndjson" | "jsonl" => Some(ExportFormat::Ndjson),
+            "xlsx" => Some(ExportFormat::Xlsx),
+            _ => None,
+        }
+    }
+
+    fn from_mime(mime: &str) -> Option<Self> {
+        match mime.split(';').next().unwrap_or("").trim() {
+            "text/csv" => Some(ExportFormat::Csv),
+            "application/x-ndjson" | "application/jsonl" | "application/x-jsonlines" => Some(ExportFormat::Ndjson),
+            XLSX_CONTENT_TYPE => Some(ExportFormat::Xlsx),
+            _ => None,
+        }
+    }
+
+    /// The `format` query parameter wins over the `Accept` header.
+    /// `Ok(None)` means plain JSON was asked for (or nothing specific).
+    pub fn requested(req: &HttpRequest, query: &HashMap<String, String>) -> Result<Option<Self>, String> {
+        if let Some(name) = query.get("format") {
+            if name.eq_ignore_ascii_case("json") {
+                return Ok(None);
+            }
+            return Self::from_name(name)
+                .map(Some)
+                .ok_or_else(|| format!("Unsupported format '{}', expected csv, ndjson or xlsx", name));
+        }
+
+        let accept = req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("");
+        Ok(accept.split(',').find_map(Self::from_mime))
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Xlsx => XLSX_CONTENT_TYPE,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+type Chunk = Result<web::Bytes, std::io::Error>;
+
+/// Hands chunks to the response body. Until the first chunk is sent, errors
+/// can still be reported as a normal JSON error response.
+struct ChunkSender {
+    ready: Option<oneshot::Sender<Result<(), String>>>,
+    body: mpsc::Sender<Chunk>,
+}
+
+impl ChunkSender {
+    async fn send(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        if let Some(ready) = self.ready.take() {
+            let _ = ready.send(Ok(()));
+        }
+        self.body
+            .send(Ok(web::Bytes::from(bytes)))
+            .await
+            .map_err(|_| "Client disconnected".to_string())
+    }
+
+    async fn fail(mut self, error: String) {
+        match self.ready.take() {
+            Some(ready) => {
+                let _ = ready.send(Err(error));
+            }
+            None => {
+                println!("Export failed after streaming started: {}", error);
+                let _ = self.body.send(Err(std::io::Error::other(error))).await;
+            }
+        }
+    }
+
+    fn finish(mut self) {
+        // An export with no rows and no header still needs a response
+        if let Some(ready) = self.ready.take() {
+            let _ = ready.send(Ok(()));
+        }
+    }
+}
+
+/// Text form of a value for CSV cells
+pub fn cell_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+async fn write_export(
+    pool: &Pool<Postgres>,
+    sql: &str,
+    format: ExportFormat,
+    timeout_secs: u64,
+    sender: &mut ChunkSender,
+) -> Result<(), String> {
+    let mut tx = sql_safety::begin_read_only(pool, timeout_secs)
+        .await
+        .map_err(|e| e.to_string())?;
+    let describe = (&mut *tx).describe(sql).await.map_err(|e| e.to_string())?;
+    let columns: Vec<String> = describe.columns().iter().map(|c| c.name().to_string()).collect();
+
+    let mut rows = sqlx::query(sql).fetch(&mut *tx);
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.write_record(&columns).map_err(|e| e.to_string())?;
+            while let Some(row) = rows.try_next().await.map_err(|e| e.to_string())? {
+                let record: Vec<String> = (0..columns.len())
+                    .map(|i| cell_text(&pg_json::value_to_json(&row, i)))
+                    .collect();
+                writer.write_record(&record).map_err(|e| e.to_string())?;
+                if writer.get_ref().len() >= CHUNK_SIZE {
+                    let chunk = writer.into_inner().map_err(|e| e.to_string())?;
+                    writer = csv::Writer::from_writer(Vec::new());
+                    sender.send(chunk).await?;
+                }
+            }
+            sender.send(writer.into_inner().map_err(|e| e.to_string())?).await?;
+        }
+        ExportFormat::Ndjson => {
+            let mut buffer = Vec::new();
+            while let Some(row) = rows.try_next().await.map_err(|e| e.to_string())? {
+                serde_json::to_writer(&mut buffer, &pg_json::row_to_json(&row)).map_err(|e| e.to_string())?;
+                buffer.push(b'\n');
+                if buffer.len() >= CHUNK_SIZE {
+                    sender.send(std::mem::take(&mut buffer)).await?;
+                }
+            }
+            if !buffer.is_empty() {
+                sender.send(buffer).await?;
+            }
+        }
+        ExportFormat::Xlsx => {
+            let mut workbook = Workbook::new();
+            let worksheet = workbook.add_worksheet_with_constant_memory();
+            let bold = Format::new().set_bold();
+            for (col, name) in columns.iter().enumerate() {
+                worksheet.write_string_with_format(0, col as u16, name, &bold).map_err(|e| e.to_string())?;
+            }
+
+            let mut row_num: u32 = 0;
+            while let Some(row) = rows.try_next().await.map_err(|e| e.to_string())? {
+                if row_num == XLSX_MAX_ROWS {
+                    return Err(format!("Result has more than {} rows, which does not fit in an XLSX sheet. Use CSV instead.", XLSX_MAX_ROWS));
+                }
+                row_num += 1;
+                for col in 0..columns.len() {
+                    let result = match pg_json::value_to_json(&row, col) {
+                        Value::Null => continue,
+                        Value::Bool(b) => worksheet.write_boolean(row_num, col as u16, b).map(|_| ()),
+                        Value::Number(n) => worksheet.write_number(row_num, col as u16, n.as_f64().unwrap_or_default()).map(|_| ()),
+                        other => worksheet.write_string(row_num, col as u16, cell_text(&other)).map(|_| ()),
+                    };
+                    result.map_err(|e| e.to_string())?;
+                }
+            }
+            let buffer = workbook.save_to_buffer().map_err(|e| e.to_string())?;
+            sender.send(buffer).await?;
+        }
+    }
+
+    drop(rows);
+    tx.rollback().await.map_err(|e| e.to_string())
+}
+
+/// Run `sql` (already validated as read-only) and stream the result in `format`
+pub async fn stream_query(
+    pool: Pool<Postgres>,
+    sql: String,
+    format: ExportFormat,
+    file_stem: &str,
+    timeout_secs: u64,
+) -> HttpResponse {
+    let (ready_tx, ready_rx) = oneshot::channel();
+    let (body_tx, body_rx) = mpsc::channel::<Chunk>(8);
+
+    tokio::spawn(async move {
+        let mut sender = ChunkSender { ready: Some(ready_tx), body: body_tx };
+        match write_export(&pool, &sql, format, timeout_secs, &mut sender).await {
+            Ok(()) => sender.finish(),
+            Err(e) => sender.fail(e).await,
+        }
+    });
+
+    match ready_rx.await {
+        Ok(Ok(())) => {
+            let body = futures_util::stream::unfold(body_rx, |mut rx| async move {
+                rx.recv().await.map(|chunk| (chunk, rx))
+            });
+            HttpResponse::Ok()
+                .content_type(format.content_type())
+                .insert_header((
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}.{}\"", file_stem, format.extension()),
+                ))
+                .streaming(body)
+        }
+        Ok(Err(e)) => HttpResponse::InternalServerError().json(json!({
+            "success": false,
+            "error": format!("Export failed: {}", e)
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(json!({
+            "success": false,
+            "error": "Export failed"
+        })),
+    }
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+// Download a whole table. CRM entity tables are open to any signed-in user;
+// everything else, and the role tables, need the admin role.
+pub async fn export_table(
+    req: HttpRequest,
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    path: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    let table = path.into_inner();
+    let format = match ExportFormat::requested(&req, &query) {
+        Ok(format) => format.unwrap_or(ExportFormat::Csv),
+        Err(e) => return Ok(HttpResponse::BadRequest().json(json!({ "success": false, "error": e }))),
+    };
+
+    if entities::lookup(&table).is_none() || auth::ADMIN_TABLES.contains(&table.as_str()) {
+        match auth::is_admin(&data.db, claims.sub).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return Ok(HttpResponse::Forbidden().json(json!({
+                    "success": false,
+                    "error": "Admin role required to export this table"
+                })));
+            }
+            Err(e) => {
+                return Ok(HttpResponse::InternalServerError().json(json!({
+                    "success": false,
+                    "error": e.to_string()
+                })));
+            }
+        }
+    }
+
+    // The column list doubles as the existence check and keeps hidden columns out
+    let columns = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT column_name::text
+        FROM information_schema.columns
+        WHERE table_schema = 'public' AND table_name = $1
+        ORDER BY ordinal_position
+        "#
+    )
+    .bind(&table)
+    .fetch_all(&data.db)
+    .await;
+    let columns: Vec<String> = match columns {
+        Ok(columns) if !columns.is_empty() => columns
+            .into_iter()
+            .filter(|c| !HIDDEN_COLUMNS.contains(&c.as_str()))
+            .collect(),
+        Ok(_) => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "success": false,
+                "error": format!("Table '{}' not found", table)
+            })));
+        }
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "error": e.to_string()
+            })));
+        }
+    };
+
+    let sql = format!(
+        "SELECT {} FROM public.{}",
+        columns.iter().map(|c| quote_ident(c)).collect::<Vec<_>>().join(", "),
+        quote_ident(&table)
+    );
+    println!("Exporting table {} as {}", table, format.extension());
+    Ok(stream_query(data.db.clone(), sql, format, &table, data.config.export_timeout_secs).await)
+}
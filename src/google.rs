@@ -1,29 +1,261 @@
 // src/google.rs
 
 use actix_web::{web, HttpResponse, Result};
+use anyhow::{bail, Context};
+use google_sheets4::api::Scope;
+use google_sheets4::{hyper, hyper_rustls, oauth2, Sheets};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use crate::ApiState;
-// use google_sheets4::{Sheets, api::ValueRange};
-// use google_apis_common::auth::{ServiceAccountAuthenticator, ServiceAccountKey};
-use anyhow::Context;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
 
+use crate::auth::Claims;
+use crate::config::Config;
+use crate::entities::{self, EntityDef};
+use crate::import::{self, ImportResponse};
+use crate::import_batch::{DuplicateCheck, ImportOptions, ImportRow, RowRecord};
+use crate::import_jobs::NewJob;
+use crate::import_tasks::{self, ImportRun};
+use crate::{relationships, ApiState};
+
+/// Read when the request names no range: the first sheet, header row first
+const DEFAULT_PARTICIPANT_RANGE: &str = "A1:Z";
+
+type SheetsHub = Sheets<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>;
+
+/// A participant list kept in Google Sheets, e.g. a Meetup sign-up export.
+/// Participants are imported into `contacts`, matched on email, and linked to
+/// `project_id` and/or `event_id` when given.
 #[derive(Deserialize)]
 pub struct MeetupRequest {
-    #[allow(dead_code)]
+    /// Link to the spreadsheet, or its ID
     meetup_link: String,
+    /// A1 range holding the participants, e.g. `Participants!A1:F`, with the
+    /// column headers in its first row
+    range: Option<String>,
+    project_id: Option<Uuid>,
+    event_id: Option<Uuid>,
+    #[serde(flatten)]
+    options: ImportOptions,
+}
+
+#[derive(Serialize)]
+struct MeetupResponse {
+    #[serde(flatten)]
+    import: ImportResponse,
+    participants: usize,
+    /// Contacts newly linked to the project and event
+    #[serde(skip_serializing_if = "Option::is_none")]
+    linked_contacts: Option<u64>,
+}
+
+/// The ID in a `docs.google.com/spreadsheets/d/{id}/edit` link, or a bare ID
+fn spreadsheet_id(link: &str) -> Option<&str> {
+    let link = link.trim();
+    match link.split_once("/spreadsheets/d/") {
+        Some((_, rest)) => rest.split(['/', '?', '#']).next().filter(|id| !id.is_empty()),
+        None => (!link.is_empty() && !link.contains('/')).then_some(link),
+    }
+}
+
+/// A Sheets client signed in with the configured service account
+async fn sheets_hub(config: &Config) -> anyhow::Result<SheetsHub> {
+    let Some(key_path) = &config.google_service_key else {
+        bail!("GOOGLE_SERVICE_KEY is not configured");
+    };
+    let key = oauth2::read_service_account_key(key_path)
+        .await
+        .with_context(|| format!("Cannot read service account key {}", key_path.display()))?;
+    let auth = oauth2::ServiceAccountAuthenticator::builder(key)
+        .build()
+        .await
+        .context("Service account authentication failed")?;
+    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()?
+        .https_or_http()
+        .enable_http1()
+        .build();
+    let mut hub = Sheets::new(hyper::Client::builder().build(connector), auth);
+    let mut base_url = config.google_sheets_base_url.clone();
+    if !base_url.ends_with('/') {
+        base_url.push('/');
+    }
+    hub.base_url(base_url);
+    Ok(hub)
+}
+
+/// Cell values of `range`, row by row, as the sheet displays them
+async fn read_range(hub: &SheetsHub, spreadsheet_id: &str, range: &str) -> anyhow::Result<Vec<Vec<Value>>> {
+    let (_, values) = hub
+        .spreadsheets()
+        .values_get(spreadsheet_id, range)
+        .value_render_option("FORMATTED_VALUE")
+        .add_scope(Scope::SpreadsheetReadonly)
+        .doit()
+        .await
+        .with_context(|| format!("Cannot read {} of spreadsheet {}", range, spreadsheet_id))?;
+    Ok(values.values.unwrap_or_default())
+}
+
+/// One import row per participant, numbered as rows of the range
+fn participant_rows(values: &[Vec<Value>]) -> Vec<ImportRow> {
+    let Some((header_row, rows)) = values.split_first() else {
+        return Vec::new();
+    };
+    let headers: Vec<String> = header_row.iter().map(|h| h.as_str().unwrap_or("").trim().to_string()).collect();
+    rows.iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let cells: Vec<(String, Value)> = headers
+                .iter()
+                .enumerate()
+                .filter(|(_, header)| !header.is_empty())
+                .map(|(col, header)| (header.clone(), row.get(col).cloned().unwrap_or(Value::Null)))
+                .collect();
+            let blank = cells.iter().all(|(_, v)| v.is_null() || v.as_str().is_some_and(|s| s.trim().is_empty()));
+            let record = if blank {
+                RowRecord::Empty
+            } else {
+                let fields: HashMap<String, Value> = cells.iter().cloned().collect();
+                RowRecord::Record(import::contact_from_json(&fields))
+            };
+            ImportRow::new(index + 2, record).with_source(cells)
+        })
+        .collect()
+}
+
+async fn record_exists(data: &ApiState, entity: &EntityDef, id: Uuid) -> std::result::Result<(), HttpResponse> {
+    let exists = sqlx::query_scalar::<_, bool>(&format!("SELECT EXISTS(SELECT 1 FROM {} WHERE id = $1)", entity.name))
+        .bind(id)
+        .fetch_one(&data.db)
+        .await;
+    match exists {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(entities::not_found(entity, id)),
+        Err(e) => Err(entities::database_error(e)),
+    }
+}
+
+/// Contacts the import wrote, and the existing contacts it matched by email
+async fn participant_contacts(data: &ApiState, job_id: Uuid, emails: &[String]) -> std::result::Result<Vec<Uuid>, sqlx::Error> {
+    sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT record_id FROM import_job_rows WHERE job_id = $1
+        UNION
+        SELECT id FROM contacts WHERE email = ANY($2)
+        "#
+    )
+    .bind(job_id)
+    .bind(emails)
+    .fetch_all(&data.db)
+    .await
 }
 
-// Get participant list from Google Meetup
+// Import the participant list of a Google Sheet into contacts
 pub async fn get_meetup_participants(
-    _data: web::Data<std::sync::Arc<ApiState>>,
-    _req: web::Json<MeetupRequest>,
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    req: web::Json<MeetupRequest>,
 ) -> Result<HttpResponse> {
-    // TODO: Re-enable when Google Sheets dependencies are properly configured
-    Ok(HttpResponse::Ok().json(json!({
-        "success": false,
-        "error": "Google Sheets integration not yet configured"
-    })))
+    let bad_request = |error: String| HttpResponse::BadRequest().json(json!({ "success": false, "error": error }));
+    let Some(sheet_id) = spreadsheet_id(&req.meetup_link) else {
+        return Ok(bad_request(format!("'{}' is not a Google Sheets link or spreadsheet ID", req.meetup_link)));
+    };
+    if req.options.background {
+        return Ok(bad_request("Participant imports cannot run in the background".to_string()));
+    }
+    for (entity, id) in [(&entities::PROJECTS, req.project_id), (&entities::EVENTS, req.event_id)] {
+        if let Some(id) = id {
+            if let Err(response) = record_exists(&data, entity, id).await {
+                return Ok(response);
+            }
+        }
+    }
+    let duplicates = match DuplicateCheck::for_import(&entities::CONTACTS, DuplicateCheck::Columns(vec!["email"]), &req.options) {
+        Ok(duplicates) => duplicates,
+        Err(errors) => return Ok(bad_request(errors.join("; "))),
+    };
+
+    let range = req.range.as_deref().unwrap_or(DEFAULT_PARTICIPANT_RANGE);
+    let values = match sheets_hub(&data.config).await {
+        Ok(hub) => read_range(&hub, sheet_id, range).await,
+        Err(e) => return Ok(bad_request(format!("{:#}", e))),
+    };
+    let values = match values {
+        Ok(values) => values,
+        Err(e) => {
+            return Ok(HttpResponse::BadGateway().json(json!({
+                "success": false,
+                "error": format!("{:#}", e)
+            })));
+        }
+    };
+
+    let rows = participant_rows(&values);
+    let participants = rows.iter().filter(|row| !matches!(row.record, RowRecord::Empty)).count();
+    let job = NewJob {
+        source: "google_sheets".to_string(),
+        file_name: Some(format!("{} {}", sheet_id, range)),
+        file_hash: serde_json::to_vec(&values).ok().map(|bytes| NewJob::hash_of(&bytes)),
+        profile: None,
+        column_mappings: None,
+    };
+    let duplicate_check_columns = duplicates.label();
+    let emails: Vec<(usize, String)> = rows
+        .iter()
+        .filter_map(|row| match &row.record {
+            RowRecord::Record(record) => record
+                .get("email")
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|email| !email.is_empty())
+                .map(|email| (row.row_number, email.to_string())),
+            _ => None,
+        })
+        .collect();
+    let run = ImportRun {
+        job,
+        entity: &entities::CONTACTS,
+        user_id: claims.user_id(),
+        rows,
+        duplicates,
+        options: req.options.clone(),
+    };
+    let (job_id, outcome) = match import_tasks::start(&data, run).await {
+        Ok((job_id, Some(outcome))) => (job_id, outcome),
+        Ok((job_id, None)) => return Ok(import_tasks::accepted(job_id)),
+        Err(response) => return Ok(response),
+    };
+
+    let mut linked_contacts = None;
+    if !outcome.rolled_back && (req.project_id.is_some() || req.event_id.is_some()) {
+        // Rejected rows are not participants, even when their email matches a contact
+        let emails: Vec<String> = emails
+            .into_iter()
+            .filter(|(row, _)| !outcome.rejections.iter().any(|r| r.row == *row))
+            .map(|(_, email)| email)
+            .collect();
+        let contacts = match participant_contacts(&data, job_id, &emails).await {
+            Ok(contacts) => contacts,
+            Err(e) => return Ok(entities::database_error(e)),
+        };
+        let mut linked = 0;
+        for (entity, id) in [(&entities::PROJECTS, req.project_id), (&entities::EVENTS, req.event_id)] {
+            let Some(id) = id else { continue };
+            match relationships::link_many(&data.db, entity, id, &entities::CONTACTS, &contacts).await {
+                Ok(count) => linked += count,
+                Err(e) => return Ok(entities::database_error(e)),
+            }
+        }
+        linked_contacts = Some(linked);
+    }
+
+    Ok(HttpResponse::Ok().json(MeetupResponse {
+        import: ImportResponse::from_outcome(job_id, outcome, entities::CONTACTS.name, duplicate_check_columns, &req.options),
+        participants,
+        linked_contacts,
+    }))
 }
 
 #[derive(Debug, Serialize)]
@@ -81,24 +313,19 @@ impl std::error::Error for GeminiErrorDetails {}
 
 // Test Gemini API configuration
 pub async fn test_gemini_config(data: web::Data<std::sync::Arc<ApiState>>) -> Result<HttpResponse> {
-    let api_key_present = !data.config.gemini_api_key.is_empty() 
-        && data.config.gemini_api_key != "dummy_key"
-        && data.config.gemini_api_key != "get-key-at-aistudio.google.com";
+    let api_key_present = data.config.gemini_api_key.is_some();
     
-    let api_key_preview = if api_key_present {
-        let key = &data.config.gemini_api_key;
+    let api_key_preview = data.config.gemini_api_key.as_ref().map(|key| {
         if key.len() > 8 {
-            Some(format!("{}...{}", &key[..4], &key[key.len()-4..]))
+            format!("{}...{}", &key[..4], &key[key.len()-4..])
         } else {
-            Some("***".to_string())
+            "***".to_string()
         }
-    } else {
-        None
-    };
+    });
     
-    let (success, message, error) = if api_key_present {
+    let (success, message, error) = if let Some(key) = &data.config.gemini_api_key {
         // Test the API key by making a simple request
-        match test_gemini_api_key(&data.config.gemini_api_key).await {
+        match test_gemini_api_key(key).await {
             Ok(()) => (true, "Gemini API key is valid and working".to_string(), None),
             Err(e) => (false, "Gemini API key present but test failed".to_string(), Some(e.to_string())),
         }
@@ -139,11 +366,7 @@ pub async fn analyze_with_gemini(
     data: web::Data<std::sync::Arc<ApiState>>,
     req: web::Json<GeminiAnalysisRequest>,
 ) -> Result<HttpResponse> {
-    let api_key_present = !data.config.gemini_api_key.is_empty() 
-        && data.config.gemini_api_key != "dummy_key"
-        && data.config.gemini_api_key != "get-key-at-aistudio.google.com";
-    
-    if !api_key_present {
+    let Some(api_key) = &data.config.gemini_api_key else {
         return Ok(HttpResponse::BadRequest().json(GeminiAnalysisResponse {
             success: false,
             analysis: None,
@@ -151,9 +374,9 @@ pub async fn analyze_with_gemini(
             error_details: None,
             token_usage: None,
         }));
-    }
+    };
 
-    match call_gemini_api(&data.config.gemini_api_key, &req.prompt).await {
+    match call_gemini_api(api_key, &req.prompt).await {
         Ok((analysis, token_usage)) => Ok(HttpResponse::Ok().json(GeminiAnalysisResponse {
             success: true,
             analysis: Some(analysis),
@@ -276,16 +499,16 @@ async fn call_gemini_api(api_key: &str, prompt: &str) -> anyhow::Result<(String,
     // Extract token usage information
     let token_usage = response_json
         .get("usageMetadata")
-        .and_then(|usage| {
+        .map(|usage| {
             let prompt_tokens = usage.get("promptTokenCount").and_then(|v| v.as_u64()).map(|v| v as u32);
             let completion_tokens = usage.get("candidatesTokenCount").and_then(|v| v.as_u64()).map(|v| v as u32);
             let total_tokens = usage.get("totalTokenCount").and_then(|v| v.as_u64()).map(|v| v as u32);
             
-            Some(TokenUsage {
+            TokenUsage {
                 prompt_tokens,
                 completion_tokens,
                 total_tokens,
-            })
+            }
         });
     
     if let Some(ref usage) = token_usage {
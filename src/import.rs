@@ -1,29 +1,91 @@
 // src/import.rs
-use calamine::{Reader, Xlsx, open_workbook, Data};
+use calamine::Data;
 use serde::{Deserialize, Serialize};
-use sqlx::{Pool, Postgres};
+use serde_json::json;
 use std::collections::HashMap;
 use actix_web::{web, HttpResponse, Result};
 use uuid::Uuid;
-use chrono::Utc;
 
+use crate::auth::Claims;
+use crate::democracylab::DemocracyLabProject;
+use crate::duplicates;
+use crate::entities::{self, EntityDef};
+use crate::import_batch::{DuplicateCheck, ImportOptions, ImportOutcome, ImportRow, Rejection, RowError, RowRecord, RowReport};
+use crate::import_jobs::NewJob;
+use crate::import_mapping::{self, ColumnMappings, ImportProfile, ResolvedMapping};
+use crate::import_tasks::{self, ImportRun};
+use crate::import_validation::{self, TableSchema};
+use crate::spreadsheet::{self, SheetData};
+use crate::uploads::UploadInfo;
+
+/// Spreadsheet import of a file staged through `/api/import/uploads`.
+/// `column_mappings` (source header -> target column) take precedence over
+/// the mappings of a saved `profile`; with neither, headers that name a
+/// column of `table_name` are imported. `mode`, `batch_size` and `dry_run`
+/// control how rows are written (see `ImportOptions`).
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ImportRequest {
-    pub file_path: String,
+    pub upload_id: Uuid,
     pub sheet_name: Option<String>,
     pub table_name: String,
     pub column_mappings: Option<HashMap<String, String>>,
+    pub profile: Option<String>,
+    #[serde(flatten)]
+    pub options: ImportOptions,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ImportResponse {
     pub success: bool,
     pub message: String,
+    /// The import job recording this run, see `/api/import/jobs`
+    pub job_id: Option<Uuid>,
     pub records_processed: Option<usize>,
     pub records_inserted: Option<usize>,
+    pub records_updated: Option<usize>,
     pub records_skipped: Option<usize>,
+    pub records_rejected: Option<usize>,
     pub duplicate_check_columns: Option<String>,
+    pub dry_run: bool,
+    pub rolled_back: bool,
+    pub batches: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rows: Option<Vec<RowReport>>,
     pub errors: Vec<String>,
+    /// `errors` by row and column, with error codes
+    pub row_errors: Vec<RowError>,
+    /// CSV of the rejected rows, when there are any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejected_rows_url: Option<String>,
+}
+
+impl ImportResponse {
+    pub fn from_outcome(
+        job_id: Uuid,
+        outcome: ImportOutcome,
+        table: &str,
+        duplicate_check_columns: Option<String>,
+        options: &ImportOptions,
+    ) -> Self {
+        ImportResponse {
+            success: outcome.succeeded(options),
+            message: outcome.message(table, options),
+            job_id: Some(job_id),
+            records_processed: Some(outcome.processed),
+            records_inserted: Some(outcome.inserted),
+            records_updated: Some(outcome.updated),
+            records_skipped: Some(outcome.skipped),
+            records_rejected: Some(outcome.rejected),
+            duplicate_check_columns,
+            dry_run: options.dry_run,
+            rolled_back: outcome.rolled_back,
+            batches: Some(outcome.batches),
+            rows: outcome.rows,
+            errors: outcome.errors,
+            row_errors: outcome.row_errors,
+            rejected_rows_url: rejected_rows_url(job_id, &outcome.rejections),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -49,173 +111,388 @@ pub struct DataImportRequest {
     pub table_name: String,
     pub source: String,
     pub file_source: String,
+    #[serde(flatten)]
+    pub options: ImportOptions,
 }
 
 #[derive(Debug, Serialize)]
 pub struct DataImportResponse {
     pub success: bool,
     pub message: String,
+    pub job_id: Option<Uuid>,
     pub imported_count: Option<usize>,
+    pub updated_count: Option<usize>,
     pub skipped_count: Option<usize>,
+    pub rejected_count: Option<usize>,
     pub duplicate_check_columns: Option<String>,
+    pub dry_run: bool,
+    pub rolled_back: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rows: Option<Vec<RowReport>>,
     pub errors: Vec<String>,
+    pub row_errors: Vec<RowError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejected_rows_url: Option<String>,
+    /// Problems that did not stop a row, e.g. an account name that matched
+    /// no account
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct DemocracyLabProject {
-    #[serde(rename = "project_name")]
-    pub name: String,
-    #[serde(rename = "project_description")]
-    pub description: Option<String>,
-    #[serde(rename = "project_url")]
-    pub url: Option<String>,
+/// Headers of the DFC active projects sheet, imported into projects by
+/// `dfc_import_row` when no mapping is given
+const DFC_HEADERS: &[&str] = &["fiscal year", "project number", "project name", "committed"];
+
+/// What an import request resolves to once the sheet and any profile are read
+pub enum PreparedImport {
+    /// Records with the sheet row they came from
+    Dfc(SheetData, Vec<(usize, ProjectRecord)>),
+    Mapped(SheetData, ResolvedMapping),
 }
 
-/// Import Excel data into the projects table
-pub async fn import_excel_data(
-    pool: web::Data<std::sync::Arc<crate::ApiState>>,
-    req: web::Json<ImportRequest>,
-) -> Result<HttpResponse> {
-    let mut errors = Vec::new();
-    
-    // Read Excel file
-    let records = match read_excel_file(&req.file_path, req.sheet_name.as_deref()) {
-        Ok(data) => data,
+pub fn import_error(message: String, errors: Vec<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(ImportResponse {
+        success: false,
+        message,
+        job_id: None,
+        records_processed: None,
+        records_inserted: None,
+        records_updated: None,
+        records_skipped: None,
+        records_rejected: None,
+        duplicate_check_columns: None,
+        dry_run: false,
+        rolled_back: false,
+        batches: None,
+        rows: None,
+        errors,
+        row_errors: Vec::new(),
+        rejected_rows_url: None,
+    })
+}
+
+fn data_import_error(message: String, errors: Vec<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(DataImportResponse {
+        success: false,
+        message,
+        job_id: None,
+        imported_count: None,
+        updated_count: None,
+        skipped_count: None,
+        rejected_count: None,
+        duplicate_check_columns: None,
+        dry_run: false,
+        rolled_back: false,
+        rows: None,
+        errors,
+        row_errors: Vec::new(),
+        rejected_rows_url: None,
+        warnings: Vec::new(),
+    })
+}
+
+fn rejected_rows_url(job_id: Uuid, rejections: &[Rejection]) -> Option<String> {
+    (!rejections.is_empty()).then(|| format!("/api/import/jobs/{}/rejected", job_id))
+}
+
+/// Load the profile named in an import request, which must target `table_name`
+pub async fn load_profile(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    name: Option<&str>,
+    table_name: &str,
+) -> std::result::Result<Option<ImportProfile>, HttpResponse> {
+    let Some(name) = name else {
+        return Ok(None);
+    };
+    let profile = match import_mapping::find_profile(pool, name).await {
+        Ok(Some(profile)) => profile,
+        Ok(None) => {
+            return Err(import_error(
+                format!("Import profile '{}' not found", name),
+                vec![format!("Import profile '{}' not found", name)],
+            ))
+        }
+        Err(e) => return Err(entities::database_error(e)),
+    };
+    if profile.table_name != table_name {
+        let error = format!("Import profile '{}' targets {}, not {}", profile.name, profile.table_name, table_name);
+        return Err(import_error(error.clone(), vec![error]));
+    }
+    Ok(Some(profile))
+}
+
+/// Settle the column mapping of a sheet to import into `entity`
+pub fn prepare_sheet(
+    entity: &'static EntityDef,
+    sheet: SheetData,
+    mappings: Option<ColumnMappings>,
+) -> std::result::Result<PreparedImport, HttpResponse> {
+    if mappings.is_none() && entity.name == entities::PROJECTS.name && is_dfc_sheet(&sheet.headers) {
+        let records = dfc_records(&sheet);
+        return Ok(PreparedImport::Dfc(sheet, records));
+    }
+
+    match ResolvedMapping::resolve(entity, &sheet.headers, mappings.as_ref()) {
+        Ok(mapping) => Ok(PreparedImport::Mapped(sheet, mapping)),
+        Err(errors) => Err(import_error(format!("Column mapping for {} is not valid", entity.name), errors)),
+    }
+}
+
+/// Read the sheet and settle the target table and column mapping
+async fn prepare_import(
+    state: &crate::ApiState,
+    claims: &Claims,
+    req: &ImportRequest,
+) -> std::result::Result<(UploadInfo, PreparedImport), HttpResponse> {
+    let (upload, path) = state.uploads.get(req.upload_id, claims.sub).map_err(|e| e.to_response())?;
+    let profile = load_profile(&state.db, req.profile.as_deref(), &req.table_name).await?;
+
+    let entity = match import_mapping::importable_table(&req.table_name) {
+        Ok(entity) => entity,
+        Err(e) => return Err(import_error(e.clone(), vec![e])),
+    };
+
+    let sheet_name = req.sheet_name.as_deref()
+        .or_else(|| profile.as_ref().and_then(|p| p.sheet_name.as_deref()));
+    let sheet = match spreadsheet::read_sheet(&path, sheet_name) {
+        Ok(sheet) => sheet,
         Err(e) => {
-            return Ok(HttpResponse::BadRequest().json(ImportResponse {
-                success: false,
-                message: format!("Failed to read Excel file at '{}': {}", req.file_path, e),
-                records_processed: None,
-                records_inserted: None,
-                records_skipped: None,
-                duplicate_check_columns: None,
-                errors: vec![format!("File path: {} - {}", req.file_path, e.to_string())],
-            }));
+            return Err(import_error(
+                format!("Failed to read spreadsheet '{}': {}", upload.file_name, e),
+                vec![format!("Upload {}: {}", upload.id, e)],
+            ))
         }
     };
 
-    // Process and insert records
-    let mut inserted_count = 0;
-    let mut skipped_count = 0;
-    let total_records = records.len();
-
-    for (index, record) in records.iter().enumerate() {
-        match insert_project_record(&pool.db, record).await {
-            Ok(InsertResult::Inserted) => inserted_count += 1,
-            Ok(InsertResult::Skipped) => skipped_count += 1,
-            Err(e) => {
-                errors.push(format!("Row {}: {}", index + 1, e));
-            }
+    let mappings: Option<ColumnMappings> = req.column_mappings.clone()
+        .or_else(|| profile.map(|p| p.column_mappings));
+    Ok((upload, prepare_sheet(entity, sheet, mappings)?))
+}
+
+/// The rows of a prepared sheet, the table they go to and the duplicate
+/// check used unless the request names match columns. A mapping is recorded
+/// on `job`.
+pub fn prepared_rows(prepared: PreparedImport, job: &mut NewJob) -> (&'static EntityDef, Vec<ImportRow>, DuplicateCheck) {
+    match prepared {
+        PreparedImport::Dfc(sheet, records) => {
+            let rows = records
+                .iter()
+                .map(|(row_number, record)| {
+                    let source = import_mapping::source_cells(&sheet.headers, &sheet.rows[row_number - 2]);
+                    dfc_import_row(*row_number, record).with_source(source)
+                })
+                .collect();
+            (&entities::PROJECTS, rows, DuplicateCheck::Name { columns: &["region", "department"] })
+        }
+        PreparedImport::Mapped(sheet, mapping) => {
+            job.column_mappings = Some(json!(mapping.describe(&sheet.headers)));
+            let rows: Vec<ImportRow> = sheet.rows.iter().enumerate().map(|(index, row)| {
+                // Row numbers as shown in the spreadsheet, below the header row
+                let record = match mapping.row_json(row) {
+                    Some(Ok(record)) => RowRecord::Record(record),
+                    Some(Err(errors)) => RowRecord::Invalid(errors),
+                    None => RowRecord::Empty,
+                };
+                ImportRow::new(index + 2, record).with_source(import_mapping::source_cells(&sheet.headers, row))
+            }).collect();
+            (mapping.entity, rows, DuplicateCheck::None)
         }
     }
+}
 
-    let message = if errors.is_empty() {
-        if skipped_count > 0 {
-            format!("Successfully imported {} records, skipped {} duplicates", inserted_count, skipped_count)
-        } else {
-            format!("Successfully imported {} records", inserted_count)
-        }
-    } else {
-        format!("Imported {} of {} records with {} errors, skipped {} duplicates", 
-                inserted_count, total_records, errors.len(), skipped_count)
+/// Import an uploaded sheet into `table_name`
+pub async fn import_excel_data(
+    pool: web::Data<std::sync::Arc<crate::ApiState>>,
+    claims: Claims,
+    req: web::Json<ImportRequest>,
+) -> Result<HttpResponse> {
+    let (upload, prepared) = match prepare_import(&pool, &claims, &req).await {
+        Ok(prepared) => prepared,
+        Err(response) => return Ok(response),
+    };
+    let mut job = NewJob {
+        source: "excel".to_string(),
+        file_name: Some(upload.file_name),
+        file_hash: upload.sha256,
+        profile: req.profile.clone(),
+        column_mappings: None,
+    };
+    let (entity, rows, default_check) = prepared_rows(prepared, &mut job);
+    let duplicates = match DuplicateCheck::for_import(entity, default_check, &req.options) {
+        Ok(duplicates) => duplicates,
+        Err(errors) => return Ok(import_error(format!("Match columns for {} are not valid", entity.name), errors)),
     };
 
-    Ok(HttpResponse::Ok().json(ImportResponse {
-        success: errors.is_empty() || inserted_count > 0,
-        message,
-        records_processed: Some(total_records),
-        records_inserted: Some(inserted_count),
-        records_skipped: Some(skipped_count),
-        duplicate_check_columns: Some("Name + Region + Department".to_string()),
-        errors,
-    }))
+    let duplicate_check_columns = duplicates.label();
+    let run = ImportRun {
+        job,
+        entity,
+        user_id: claims.user_id(),
+        rows,
+        duplicates,
+        options: req.options.clone(),
+    };
+    let (job_id, outcome) = match import_tasks::start(&pool, run).await {
+        Ok((job_id, Some(outcome))) => (job_id, outcome),
+        Ok((job_id, None)) => return Ok(import_tasks::accepted(job_id)),
+        Err(response) => return Ok(response),
+    };
+    Ok(HttpResponse::Ok().json(ImportResponse::from_outcome(
+        job_id,
+        outcome,
+        entity.name,
+        duplicate_check_columns,
+        &req.options,
+    )))
+}
+
+/// A DFC sheet row as a project
+fn dfc_import_row(row_number: usize, record: &ProjectRecord) -> ImportRow {
+    // Set priority based on committed amount
+    let priority = match record.committed {
+        Some(amount) if amount >= 10_000_000.0 => Some("High"),
+        Some(amount) if amount >= 1_000_000.0 => Some("Medium"),
+        Some(_) => Some("Low"),
+        None => None,
+    };
+
+    // Set status based on project type
+    let status = match &record.project_type {
+        Some(pt) if pt.to_lowercase().contains("active") => "Active",
+        Some(pt) if pt.to_lowercase().contains("planned") => "Planning",
+        Some(pt) if pt.to_lowercase().contains("completed") => "Completed",
+        _ => "Active", // Default status
+    };
+
+    let mut project = serde_json::Map::new();
+    project.insert("name".to_string(), json!(record.project_name));
+    project.insert("description".to_string(), json!(record.project_description));
+    project.insert("status".to_string(), json!(status));
+    project.insert("priority".to_string(), json!(priority));
+    project.insert("fiscal_year".to_string(), json!(record.fiscal_year));
+    project.insert("project_number".to_string(), json!(record.project_number));
+    project.insert("committed_amount".to_string(), json!(record.committed));
+    project.insert("country".to_string(), json!(record.country));
+    project.insert("region".to_string(), json!(record.region));
+    project.insert("department".to_string(), json!(record.department));
+    project.insert("framework".to_string(), json!(record.framework));
+    project.insert("naics_sector".to_string(), json!(record.naics_sector));
+    project.insert("profile_url".to_string(), json!(record.project_profile_url));
+
+    ImportRow::new(row_number, RowRecord::Record(project))
 }
 
-/// Preview Excel data without importing
 pub async fn preview_excel_data(
+    pool: web::Data<std::sync::Arc<crate::ApiState>>,
+    claims: Claims,
     req: web::Json<ImportRequest>,
 ) -> Result<HttpResponse> {
-    println!("Preview request - file_path: {}, sheet_name: {:?}", req.file_path, req.sheet_name);
-    let records = match read_excel_file(&req.file_path, req.sheet_name.as_deref()) {
-        Ok(data) => data,
-        Err(e) => {
-            return Ok(HttpResponse::BadRequest().json(ImportResponse {
-                success: false,
-                message: format!("Failed to read Excel file at '{}': {}", req.file_path, e),
-                records_processed: None,
-                records_inserted: None,
-                records_skipped: None,
-                duplicate_check_columns: None,
-                errors: vec![format!("File path: {} - {}", req.file_path, e.to_string())],
+    println!("Preview request - upload: {}, sheet_name: {:?}", req.upload_id, req.sheet_name);
+    let prepared = match prepare_import(&pool, &claims, &req).await {
+        Ok((_, prepared)) => prepared,
+        Err(response) => return Ok(response),
+    };
+    match preview_json(&pool.db, prepared).await {
+        Ok(preview) => Ok(HttpResponse::Ok().json(preview)),
+        Err(response) => Ok(response),
+    }
+}
+
+/// The first records of a prepared sheet as they would be inserted, with the
+/// problems every row would hit
+pub async fn preview_json(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    prepared: PreparedImport,
+) -> std::result::Result<serde_json::Value, HttpResponse> {
+    let (sheet, mapping) = match prepared {
+        PreparedImport::Dfc(_, records) => {
+            // Return first 10 records for preview
+            let preview_records: Vec<&ProjectRecord> = records.iter().take(10).map(|(_, record)| record).collect();
+            return Ok(serde_json::json!({
+                "success": true,
+                "message": format!("Preview of {} records (showing first 10)", records.len()),
+                "total_records": records.len(),
+                "preview": preview_records
             }));
         }
+        PreparedImport::Mapped(sheet, mapping) => (sheet, mapping),
     };
 
-    // Return first 10 records for preview
-    let preview_records: Vec<&ProjectRecord> = records.iter().take(10).collect();
-    
-    Ok(HttpResponse::Ok().json(serde_json::json!({
+    let schema = match TableSchema::load(pool, mapping.entity.name).await {
+        Ok(schema) => schema,
+        Err(e) => return Err(entities::database_error(e)),
+    };
+    let mut preview = Vec::new();
+    let mut errors = Vec::new();
+    let mut row_errors = Vec::new();
+    for (index, row) in sheet.rows.iter().enumerate() {
+        let problems = match mapping.row_json(row) {
+            Some(Ok(record)) => {
+                let problems = import_validation::validate_row(mapping.entity, &schema, &record).err();
+                if preview.len() < 10 {
+                    preview.push(record);
+                }
+                problems
+            }
+            Some(Err(problems)) => Some(problems),
+            None => None,
+        };
+        if let Some(problems) = problems {
+            errors.push(format!("Row {}: {}", index + 2, import_validation::summary(&problems)));
+            row_errors.extend(problems.into_iter().map(|error| RowError { row: index + 2, error }));
+        }
+    }
+
+    Ok(serde_json::json!({
         "success": true,
-        "message": format!("Preview of {} records (showing first 10)", records.len()),
-        "total_records": records.len(),
-        "preview": preview_records
-    })))
+        "message": format!("Preview of {} records (showing first 10)", sheet.rows.len()),
+        "total_records": sheet.rows.len(),
+        "table_name": mapping.entity.name,
+        "column_mappings": mapping.describe(&sheet.headers),
+        "unmapped_columns": mapping.unmapped,
+        "preview": preview,
+        "errors": errors,
+        "row_errors": row_errors
+    }))
 }
 
-/// Get Excel file sheets
+#[derive(Debug, Deserialize)]
+pub struct SheetsRequest {
+    pub upload_id: Uuid,
+}
+
+/// Get the sheets of an uploaded file
 pub async fn get_excel_sheets(
-    req: web::Json<serde_json::Value>,
+    pool: web::Data<std::sync::Arc<crate::ApiState>>,
+    claims: Claims,
+    req: web::Json<SheetsRequest>,
 ) -> Result<HttpResponse> {
-    let file_path = match req.get("file_path").and_then(|v| v.as_str()) {
-        Some(path) => {
-            println!("Sheets request - file_path: {}", path);
-            path
-        },
-        None => {
-            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                "success": false,
-                "message": "file_path is required"
-            })));
-        }
+    let (upload, path) = match pool.uploads.get(req.upload_id, claims.sub) {
+        Ok(found) => found,
+        Err(e) => return Ok(e.to_response()),
     };
 
-    match get_excel_sheet_names(file_path) {
+    match spreadsheet::sheet_names(&path) {
         Ok(sheets) => Ok(HttpResponse::Ok().json(serde_json::json!({
             "success": true,
             "sheets": sheets
         }))),
         Err(e) => Ok(HttpResponse::BadRequest().json(serde_json::json!({
             "success": false,
-            "message": format!("Failed to read Excel file at '{}': {}", file_path, e)
+            "message": format!("Failed to read spreadsheet '{}': {}", upload.file_name, e)
         })))
     }
 }
 
-fn read_excel_file(file_path: &str, sheet_name: Option<&str>) -> Result<Vec<ProjectRecord>, Box<dyn std::error::Error>> {
-    let mut workbook: Xlsx<_> = open_workbook(file_path)
-        .map_err(|e| format!("File not found at: {} - {}", file_path, e))?;
-    
-    let sheet_name = match sheet_name {
-        Some(name) => name.to_string(),
-        None => workbook.sheet_names().get(0).unwrap_or(&"Sheet1".to_string()).clone(),
-    };
-
-    let range = workbook.worksheet_range(&sheet_name)
-        .map_err(|e| format!("Error reading sheet: {}", e))?;
+fn is_dfc_sheet(headers: &[String]) -> bool {
+    DFC_HEADERS.iter().all(|dfc| headers.iter().any(|h| h.eq_ignore_ascii_case(dfc)))
+}
 
+fn dfc_records(sheet: &SheetData) -> Vec<(usize, ProjectRecord)> {
+    let headers: Vec<String> = sheet.headers.iter().map(|h| h.to_lowercase()).collect();
     let mut records = Vec::new();
-    let mut headers = HashMap::new();
-    
-    // Get headers from first row
-    if let Some(first_row) = range.rows().next() {
-        for (col_idx, cell) in first_row.iter().enumerate() {
-            let header = cell.to_string().to_lowercase().trim().to_string();
-            headers.insert(col_idx, header);
-        }
-    }
 
-    // Process data rows (skip header row)
-    for row in range.rows().skip(1) {
+    for (index, row) in sheet.rows.iter().enumerate() {
         let mut record = ProjectRecord {
             fiscal_year: None,
             project_number: None,
@@ -232,7 +509,7 @@ fn read_excel_file(file_path: &str, sheet_name: Option<&str>) -> Result<Vec<Proj
         };
 
         for (col_idx, cell) in row.iter().enumerate() {
-            if let Some(header) = headers.get(&col_idx) {
+            if let Some(header) = headers.get(col_idx) {
                 let value = match cell {
                     Data::Empty => None,
                     Data::String(s) => if s.trim().is_empty() { None } else { Some(s.trim().to_string()) },
@@ -264,507 +541,396 @@ fn read_excel_file(file_path: &str, sheet_name: Option<&str>) -> Result<Vec<Proj
 
         // Only include records with at least a project name
         if record.project_name.is_some() {
-            records.push(record);
+            records.push((index + 2, record));
         }
     }
 
-    Ok(records)
-}
-
-fn get_excel_sheet_names(file_path: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let workbook: Xlsx<_> = open_workbook(file_path)
-        .map_err(|e| format!("File not found at: {} - {}", file_path, e))?;
-    Ok(workbook.sheet_names().clone())
-}
-
-#[derive(Debug)]
-enum InsertResult {
-    Inserted,
-    Skipped,
-}
-
-async fn insert_project_record(
-    pool: &Pool<Postgres>,
-    record: &ProjectRecord,
-) -> Result<InsertResult, sqlx::Error> {
-    // Check for existing record based on name, region, and department
-    let existing_count = sqlx::query_scalar::<_, i64>(
-        r#"
-        SELECT COUNT(*) FROM projects 
-        WHERE name = $1 
-        AND (description LIKE '%Region: ' || $2 || '%' OR $2 IS NULL)
-        AND (description LIKE '%Department: ' || $3 || '%' OR $3 IS NULL)
-        "#
-    )
-    .bind(&record.project_name)
-    .bind(&record.region)
-    .bind(&record.department)
-    .fetch_one(pool)
-    .await?;
-
-    if existing_count > 0 {
-        // Record already exists, skip insertion
-        println!("Skipping duplicate project: {} (Region: {:?}, Department: {:?})", 
-                 record.project_name.as_deref().unwrap_or("Unknown"),
-                 record.region,
-                 record.department);
-        return Ok(InsertResult::Skipped);
-    }
-
-    let id = Uuid::new_v4();
-    let now = Utc::now();
-    
-    // Create a description combining multiple fields
-    let mut description_parts = Vec::new();
-    
-    if let Some(desc) = &record.project_description {
-        description_parts.push(desc.clone());
-    }
-    
-    if let Some(dept) = &record.department {
-        description_parts.push(format!("Department: {}", dept));
-    }
-    
-    if let Some(region) = &record.region {
-        description_parts.push(format!("Region: {}", region));
-    }
-    
-    if let Some(country) = &record.country {
-        description_parts.push(format!("Country: {}", country));
-    }
-    
-    if let Some(framework) = &record.framework {
-        description_parts.push(format!("Framework: {}", framework));
-    }
-    
-    if let Some(naics) = &record.naics_sector {
-        description_parts.push(format!("NAICS Sector: {}", naics));
-    }
-    
-    if let Some(url) = &record.project_profile_url {
-        description_parts.push(format!("Profile URL: {}", url));
-    }
-    
-    let description = if description_parts.is_empty() {
-        None
-    } else {
-        Some(description_parts.join("
-
-"))
-    };
-
-    // Set priority based on committed amount
-    let priority = match record.committed {
-        Some(amount) if amount >= 10_000_000.0 => Some("High".to_string()),
-        Some(amount) if amount >= 1_000_000.0 => Some("Medium".to_string()),
-        Some(_) => Some("Low".to_string()),
-        None => None,
-    };
-
-    // Set status based on project type
-    let status = match &record.project_type {
-        Some(pt) if pt.to_lowercase().contains("active") => Some("Active".to_string()),
-        Some(pt) if pt.to_lowercase().contains("planned") => Some("Planning".to_string()),
-        Some(pt) if pt.to_lowercase().contains("completed") => Some("Completed".to_string()),
-        _ => Some("Active".to_string()), // Default status
-    };
-
-    sqlx::query(
-        r#"
-        INSERT INTO projects (
-            id, name, description, status, priority,
-            date_entered, date_modified, created_by, modified_user_id
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-        "#
-    )
-    .bind(id)
-    .bind(&record.project_name)
-    .bind(&description)
-    .bind(&status)
-    .bind(&priority)
-    .bind(now)
-    .bind(now)
-    .bind("excel-import") // Creator identifier
-    .bind("excel-import") // Modifier identifier
-    .execute(pool)
-    .await?;
-
-    Ok(InsertResult::Inserted)
+    records
 }
 
 /// Import JSON data directly into specified table
 pub async fn import_data(
     pool: web::Data<std::sync::Arc<crate::ApiState>>,
+    claims: Claims,
     req: web::Json<DataImportRequest>,
 ) -> Result<HttpResponse> {
-    let mut errors = Vec::new();
-    let mut imported_count = 0;
-    let mut skipped_count = 0;
-    let mut actual_duplicate_check_columns = None;
-    
     println!("Data import request - table: {}, source: {}, records: {}", 
         req.table_name, req.source, req.data.len());
-    
-    match req.table_name.as_str() {
-        "accounts" => {
-            for (index, record) in req.data.iter().enumerate() {
-                match import_account_record(&pool.db, record).await {
-                    Ok((InsertResult::Inserted, fields_used)) => {
-                        imported_count += 1;
-                        if actual_duplicate_check_columns.is_none() {
-                            actual_duplicate_check_columns = Some(fields_used);
-                        }
-                    },
-                    Ok((InsertResult::Skipped, fields_used)) => {
-                        skipped_count += 1;
-                        if actual_duplicate_check_columns.is_none() {
-                            actual_duplicate_check_columns = Some(fields_used);
-                        }
-                    },
-                    Err(e) => {
-                        let error_msg = format!("Row {}: {}", index + 1, e);
-                        println!("Import error: {}", error_msg);
-                        errors.push(error_msg);
-                    }
-                }
-            }
-        }
-        "projects" => {
-            for (index, record) in req.data.iter().enumerate() {
-                match import_project_record_from_json(&pool.db, record).await {
-                    Ok((InsertResult::Inserted, fields_used)) => {
-                        imported_count += 1;
-                        if actual_duplicate_check_columns.is_none() {
-                            actual_duplicate_check_columns = Some(fields_used);
-                        }
-                    },
-                    Ok((InsertResult::Skipped, fields_used)) => {
-                        skipped_count += 1;
-                        if actual_duplicate_check_columns.is_none() {
-                            actual_duplicate_check_columns = Some(fields_used);
-                        }
-                    },
-                    Err(e) => {
-                        let error_msg = format!("Row {}: {}", index + 1, e);
-                        println!("Import error: {}", error_msg);
-                        errors.push(error_msg);
-                    }
-                }
-            }
-        }
-        _ => {
-            errors.push(format!("Unsupported table: {}", req.table_name));
-        }
-    }
-    
-    let success = errors.is_empty() || (imported_count > 0 && errors.len() < req.data.len());
-    let message = if success {
-        if errors.is_empty() {
-            if skipped_count > 0 {
-                format!("Successfully imported {} records into {}, skipped {} duplicates", 
-                        imported_count, req.table_name, skipped_count)
-            } else {
-                format!("Successfully imported {} records into {}", imported_count, req.table_name)
+
+    let (entity, default_check, to_record): (_, _, RecordFromJson) =
+        match req.table_name.as_str() {
+            "accounts" => (&entities::ACCOUNTS, DuplicateCheck::Name { columns: &["industry"] }, account_from_json),
+            "projects" => (&entities::PROJECTS, DuplicateCheck::Name { columns: &[] }, project_from_json),
+            "contacts" => (&entities::CONTACTS, DuplicateCheck::Columns(vec!["email"]), contact_from_json),
+            "leads" => (&entities::LEADS, DuplicateCheck::Columns(vec!["email"]), lead_from_json),
+            "opportunities" => (&entities::OPPORTUNITIES, DuplicateCheck::Name { columns: &["account_id"] }, opportunity_from_json),
+            "events" => (&entities::EVENTS, DuplicateCheck::Name { columns: &["date_start"] }, event_from_json),
+            "products" => (&entities::PRODUCTS, DuplicateCheck::Name { columns: &["product_code"] }, product_from_json),
+            _ => {
+                return Ok(data_import_error(
+                    format!("Failed to import data into {}", req.table_name),
+                    vec![format!("Unsupported table: {}", req.table_name)],
+                ));
             }
-        } else {
-            format!("Imported {} of {} records into {} with {} errors, skipped {} duplicates", 
-                imported_count, req.data.len(), req.table_name, errors.len(), skipped_count)
+        };
+    let duplicates = match DuplicateCheck::for_import(entity, default_check, &req.options) {
+        Ok(duplicates) => duplicates,
+        Err(errors) => return Ok(data_import_error(format!("Match columns for {} are not valid", entity.name), errors)),
+    };
+
+    let mut records: Vec<_> = req.data.iter().map(to_record).collect();
+    let warnings = if entity.field("account_id").is_some() {
+        match with_account_ids(&pool.db, &req.data, &mut records).await {
+            Ok(warnings) => warnings,
+            Err(e) => return Ok(entities::database_error(e)),
         }
     } else {
-        format!("Failed to import data into {}", req.table_name)
+        Vec::new()
     };
-    
-    let duplicate_check_columns = actual_duplicate_check_columns.or_else(|| {
-        match req.table_name.as_str() {
-            "accounts" => Some("Name + Industry".to_string()),
-            "projects" => Some("Name + Region + Department".to_string()),
-            _ => None,
-        }
-    });
-    
+    for warning in &warnings {
+        println!("Import warning: {}", warning);
+    }
+    let rows: Vec<ImportRow> = records.into_iter().zip(&req.data).enumerate()
+        .map(|(index, (record, source))| {
+            ImportRow::new(index + 1, RowRecord::Record(record)).with_source(data_source(&req.headers, source))
+        })
+        .collect();
+    let job = NewJob {
+        source: req.source.clone(),
+        file_name: Some(req.file_source.clone()),
+        file_hash: serde_json::to_vec(&req.data).ok().map(|bytes| NewJob::hash_of(&bytes)),
+        profile: None,
+        column_mappings: None,
+    };
+    let duplicate_check_columns = duplicates.label();
+    let run = ImportRun {
+        job,
+        entity,
+        user_id: claims.user_id(),
+        rows,
+        duplicates,
+        options: req.options.clone(),
+    };
+    let (job_id, outcome) = match import_tasks::start(&pool, run).await {
+        Ok((job_id, Some(outcome))) => (job_id, outcome),
+        Ok((job_id, None)) => return Ok(import_tasks::accepted(job_id)),
+        Err(response) => return Ok(response),
+    };
+    for error in &outcome.errors {
+        println!("Import error: {}", error);
+    }
+
     Ok(HttpResponse::Ok().json(DataImportResponse {
-        success,
-        message,
-        imported_count: Some(imported_count),
-        skipped_count: Some(skipped_count),
+        success: outcome.succeeded(&req.options),
+        message: outcome.message(entity.name, &req.options),
+        job_id: Some(job_id),
+        imported_count: Some(outcome.inserted),
+        updated_count: Some(outcome.updated),
+        skipped_count: Some(outcome.skipped),
+        rejected_count: Some(outcome.rejected),
         duplicate_check_columns,
-        errors,
+        dry_run: req.options.dry_run,
+        rolled_back: outcome.rolled_back,
+        rejected_rows_url: rejected_rows_url(job_id, &outcome.rejections),
+        rows: outcome.rows,
+        errors: outcome.errors,
+        row_errors: outcome.row_errors,
+        warnings,
     }))
 }
 
-/// Helper function to import a single account record
-/// Returns (InsertResult, fields_used_for_duplicate_check)
-async fn import_account_record(
-    pool: &Pool<Postgres>,
+/// A posted record as it was given, in the order of `headers`
+fn data_source(headers: &[String], record: &HashMap<String, serde_json::Value>) -> Vec<(String, serde_json::Value)> {
+    if headers.is_empty() {
+        let mut source: Vec<_> = record.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        source.sort_by(|a, b| a.0.cmp(&b.0));
+        return source;
+    }
+    headers
+        .iter()
+        .map(|header| (header.clone(), record.get(header).cloned().unwrap_or(serde_json::Value::Null)))
+        .collect()
+}
+
+/// Builds a table's record from a CSV/JSON record with loosely named fields
+type RecordFromJson = fn(&HashMap<String, serde_json::Value>) -> serde_json::Map<String, serde_json::Value>;
+
+/// First of `keys` holding a string
+fn json_str<'a>(record: &'a HashMap<String, serde_json::Value>, keys: &[&str]) -> Option<&'a str> {
+    keys.iter().find_map(|key| record.get(*key).and_then(|v| v.as_str()))
+}
+
+/// An account from a CSV/JSON record with loosely named fields
+fn account_from_json(record: &HashMap<String, serde_json::Value>) -> serde_json::Map<String, serde_json::Value> {
+    let name = json_str(record, &["Name", "name"]).unwrap_or("Unknown");
+    let email = json_str(record, &["Email", "email"]);
+    let phone = json_str(record, &["Phone", "phone"]);
+    let website = json_str(record, &["Website", "website"]);
+    let industry = json_str(record, &["Industry", "industry", "Sector", "sector"]);
+
+    // Set account type based on available data
+    let account_type = if email.is_some() || phone.is_some() { "Customer" } else { "Prospect" };
+
+    let mut account = serde_json::Map::new();
+    account.insert("name".to_string(), json!(name));
+    account.insert("account_type".to_string(), json!(account_type));
+    account.insert("industry".to_string(), json!(industry));
+    account.insert("phone_office".to_string(), json!(phone));
+    account.insert("website".to_string(), json!(website));
+    account
+}
+
+/// A project from a JSON record, e.g. a DemocracyLab export
+fn project_from_json(record: &HashMap<String, serde_json::Value>) -> serde_json::Map<String, serde_json::Value> {
+    // Handle different field name formats for DemocracyLab vs other sources
+    let raw_name = json_str(record, &["project_name", "name"]).unwrap_or("Unknown");
+    let description = json_str(record, &["project_description", "description"]);
+
+    let mut project = serde_json::Map::new();
+    project.insert("name".to_string(), json!(truncate_name(raw_name)));
+    project.insert("description".to_string(), json!(description));
+    project.insert("status".to_string(), json!("Active")); // Default status
+    project
+}
+
+/// Field names `/import/data` accepts for a column besides its own, compared
+/// as `import_mapping::normalize_header` leaves them, so "First Name",
+/// "first_name" and "FIRST-NAME" are the same
+type FieldAliases = &'static [(&'static str, &'static [&'static str])];
+
+const PERSON_ALIASES: FieldAliases = &[
+    ("salutation", &["prefix", "honorific"]),
+    ("first_name", &["first", "firstname", "given_name", "forename"]),
+    ("last_name", &["last", "lastname", "surname", "family_name"]),
+    ("title", &["job_title", "position", "role"]),
+    ("department", &["dept"]),
+    ("company", &["company_name", "organization", "organisation", "employer"]),
+    ("email", &["email_address", "e_mail", "mail"]),
+    ("phone_work", &["phone", "work_phone", "business_phone", "telephone", "tel"]),
+    ("phone_mobile", &["mobile", "mobile_phone", "cell", "cell_phone"]),
+    ("primary_address_street", &["street", "address", "street_address"]),
+    ("primary_address_city", &["city", "town"]),
+    ("primary_address_state", &["state", "province", "county"]),
+    ("primary_address_postalcode", &["postal_code", "postcode", "zip", "zip_code"]),
+    ("primary_address_country", &["country"]),
+    ("status", &["lead_status"]),
+    ("lead_source", &["source"]),
+    ("description", &["notes", "comments"]),
+];
+
+const OPPORTUNITY_ALIASES: FieldAliases = &[
+    ("name", &["opportunity", "opportunity_name", "deal", "deal_name"]),
+    ("opportunity_type", &["type"]),
+    ("lead_source", &["source"]),
+    ("amount", &["value", "deal_value"]),
+    ("currency_id", &["currency"]),
+    ("date_closed", &["close_date", "closed", "expected_close_date"]),
+    ("sales_stage", &["stage"]),
+    ("probability", &["win_probability"]),
+    ("description", &["notes", "comments"]),
+];
+
+const EVENT_ALIASES: FieldAliases = &[
+    ("name", &["event", "event_name", "title"]),
+    ("date_start", &["start", "starts", "start_date", "start_time"]),
+    ("date_end", &["end", "ends", "end_date", "end_time"]),
+    ("duration_hours", &["hours"]),
+    ("duration_minutes", &["minutes"]),
+    ("location", &["venue", "place", "address"]),
+    ("description", &["details", "notes"]),
+];
+
+const PRODUCT_ALIASES: FieldAliases = &[
+    ("name", &["product", "product_name"]),
+    ("product_code", &["code", "sku"]),
+    ("category", &["type", "product_type"]),
+    ("manufacturer", &["brand", "vendor", "maker"]),
+    ("cost", &["unit_cost"]),
+    ("price", &["unit_price", "list_price"]),
+    ("description", &["details", "notes"]),
+];
+
+/// Field names that give the account of a contact or opportunity by name
+const ACCOUNT_NAME_KEYS: &[&str] = &["account", "account_name"];
+
+/// The columns of `entity` found in `record` under their own name or an alias.
+/// Fields that name no column are left out.
+fn aliased_record(
+    entity: &EntityDef,
+    aliases: FieldAliases,
     record: &HashMap<String, serde_json::Value>,
-) -> Result<(InsertResult, String), Box<dyn std::error::Error>> {
-    // Extract fields from the record
-    let name = record.get("Name")
-        .or_else(|| record.get("name"))
-        .and_then(|v| v.as_str())
-        .unwrap_or("Unknown");
-    
-    let email = record.get("Email")
-        .or_else(|| record.get("email"))
-        .and_then(|v| v.as_str());
-    
-    let phone = record.get("Phone")
-        .or_else(|| record.get("phone"))
-        .and_then(|v| v.as_str());
-    
-    let website = record.get("Website")
-        .or_else(|| record.get("website"))
-        .and_then(|v| v.as_str());
-    
-    let industry = record.get("Industry")
-        .or_else(|| record.get("industry"))
-        .or_else(|| record.get("Sector"))
-        .or_else(|| record.get("sector"))
-        .and_then(|v| v.as_str());
-    
-    // Check for existing record based on available fields
-    let existing_count = if industry.is_some() {
-        // Use name + industry if industry is available
-        sqlx::query_scalar::<_, i64>(
-            r#"
-            SELECT COUNT(*) FROM accounts 
-            WHERE name = $1 AND industry = $2
-            "#
-        )
-        .bind(name)
-        .bind(industry)
-        .fetch_one(pool)
-        .await?
+) -> serde_json::Map<String, serde_json::Value> {
+    let given: HashMap<String, &serde_json::Value> = record
+        .iter()
+        .filter(|(_, value)| !value.is_null())
+        .map(|(key, value)| (import_mapping::normalize_header(key), value))
+        .collect();
+    let mut mapped = serde_json::Map::new();
+    for field in entity.fields {
+        let names = aliases.iter().find(|(column, _)| *column == field.name).map_or(&[][..], |(_, names)| *names);
+        let value = std::iter::once(&field.name).chain(names).find_map(|name| given.get(*name));
+        if let Some(value) = value {
+            mapped.insert(field.name.to_string(), (*value).clone());
+        }
+    }
+    mapped
+}
+
+/// A contact or lead, splitting a full `name` when no last name is given
+fn person_from_json(
+    entity: &EntityDef,
+    record: &HashMap<String, serde_json::Value>,
+) -> serde_json::Map<String, serde_json::Value> {
+    let mut person = aliased_record(entity, PERSON_ALIASES, record);
+    if !person.contains_key("last_name") {
+        if let Some(full_name) = json_str(record, &["Name", "name", "Full Name", "full_name"]) {
+            let mut parts: Vec<&str> = full_name.split_whitespace().collect();
+            if let Some(last_name) = parts.pop() {
+                person.insert("last_name".to_string(), json!(last_name));
+                if !parts.is_empty() && !person.contains_key("first_name") {
+                    person.insert("first_name".to_string(), json!(parts.join(" ")));
+                }
+            }
+        }
+    }
+    person
+}
+
+pub fn contact_from_json(record: &HashMap<String, serde_json::Value>) -> serde_json::Map<String, serde_json::Value> {
+    person_from_json(&entities::CONTACTS, record)
+}
+
+fn lead_from_json(record: &HashMap<String, serde_json::Value>) -> serde_json::Map<String, serde_json::Value> {
+    person_from_json(&entities::LEADS, record)
+}
+
+fn opportunity_from_json(record: &HashMap<String, serde_json::Value>) -> serde_json::Map<String, serde_json::Value> {
+    aliased_record(&entities::OPPORTUNITIES, OPPORTUNITY_ALIASES, record)
+}
+
+fn event_from_json(record: &HashMap<String, serde_json::Value>) -> serde_json::Map<String, serde_json::Value> {
+    aliased_record(&entities::EVENTS, EVENT_ALIASES, record)
+}
+
+fn product_from_json(record: &HashMap<String, serde_json::Value>) -> serde_json::Map<String, serde_json::Value> {
+    aliased_record(&entities::PRODUCTS, PRODUCT_ALIASES, record)
+}
+
+/// The account name a posted record gives, if any
+fn account_name(record: &HashMap<String, serde_json::Value>) -> Option<&str> {
+    record
+        .iter()
+        .find(|(key, _)| ACCOUNT_NAME_KEYS.contains(&import_mapping::normalize_header(key).as_str()))
+        .and_then(|(_, value)| value.as_str())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// Fill `account_id` of records that name their account instead. Names are
+/// matched like duplicate accounts (see `duplicates::normalize_name`), the
+/// oldest account winning. A row naming an account that does not exist keeps
+/// an empty `account_id` and gets a warning.
+async fn with_account_ids(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    data: &[HashMap<String, serde_json::Value>],
+    records: &mut [serde_json::Map<String, serde_json::Value>],
+) -> Result<Vec<String>, sqlx::Error> {
+    let names: Vec<Option<&str>> = data
+        .iter()
+        .zip(records.iter())
+        .map(|(source, record)| if record.contains_key("account_id") { None } else { account_name(source) })
+        .collect();
+    let mut keys: Vec<String> = names.iter().flatten().map(|name| duplicates::normalize_name(name)).collect();
+    keys.sort();
+    keys.dedup();
+
+    let accounts: HashMap<String, Uuid> = if keys.is_empty() {
+        HashMap::new()
     } else {
-        // Use only name if industry is not available
-        sqlx::query_scalar::<_, i64>(
+        sqlx::query_as::<_, (String, Uuid)>(
             r#"
-            SELECT COUNT(*) FROM accounts 
-            WHERE name = $1
+            SELECT DISTINCT ON (normalize_name(name)) normalize_name(name), id
+            FROM accounts
+            WHERE normalize_name(name) = ANY($1)
+            ORDER BY normalize_name(name), date_entered, id
             "#
         )
-        .bind(name)
-        .fetch_one(pool)
+        .bind(&keys)
+        .fetch_all(pool)
         .await?
+        .into_iter()
+        .collect()
     };
 
-    // Determine which fields were used for duplicate checking
-    let duplicate_check_fields = if industry.is_some() {
-        "Name + Industry".to_string()
-    } else {
-        "Name".to_string()
-    };
-
-    if existing_count > 0 {
-        // Record already exists, skip insertion
-        let fields_used = if industry.is_some() {
-            format!("Name + Industry: {} (Industry: {:?})", name, industry)
-        } else {
-            format!("Name: {}", name)
-        };
-        println!("Skipping duplicate account: {}", fields_used);
-        return Ok((InsertResult::Skipped, duplicate_check_fields));
+    let mut warnings = Vec::new();
+    for (index, (record, name)) in records.iter_mut().zip(names).enumerate() {
+        let Some(name) = name else { continue };
+        match accounts.get(&duplicates::normalize_name(name)) {
+            Some(id) => {
+                record.insert("account_id".to_string(), json!(id));
+            }
+            None => warnings.push(format!("Row {}: no account named '{}', account_id left empty", index + 1, name)),
+        }
     }
+    Ok(warnings)
+}
 
-    let id = Uuid::new_v4();
-    let now = Utc::now().naive_utc();
-    
-    // Set account type based on available data
-    let account_type = if email.is_some() || phone.is_some() {
-        Some("Customer")
-    } else {
-        Some("Prospect")
-    };
-    
-    sqlx::query(
-        r#"
-        INSERT INTO accounts (
-            id, name, account_type, industry, phone_office, website,
-            date_entered, date_modified, created_by, modified_user_id
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
-        "#
-    )
-    .bind(id)
-    .bind(name)
-    .bind(account_type)
-    .bind(industry)
-    .bind(phone)
-    .bind(website)
-    .bind(now)
-    .bind(now)
-    .bind("csv-import") // Creator identifier
-    .bind("csv-import") // Modifier identifier
-    .execute(pool)
-    .await?;
-
-    Ok((InsertResult::Inserted, duplicate_check_fields))
-}
-
-async fn import_project_record_from_json(
-    pool: &Pool<Postgres>,
-    record: &HashMap<String, serde_json::Value>,
-) -> Result<(InsertResult, String), Box<dyn std::error::Error>> {
-    // Handle different field name formats for DemocracyLab vs other sources
-    let raw_name = record.get("project_name")
-        .and_then(|v| v.as_str())
-        .or_else(|| record.get("name").and_then(|v| v.as_str()))
-        .unwrap_or("Unknown");
-    
-    // Truncate name to fit database constraint (50 characters max)
-    let name = if raw_name.len() > 50 {
-        let truncated = &raw_name[..47]; // Leave room for "..."
+/// Truncate name to fit database constraint (255 characters max)
+fn truncate_name(raw_name: &str) -> String {
+    if raw_name.chars().count() > 255 {
+        let truncated: String = raw_name.chars().take(252).collect(); // Leave room for "..."
         format!("{}...", truncated)
     } else {
         raw_name.to_string()
-    };
-    
-    let description = record.get("project_description")
-        .and_then(|v| v.as_str())
-        .or_else(|| record.get("description").and_then(|v| v.as_str()));
-
-    // Check for existing record based on name
-    let existing_count = sqlx::query_scalar::<_, i64>(
-        r#"
-        SELECT COUNT(*) FROM projects 
-        WHERE name = $1
-        "#
-    )
-    .bind(&name)
-    .fetch_one(pool)
-    .await?;
-
-    if existing_count > 0 {
-        println!("Skipping duplicate project: {}", name);
-        return Ok((InsertResult::Skipped, "Name".to_string()));
     }
-
-    let id = Uuid::new_v4();
-    let now = Utc::now();
-
-    sqlx::query(
-        r#"
-        INSERT INTO projects (
-            id, name, description, status,
-            date_entered, date_modified, created_by, modified_user_id
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-        "#
-    )
-    .bind(id)
-    .bind(&name)
-    .bind(description)
-    .bind("Active") // Default status
-    .bind(now)
-    .bind(now)
-    .bind("json-import")
-    .bind("json-import")
-    .execute(pool)
-    .await?;
-
-    Ok((InsertResult::Inserted, "Name".to_string()))
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DemocracyLabApiResponse {
     pub projects: Vec<DemocracyLabProject>,
+    #[serde(flatten)]
+    pub options: ImportOptions,
 }
 
 pub async fn import_democracylab_projects(
     pool: web::Data<std::sync::Arc<crate::ApiState>>,
+    claims: Claims,
     req: web::Json<DemocracyLabApiResponse>,
 ) -> Result<HttpResponse> {
-    let mut errors = Vec::new();
-    let mut inserted_count = 0;
-    let mut skipped_count = 0;
-    let total_records = req.projects.len();
-
-    for (index, project) in req.projects.iter().enumerate() {
-        match insert_democracylab_project(&pool.db, project).await {
-            Ok(InsertResult::Inserted) => inserted_count += 1,
-            Ok(InsertResult::Skipped) => skipped_count += 1,
-            Err(e) => {
-                errors.push(format!("Row {}: {}", index + 1, e));
-            }
-        }
-    }
-
-    let message = if errors.is_empty() {
-        if skipped_count > 0 {
-            format!("Successfully imported {} projects, skipped {} duplicates", inserted_count, skipped_count)
-        } else {
-            format!("Successfully imported {} projects", inserted_count)
-        }
-    } else {
-        format!("Imported {} of {} projects with {} errors, skipped {} duplicates",
-                inserted_count, total_records, errors.len(), skipped_count)
+    let rows: Vec<ImportRow> = req.projects.iter().enumerate()
+        .map(|(index, project)| project.import_row(index + 1))
+        .collect();
+    let duplicates = match DuplicateCheck::for_import(&entities::PROJECTS, DuplicateCheck::Name { columns: &[] }, &req.options) {
+        Ok(duplicates) => duplicates,
+        Err(errors) => return Ok(import_error("Match columns for projects are not valid".to_string(), errors)),
     };
 
-    Ok(HttpResponse::Ok().json(ImportResponse {
-        success: errors.is_empty() || inserted_count > 0,
-        message,
-        records_processed: Some(total_records),
-        records_inserted: Some(inserted_count),
-        records_skipped: Some(skipped_count),
-        duplicate_check_columns: Some("Name".to_string()),
-        errors,
-    }))
-}
-
-async fn insert_democracylab_project(
-    pool: &Pool<Postgres>,
-    project: &DemocracyLabProject,
-) -> Result<InsertResult, sqlx::Error> {
-    // Check for existing record based on name
-    let existing_count = sqlx::query_scalar::<_, i64>(
-        r#"
-        SELECT COUNT(*) FROM projects
-        WHERE name = $1
-        "#
-    )
-    .bind(&project.name)
-    .fetch_one(pool)
-    .await?;
-
-    if existing_count > 0 {
-        println!("Skipping duplicate project: {}", &project.name);
-        return Ok(InsertResult::Skipped);
-    }
-
-    let id = Uuid::new_v4();
-    let now = Utc::now();
-
-    let mut description_parts = Vec::new();
-    if let Some(desc) = &project.description {
-        description_parts.push(desc.clone());
-    }
-    if let Some(url) = &project.url {
-        description_parts.push(format!("Project URL: {}", url));
-    }
-    let description = if description_parts.is_empty() {
-        None
-    } else {
-        Some(description_parts.join("
-
-"))
+    let job = NewJob {
+        source: "democracylab".to_string(),
+        file_name: None,
+        file_hash: serde_json::to_vec(&req.projects).ok().map(|bytes| NewJob::hash_of(&bytes)),
+        profile: None,
+        column_mappings: None,
     };
-
-    sqlx::query(
-        r#"
-        INSERT INTO projects (
-            id, name, description, status,
-            date_entered, date_modified, created_by, modified_user_id
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-        "#
-    )
-    .bind(id)
-    .bind(&project.name)
-    .bind(&description)
-    .bind("Active") // Default status
-    .bind(now)
-    .bind(now)
-    .bind("democracylab-import")
-    .bind("democracylab-import")
-    .execute(pool)
-    .await?;
-
-    Ok(InsertResult::Inserted)
-}
\ No newline at end of file
+    let duplicate_check_columns = duplicates.label();
+    let run = ImportRun {
+        job,
+        entity: &entities::PROJECTS,
+        user_id: claims.user_id(),
+        rows,
+        duplicates,
+        options: req.options.clone(),
+    };
+    let (job_id, outcome) = match import_tasks::start(&pool, run).await {
+        Ok((job_id, Some(outcome))) => (job_id, outcome),
+        Ok((job_id, None)) => return Ok(import_tasks::accepted(job_id)),
+        Err(response) => return Ok(response),
+    };
+    Ok(HttpResponse::Ok().json(ImportResponse::from_outcome(
+        job_id,
+        outcome,
+        entities::PROJECTS.name,
+        duplicate_check_columns,
+        &req.options,
+    )))
+}
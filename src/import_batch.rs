@@ -0,0 +1,688 @@
+// src/import_batch.rs
+//
+// Writes imported rows in batches. Every import endpoint turns its input into
+// `ImportRow`s and hands them here: rows are validated against the target
+// `EntityDef` and table (see `import_validation`), checked for duplicates
+// with one lookup per batch, and inserted with one multi-row `INSERT` per
+// batch. If a batch insert fails, its rows are retried one at a time inside
+// savepoints so each failure is reported against its row. Rows matching an
+// existing record are skipped or update it, depending on the conflict
+// strategy. Imports run all-or-nothing in one transaction or commit batch by
+// batch; a dry run does the same work and rolls it all back.
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::{Pool, Postgres, QueryBuilder, Transaction};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::duplicates::normalize_name;
+use crate::entities::{self, EntityDef, FieldValue};
+use crate::import_jobs::{self, JobRow};
+use crate::import_tasks::JobTracker;
+use crate::import_validation::{self, CellError, TableSchema};
+
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Keeps the bind parameters of one multi-row `INSERT` well under Postgres' limit
+pub const MAX_BATCH_SIZE: usize = 2000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Commit after each batch, keeping the rows that succeeded
+    #[default]
+    Batched,
+    /// One transaction; any rejected row undoes the whole import
+    AllOrNothing,
+}
+
+/// What to do with a row that matches an existing record
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    /// Leave the existing record alone
+    #[default]
+    Skip,
+    /// Write every column the row provides, blanks included
+    Overwrite,
+    /// Write only the columns the row has a value for
+    Merge,
+}
+
+/// Options accepted by every import endpoint
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportOptions {
+    #[serde(default)]
+    pub mode: ImportMode,
+    pub batch_size: Option<usize>,
+    /// Report what would happen, then roll everything back
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Return the job ID at once and import in the background
+    #[serde(default)]
+    pub background: bool,
+    #[serde(default)]
+    pub on_conflict: ConflictStrategy,
+    /// Columns that identify an existing record, replacing the endpoint's
+    /// own duplicate check
+    pub match_columns: Option<Vec<String>>,
+}
+
+impl ImportOptions {
+    pub fn batch_size(&self) -> usize {
+        self.batch_size.unwrap_or(DEFAULT_BATCH_SIZE).clamp(1, MAX_BATCH_SIZE)
+    }
+}
+
+/// One input row, numbered as the client knows it
+pub struct ImportRow {
+    pub row_number: usize,
+    pub record: RowRecord,
+    /// The row as the client gave it, column by column, written to the
+    /// rejected-rows file when the row is rejected
+    pub source: Vec<(String, Value)>,
+}
+
+pub enum RowRecord {
+    /// Column -> value, validated like a `POST /api/{entity}` body
+    Record(Map<String, Value>),
+    /// Nothing to import
+    Empty,
+    /// Problems found before validation, e.g. cells that could not be read
+    Invalid(Vec<CellError>),
+}
+
+impl ImportRow {
+    pub fn new(row_number: usize, record: RowRecord) -> Self {
+        ImportRow { row_number, record, source: Vec::new() }
+    }
+
+    pub fn with_source(mut self, source: Vec<(String, Value)>) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// The row for the rejected-rows file: its source, or else the record
+    fn cells(&self, entity: &EntityDef) -> Vec<(String, Value)> {
+        if !self.source.is_empty() {
+            return self.source.clone();
+        }
+        match &self.record {
+            RowRecord::Record(record) => entity
+                .fields
+                .iter()
+                .filter_map(|f| record.get(f.name).map(|v| (f.name.to_string(), v.clone())))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// How an incoming row is recognised as already imported
+pub enum DuplicateCheck {
+    /// Every valid row is inserted
+    None,
+    /// A record with the same name once normalised (see
+    /// `duplicates::normalize_name`) and, for each listed column the row
+    /// fills, the same value
+    Name { columns: &'static [&'static str] },
+    /// A record with the same value in every column, from `match_columns`.
+    /// Rows missing one of them never match.
+    Columns(Vec<&'static str>),
+}
+
+impl DuplicateCheck {
+    /// The check an import uses: the request's `match_columns` when given,
+    /// otherwise the endpoint's `default`
+    pub fn for_import(entity: &EntityDef, default: DuplicateCheck, options: &ImportOptions) -> Result<Self, Vec<String>> {
+        let Some(names) = &options.match_columns else {
+            return Ok(default);
+        };
+        if names.is_empty() {
+            return Err(vec!["match_columns must name at least one column".to_string()]);
+        }
+        let mut columns = Vec::new();
+        let mut errors = Vec::new();
+        for name in names {
+            match entity.field(name) {
+                Some(field) => columns.push(field.name),
+                None => errors.push(format!("Unknown match column for {}: {}", entity.name, name)),
+            }
+        }
+        if errors.is_empty() { Ok(DuplicateCheck::Columns(columns)) } else { Err(errors) }
+    }
+
+    /// Columns compared, for `duplicate_check_columns` in responses
+    pub fn label(&self) -> Option<String> {
+        match self {
+            DuplicateCheck::None => None,
+            DuplicateCheck::Name { columns } => {
+                let mut parts = vec!["Name".to_string()];
+                parts.extend(columns.iter().map(|c| title_case(c)));
+                Some(parts.join(" + "))
+            }
+            DuplicateCheck::Columns(columns) => {
+                Some(columns.iter().map(|c| title_case(c)).collect::<Vec<_>>().join(" + "))
+            }
+        }
+    }
+
+    /// The column existing records are looked up by, and whether it is
+    /// compared normalised
+    fn lookup_column(&self) -> Option<(&'static str, bool)> {
+        match self {
+            DuplicateCheck::None => None,
+            DuplicateCheck::Name { .. } => Some(("name", true)),
+            DuplicateCheck::Columns(columns) => columns.first().map(|c| (*c, false)),
+        }
+    }
+
+    /// The row's value in the lookup column, which every record it matches
+    /// shares. Rows without one match nothing.
+    fn lookup_key(&self, pending: &Pending<'_>) -> Option<String> {
+        let (column, normalized) = self.lookup_column()?;
+        match pending.value(column)? {
+            FieldValue::Text(name) if normalized => Some(normalize_name(name)),
+            _ if normalized => None,
+            FieldValue::Null => None,
+            FieldValue::Decimal(d) => d.parse::<f64>().ok().map(|d| d.to_string()),
+            value => Some(json_value(value).to_string()),
+        }
+    }
+
+    /// Whether `existing`, a record as `to_jsonb` returns it, is a copy of the row
+    fn matches(&self, pending: &Pending<'_>, existing: &Map<String, Value>) -> bool {
+        match self {
+            DuplicateCheck::None => false,
+            DuplicateCheck::Name { columns } => {
+                let value = |column: &str| pending.value(column).filter(|v| !matches!(v, FieldValue::Null));
+                value("name").is_some_and(|v| same_name(v, existing.get("name")))
+                    && columns.iter().all(|c| value(c).is_none_or(|v| same_value(v, existing.get(*c))))
+            }
+            DuplicateCheck::Columns(columns) => columns.iter().all(|c| match pending.value(c) {
+                None | Some(FieldValue::Null) => false,
+                Some(v) => same_value(v, existing.get(*c)),
+            }),
+        }
+    }
+
+}
+
+/// Validated values as `to_jsonb` would return them once inserted
+fn as_record(values: &[(&'static str, FieldValue)]) -> Map<String, Value> {
+    values.iter().map(|(name, value)| (name.to_string(), json_value(value))).collect()
+}
+
+fn json_value(value: &FieldValue) -> Value {
+    match value {
+        FieldValue::Null => Value::Null,
+        FieldValue::Text(s) | FieldValue::Decimal(s) => Value::String(s.clone()),
+        FieldValue::Uuid(u) => Value::String(u.to_string()),
+        FieldValue::Date(d) => Value::String(d.to_string()),
+        FieldValue::Timestamp(t) => Value::String(t.to_rfc3339()),
+        FieldValue::Integer(i) => Value::from(*i),
+        FieldValue::Boolean(b) => Value::Bool(*b),
+    }
+}
+
+/// Compare a validated value with a column of a record from `to_jsonb`
+fn same_value(value: &FieldValue, existing: Option<&Value>) -> bool {
+    let existing = existing.unwrap_or(&Value::Null);
+    let as_number = |v: &Value| match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse::<f64>().ok(),
+        _ => None,
+    };
+    match value {
+        FieldValue::Null => existing.is_null(),
+        FieldValue::Text(s) => existing.as_str() == Some(s.as_str()),
+        FieldValue::Uuid(u) => existing.as_str().and_then(|e| Uuid::parse_str(e).ok()) == Some(*u),
+        FieldValue::Date(d) => existing.as_str() == Some(d.to_string().as_str()),
+        FieldValue::Timestamp(t) => existing
+            .as_str()
+            .and_then(|e| DateTime::parse_from_rfc3339(e).ok())
+            .is_some_and(|e| e.with_timezone(&Utc) == *t),
+        FieldValue::Integer(i) => existing.as_i64() == Some(*i as i64),
+        FieldValue::Decimal(s) => s.parse::<f64>().ok().is_some_and(|d| as_number(existing) == Some(d)),
+        FieldValue::Boolean(b) => existing.as_bool() == Some(*b),
+    }
+}
+
+/// Compare names as `duplicates::normalize_name` leaves them
+fn same_name(value: &FieldValue, existing: Option<&Value>) -> bool {
+    match (value, existing.and_then(Value::as_str)) {
+        (FieldValue::Text(name), Some(existing)) => normalize_name(name) == normalize_name(existing),
+        _ => false,
+    }
+}
+
+fn title_case(column: &str) -> String {
+    column
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RowAction {
+    Insert,
+    Update,
+    Skip,
+    Reject,
+}
+
+/// What happened, or in a dry run would happen, to one row
+#[derive(Debug, Serialize)]
+pub struct RowReport {
+    pub row: usize,
+    pub action: RowAction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record: Option<Map<String, Value>>,
+    /// Why a rejected row was rejected, column by column
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<CellError>,
+}
+
+impl RowReport {
+    fn rejected(row: usize, errors: Vec<CellError>) -> Self {
+        RowReport {
+            row,
+            action: RowAction::Reject,
+            reason: Some(import_validation::summary(&errors)),
+            record: None,
+            errors,
+        }
+    }
+}
+
+/// A validation error with the row it belongs to
+#[derive(Debug, Clone, Serialize)]
+pub struct RowError {
+    pub row: usize,
+    #[serde(flatten)]
+    pub error: CellError,
+}
+
+/// A rejected row as it was given, kept with its job for download
+#[derive(Debug)]
+pub struct Rejection {
+    pub row: usize,
+    pub cells: Vec<(String, Value)>,
+    pub errors: Vec<CellError>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportOutcome {
+    pub processed: usize,
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub rejected: usize,
+    pub errors: Vec<String>,
+    /// `errors` with the column and error code of each problem
+    pub row_errors: Vec<RowError>,
+    pub batches: usize,
+    /// Nothing was kept: a dry run, or an all-or-nothing import with rejected
+    /// rows or that was cancelled
+    pub rolled_back: bool,
+    /// Stopped by `POST /api/import/jobs/{id}/cancel` before every row was processed
+    pub cancelled: bool,
+    /// Per-row report, returned for dry runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rows: Option<Vec<RowReport>>,
+    /// Rejected rows, recorded with the job by `import_jobs::finish_job`
+    #[serde(skip)]
+    pub rejections: Vec<Rejection>,
+}
+
+/// A validated row waiting for its batch
+struct Pending<'r> {
+    row: &'r ImportRow,
+    record: &'r Map<String, Value>,
+    values: Vec<(&'static str, FieldValue)>,
+}
+
+impl Pending<'_> {
+    fn value(&self, column: &str) -> Option<&FieldValue> {
+        self.values.iter().find(|(name, _)| *name == column).map(|(_, v)| v)
+    }
+}
+
+/// Records already in the table that share the lookup column with a row of
+/// the batch. `normalized` compares names through `normalize_name`.
+async fn existing_records(
+    tx: &mut Transaction<'_, Postgres>,
+    entity: &EntityDef,
+    column: &str,
+    normalized: bool,
+    batch: &[Pending<'_>],
+) -> Result<Vec<Map<String, Value>>, sqlx::Error> {
+    let keys: Vec<FieldValue> = batch
+        .iter()
+        .filter_map(|p| p.value(column).filter(|v| !matches!(v, FieldValue::Null)).cloned())
+        .map(|key| match key {
+            FieldValue::Text(name) if normalized => FieldValue::Text(normalize_name(&name)),
+            key => key,
+        })
+        .collect();
+    if keys.is_empty() {
+        return Ok(Vec::new());
+    }
+    let target = if normalized { format!("normalize_name(t.{})", column) } else { format!("t.{}", column) };
+    let mut builder = QueryBuilder::<Postgres>::new(format!("SELECT to_jsonb(t) FROM {} t WHERE {} IN (", entity.name, target));
+    for (index, key) in keys.into_iter().enumerate() {
+        if index > 0 {
+            builder.push(", ");
+        }
+        entities::push_value(&mut builder, key);
+    }
+    builder.push(")");
+    let found = builder.build_query_scalar::<Value>().fetch_all(&mut **tx).await?;
+    Ok(found.into_iter().filter_map(|v| match v {
+        Value::Object(map) => Some(map),
+        _ => None,
+    }).collect())
+}
+
+/// Run one statement inside a savepoint, so a failure only loses that row
+async fn execute_row(
+    tx: &mut Transaction<'_, Postgres>,
+    mut builder: QueryBuilder<'_, Postgres>,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query("SAVEPOINT import_row").execute(&mut **tx).await?;
+    match builder.build().execute(&mut **tx).await {
+        Ok(_) => {
+            sqlx::query("RELEASE SAVEPOINT import_row").execute(&mut **tx).await?;
+            Ok(None)
+        }
+        Err(e) => {
+            sqlx::query("ROLLBACK TO SAVEPOINT import_row").execute(&mut **tx).await?;
+            Ok(Some(e.to_string()))
+        }
+    }
+}
+
+/// The values an update of a matched record writes, or None when the row
+/// adds nothing under `strategy`
+fn update_values(
+    strategy: ConflictStrategy,
+    values: &[(&'static str, FieldValue)],
+) -> Option<Vec<(&'static str, FieldValue)>> {
+    let values: Vec<(&'static str, FieldValue)> = match strategy {
+        ConflictStrategy::Skip => return None,
+        ConflictStrategy::Overwrite => values.to_vec(),
+        ConflictStrategy::Merge => values.iter().filter(|(_, v)| !matches!(v, FieldValue::Null)).cloned().collect(),
+    };
+    if values.is_empty() { None } else { Some(values) }
+}
+
+/// Insert a batch with one statement, falling back to one row at a time so
+/// that a bad row only rejects itself. Returns each row's new ID or error.
+async fn insert_batch(
+    tx: &mut Transaction<'_, Postgres>,
+    entity: &EntityDef,
+    user_id: &str,
+    batch: Vec<Pending<'_>>,
+) -> Result<Vec<(usize, Result<Uuid, String>)>, sqlx::Error> {
+    let rows: Vec<(Uuid, Vec<(&'static str, FieldValue)>)> =
+        batch.iter().map(|p| (Uuid::new_v4(), p.values.clone())).collect();
+    let ids: Vec<(usize, Uuid)> = batch.iter().zip(&rows).map(|(p, (id, _))| (p.row.row_number, *id)).collect();
+
+    sqlx::query("SAVEPOINT import_batch").execute(&mut **tx).await?;
+    let result = entities::insert_many_query(entity, user_id, rows).build().execute(&mut **tx).await;
+    if result.is_ok() {
+        sqlx::query("RELEASE SAVEPOINT import_batch").execute(&mut **tx).await?;
+        return Ok(ids.into_iter().map(|(row_number, id)| (row_number, Ok(id))).collect());
+    }
+    sqlx::query("ROLLBACK TO SAVEPOINT import_batch").execute(&mut **tx).await?;
+
+    let mut results = Vec::new();
+    for (pending, (row_number, id)) in batch.into_iter().zip(ids) {
+        let builder = entities::insert_query(entity, id, user_id, pending.values);
+        let result = match execute_row(tx, builder).await? {
+            None => Ok(id),
+            Some(e) => Err(e),
+        };
+        results.push((row_number, result));
+    }
+    Ok(results)
+}
+
+/// Validate `rows` and insert them into `entity`, or skip or update the
+/// records they match. With a `tracker`, the records written are linked to
+/// its import job in the same transaction, progress is reported after every
+/// batch, and cancelling the job stops the import before the next batch.
+pub async fn run_import(
+    pool: &Pool<Postgres>,
+    entity: &'static EntityDef,
+    user_id: &str,
+    rows: &[ImportRow],
+    duplicates: &DuplicateCheck,
+    options: &ImportOptions,
+    tracker: Option<&JobTracker>,
+) -> Result<ImportOutcome, sqlx::Error> {
+    let schema = TableSchema::load(pool, entity.name).await?;
+    let mut reports: Vec<RowReport> = Vec::with_capacity(rows.len());
+    let mut valid = Vec::new();
+
+    for row in rows {
+        match &row.record {
+            RowRecord::Empty => reports.push(RowReport {
+                row: row.row_number,
+                action: RowAction::Skip,
+                reason: Some("Empty row".to_string()),
+                record: None,
+                errors: Vec::new(),
+            }),
+            RowRecord::Invalid(errors) => reports.push(RowReport::rejected(row.row_number, errors.clone())),
+            RowRecord::Record(record) => match import_validation::validate_row(entity, &schema, record) {
+                Ok(values) => valid.push(Pending { row, record, values }),
+                Err(errors) => reports.push(RowReport::rejected(row.row_number, errors)),
+            },
+        }
+    }
+
+    let all_or_nothing = options.mode == ImportMode::AllOrNothing;
+    // Batches are committed one by one only when the import is kept as it goes
+    let commit_batches = options.mode == ImportMode::Batched && !options.dry_run;
+
+    let mut batches = 0;
+    let mut cancelled = false;
+    let mut reported = 0;
+    if let Some(tracker) = tracker {
+        tracker.report(&reports[reported..], batches);
+        reported = reports.len();
+    }
+    // Rows inserted so far by lookup key, which later rows of the import may
+    // duplicate. Only rows with the same key are compared.
+    let mut accepted: HashMap<String, Vec<_>> = HashMap::new();
+    let mut pending_rows = valid.into_iter().peekable();
+    let mut tx = pool.begin().await?;
+
+    while pending_rows.peek().is_some() {
+        if tracker.is_some_and(JobTracker::is_cancelled) {
+            cancelled = true;
+            break;
+        }
+        let batch: Vec<Pending> = pending_rows.by_ref().take(options.batch_size()).collect();
+        let existing = match duplicates.lookup_column() {
+            None => Vec::new(),
+            Some((column, normalized)) => existing_records(&mut tx, entity, column, normalized, &batch).await?,
+        };
+
+        let mut to_insert = Vec::new();
+        let mut inserted_keys: HashMap<usize, String> = HashMap::new();
+        let mut written: Vec<JobRow> = Vec::new();
+        for pending in batch {
+            let report = |action, reason: Option<String>| RowReport {
+                row: pending.row.row_number,
+                action,
+                reason,
+                record: None,
+                errors: Vec::new(),
+            };
+            let key = duplicates.lookup_key(&pending);
+            let earlier = key
+                .as_ref()
+                .and_then(|key| accepted.get(key))
+                .and_then(|rows| rows.iter().find(|(_, record)| duplicates.matches(&pending, record)));
+            if let Some((row_number, _)) = earlier {
+                reports.push(report(RowAction::Skip, Some(format!("Duplicate of row {}", row_number))));
+                continue;
+            }
+            let Some(matched) = existing.iter().find(|e| duplicates.matches(&pending, e)) else {
+                if let Some(key) = key {
+                    let row_number = pending.row.row_number;
+                    accepted.entry(key.clone()).or_default().push((row_number, as_record(&pending.values)));
+                    inserted_keys.insert(row_number, key);
+                }
+                to_insert.push(pending);
+                continue;
+            };
+
+            let matched_id = matched.get("id").and_then(Value::as_str).and_then(|id| Uuid::parse_str(id).ok());
+            match (update_values(options.on_conflict, &pending.values), matched_id) {
+                (Some(values), Some(id)) => {
+                    let builder = entities::update_query(entity, id, user_id, values);
+                    match execute_row(&mut tx, builder).await? {
+                        None => {
+                            written.push(JobRow::updated(pending.row.row_number, id, matched.clone()));
+                            reports.push(RowReport {
+                                record: options.dry_run.then(|| pending.record.clone()),
+                                ..report(RowAction::Update, Some(format!("Matched {}", id)))
+                            });
+                        }
+                        Some(e) => reports.push(RowReport::rejected(pending.row.row_number, vec![CellError::database(e)])),
+                    }
+                }
+                _ => reports.push(report(RowAction::Skip, Some("Duplicate".to_string()))),
+            }
+        }
+
+        let records: Vec<&Map<String, Value>> = to_insert.iter().map(|p| p.record).collect();
+        let results = if to_insert.is_empty() {
+            Vec::new()
+        } else {
+            insert_batch(&mut tx, entity, user_id, to_insert).await?
+        };
+        for ((row_number, result), record) in results.into_iter().zip(records) {
+            match result {
+                Ok(id) => {
+                    written.push(JobRow::inserted(row_number, id));
+                    reports.push(RowReport {
+                        row: row_number,
+                        action: RowAction::Insert,
+                        reason: None,
+                        record: options.dry_run.then(|| record.clone()),
+                        errors: Vec::new(),
+                    });
+                }
+                Err(e) => {
+                    // A row that failed to insert does not make later copies duplicates
+                    if let Some(rows) = inserted_keys.get(&row_number).and_then(|key| accepted.get_mut(key)) {
+                        rows.retain(|(n, _)| *n != row_number);
+                    }
+                    reports.push(RowReport::rejected(row_number, vec![CellError::database(e)]));
+                }
+            }
+        }
+        if let Some(tracker) = tracker {
+            import_jobs::link_rows(&mut tx, tracker.id, written).await?;
+        }
+
+        batches += 1;
+        if commit_batches {
+            tx.commit().await?;
+            tx = pool.begin().await?;
+        }
+        if let Some(tracker) = tracker {
+            tracker.report(&reports[reported..], batches);
+            reported = reports.len();
+        }
+    }
+
+    reports.sort_by_key(|r| r.row);
+    let count = |action| reports.iter().filter(|r| r.action == action).count();
+    let rejected = count(RowAction::Reject);
+    let rolled_back = options.dry_run || (all_or_nothing && (rejected > 0 || cancelled));
+    if rolled_back {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+    }
+
+    let errors = reports
+        .iter()
+        .filter(|r| r.action == RowAction::Reject)
+        .map(|r| format!("Row {}: {}", r.row, r.reason.as_deref().unwrap_or("rejected")))
+        .collect();
+    let row_errors = reports
+        .iter()
+        .flat_map(|r| r.errors.iter().map(|error| RowError { row: r.row, error: error.clone() }))
+        .collect();
+    let by_number: HashMap<usize, &ImportRow> = rows.iter().map(|row| (row.row_number, row)).collect();
+    let rejections = reports
+        .iter()
+        .filter(|r| r.action == RowAction::Reject)
+        .map(|r| Rejection {
+            row: r.row,
+            cells: by_number.get(&r.row).map(|row| row.cells(entity)).unwrap_or_default(),
+            errors: r.errors.clone(),
+        })
+        .collect();
+    Ok(ImportOutcome {
+        processed: reports.len(),
+        // In a dry run, what would have been inserted
+        inserted: if rolled_back && !options.dry_run { 0 } else { count(RowAction::Insert) },
+        updated: if rolled_back && !options.dry_run { 0 } else { count(RowAction::Update) },
+        skipped: count(RowAction::Skip),
+        rejected,
+        errors,
+        row_errors,
+        batches,
+        rolled_back,
+        cancelled,
+        rows: options.dry_run.then_some(reports),
+        rejections,
+    })
+}
+
+impl ImportOutcome {
+    /// Whether anything was, or in a dry run would be, written, or there was
+    /// nothing wrong. A rolled-back import that was meant to be kept failed.
+    pub fn succeeded(&self, options: &ImportOptions) -> bool {
+        (options.dry_run || !self.rolled_back)
+            && (self.errors.is_empty() || self.inserted + self.updated > 0)
+    }
+
+    /// Summary line for the response `message`
+    pub fn message(&self, table: &str, options: &ImportOptions) -> String {
+        let verb = if options.dry_run { "Would import" } else { "Imported" };
+        let mut message = format!("{} {} of {} records into {}", verb, self.inserted, self.processed, table);
+        if self.updated > 0 {
+            message.push_str(&format!(", updated {}", self.updated));
+        }
+        if self.skipped > 0 {
+            message.push_str(&format!(", skipped {}", self.skipped));
+        }
+        if self.rejected > 0 {
+            message.push_str(&format!(", {} rejected", self.rejected));
+        }
+        if self.cancelled {
+            message.push_str(&format!("; cancelled after {} rows", self.processed));
+        }
+        if self.rolled_back && !options.dry_run {
+            let reason = if self.cancelled { "was cancelled" } else { "is all-or-nothing" };
+            message.push_str(&format!("; nothing was imported because the import {}", reason));
+        }
+        message
+    }
+}
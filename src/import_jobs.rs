@@ -0,0 +1,553 @@
+// src/import_jobs.rs
+//
+// History of import runs. Every run of an import endpoint is recorded in
+// `import_jobs` with its source, file hash, mapping, options, counts and
+// errors, and the records it inserted or updated are linked to it in
+// `import_job_rows`, together with how updated records looked before. Rolling
+// a job back deletes the records it inserted and restores the ones it updated.
+// Running jobs are tracked in memory by `import_tasks`.
+use actix_web::{http::header, web, HttpResponse, Result};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres, QueryBuilder, Transaction};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::{self, Claims};
+use crate::entities::{self, EntityDef, Pagination};
+use crate::export;
+use crate::import_batch::{ImportOptions, ImportOutcome, Rejection};
+use crate::import_validation;
+use crate::relationships;
+use crate::ApiState;
+
+const REJECTIONS_PER_INSERT: usize = 1000;
+
+/// Columns added to the rejected-rows file after the row's own columns
+const REJECTION_COLUMNS: &[&str] = &["import_row", "import_error_codes", "import_errors"];
+
+/// Where an import came from, as recorded on its job
+pub struct NewJob {
+    /// `excel`, `url`, `google_sheets`, `democracylab`, `democracylab_sync`, or the
+    /// `source` given to `/import/data`
+    pub source: String,
+    pub file_name: Option<String>,
+    pub file_hash: Option<String>,
+    pub profile: Option<String>,
+    pub column_mappings: Option<Value>,
+}
+
+impl NewJob {
+    /// Hex SHA-256, for records posted as JSON rather than uploaded
+    pub fn hash_of(bytes: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(bytes))
+    }
+}
+
+/// A record written by an import, linked to its job
+pub struct JobRow {
+    row_number: usize,
+    record_id: Uuid,
+    action: &'static str,
+    previous: Option<Value>,
+}
+
+impl JobRow {
+    pub fn inserted(row_number: usize, record_id: Uuid) -> Self {
+        JobRow { row_number, record_id, action: "insert", previous: None }
+    }
+
+    /// `previous` is the record as it was before the update
+    pub fn updated(row_number: usize, record_id: Uuid, previous: Map<String, Value>) -> Self {
+        JobRow { row_number, record_id, action: "update", previous: Some(Value::Object(previous)) }
+    }
+}
+
+/// Link written records to a job, inside the transaction that wrote them
+pub async fn link_rows(
+    tx: &mut Transaction<'_, Postgres>,
+    job_id: Uuid,
+    rows: Vec<JobRow>,
+) -> Result<(), sqlx::Error> {
+    if rows.is_empty() {
+        return Ok(());
+    }
+    let mut builder = QueryBuilder::<Postgres>::new(
+        "INSERT INTO import_job_rows (job_id, row_number, record_id, action, previous) ",
+    );
+    builder.push_values(rows, |mut b, row| {
+        b.push_bind(job_id)
+            .push_bind(row.row_number as i32)
+            .push_bind(row.record_id)
+            .push_bind(row.action)
+            .push_bind(row.previous);
+    });
+    builder.build().execute(&mut **tx).await?;
+    Ok(())
+}
+
+/// Record a new job as running and return its ID
+pub async fn start_job(
+    pool: &Pool<Postgres>,
+    job: &NewJob,
+    entity: &EntityDef,
+    user_id: &str,
+    options: &ImportOptions,
+) -> Result<Uuid, sqlx::Error> {
+    sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO import_jobs (
+            source, table_name, file_name, file_hash, profile, column_mappings,
+            options, status, created_by, started_at
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, 'running', $8, $9)
+        RETURNING id
+        "#
+    )
+    .bind(&job.source)
+    .bind(entity.name)
+    .bind(&job.file_name)
+    .bind(&job.file_hash)
+    .bind(&job.profile)
+    .bind(&job.column_mappings)
+    .bind(json!(options))
+    .bind(user_id)
+    .bind(Utc::now())
+    .fetch_one(pool)
+    .await
+}
+
+/// The status a finished import is recorded with
+pub fn job_status(result: &Result<ImportOutcome, sqlx::Error>, options: &ImportOptions) -> &'static str {
+    match result {
+        Ok(_) if options.dry_run => "dry_run",
+        Ok(outcome) if outcome.cancelled => "cancelled",
+        Ok(outcome) if outcome.rolled_back => "discarded",
+        Ok(_) => "completed",
+        Err(_) => "failed",
+    }
+}
+
+/// Record how a job ended
+pub async fn finish_job(
+    pool: &Pool<Postgres>,
+    job_id: Uuid,
+    status: &str,
+    result: &Result<ImportOutcome, sqlx::Error>,
+) -> Result<(), sqlx::Error> {
+    let empty = ImportOutcome::default();
+    let (outcome, errors) = match result {
+        Ok(outcome) => (outcome, json!(outcome.errors)),
+        Err(e) => (&empty, json!([e.to_string()])),
+    };
+    sqlx::query(
+        r#"
+        UPDATE import_jobs SET
+            status = $2,
+            records_processed = $3,
+            records_inserted = $4,
+            records_updated = $5,
+            records_skipped = $6,
+            records_rejected = $7,
+            errors = $8,
+            finished_at = $9
+        WHERE id = $1
+        "#
+    )
+    .bind(job_id)
+    .bind(status)
+    .bind(outcome.processed as i32)
+    .bind(outcome.inserted as i32)
+    .bind(outcome.updated as i32)
+    .bind(outcome.skipped as i32)
+    .bind(outcome.rejected as i32)
+    .bind(errors)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+    save_rejections(pool, job_id, &outcome.rejections).await
+}
+
+/// Keep a job's rejected rows for `GET /api/import/jobs/{id}/rejected`
+async fn save_rejections(pool: &Pool<Postgres>, job_id: Uuid, rejections: &[Rejection]) -> Result<(), sqlx::Error> {
+    // Four parameters a row, well under Postgres' limit
+    for chunk in rejections.chunks(REJECTIONS_PER_INSERT) {
+        let mut builder = QueryBuilder::<Postgres>::new(
+            "INSERT INTO import_job_rejections (job_id, row_number, cells, errors) ",
+        );
+        builder.push_values(chunk, |mut b, rejection| {
+            b.push_bind(job_id)
+                .push_bind(rejection.row as i32)
+                .push_bind(json!(rejection.cells))
+                .push_bind(json!(rejection.errors));
+        });
+        builder.build().execute(pool).await?;
+    }
+    Ok(())
+}
+
+/// Mark jobs left running by a previous server process as failed
+pub async fn fail_interrupted(pool: &Pool<Postgres>) -> Result<u64, sqlx::Error> {
+    let done = sqlx::query(
+        r#"
+        UPDATE import_jobs SET
+            status = 'failed',
+            errors = errors || '["The server stopped before the import finished"]'::jsonb,
+            finished_at = $1
+        WHERE status = 'running'
+        "#
+    )
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+    Ok(done.rows_affected())
+}
+
+fn job_not_found(id: Uuid) -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "error": format!("Import job {} not found", id)
+    }))
+}
+
+/// A job the user may see: their own, or any job for admins
+pub async fn find_job(pool: &Pool<Postgres>, id: Uuid, claims: &Claims) -> std::result::Result<Value, HttpResponse> {
+    let job = sqlx::query_scalar::<_, Value>("SELECT to_jsonb(j) FROM import_jobs j WHERE j.id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(entities::database_error)?
+        .ok_or_else(|| job_not_found(id))?;
+    if job.get("created_by").and_then(Value::as_str) == Some(claims.user_id().as_str()) {
+        return Ok(job);
+    }
+    match auth::is_admin(pool, claims.sub).await {
+        Ok(true) => Ok(job),
+        // Other users' jobs are reported as missing
+        Ok(false) => Err(job_not_found(id)),
+        Err(e) => Err(entities::database_error(e)),
+    }
+}
+
+/// `GET /api/import/jobs`: newest first, filtered by `status`, `table_name`
+/// or `source`. Admins see every user's jobs.
+pub async fn list_jobs(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    let pagination = match Pagination::from_query(&query) {
+        Ok(p) => p,
+        Err(e) => return Ok(entities::validation_failed(vec![e])),
+    };
+    let mut filters = Vec::new();
+    let mut errors = Vec::new();
+    for (key, value) in query.iter().filter(|(k, _)| k.as_str() != "limit" && k.as_str() != "offset") {
+        match key.as_str() {
+            "status" | "table_name" | "source" => filters.push((key.as_str(), value.clone())),
+            _ => errors.push(format!("Unknown filter: {}", key)),
+        }
+    }
+    if !errors.is_empty() {
+        return Ok(entities::validation_failed(errors));
+    }
+    let is_admin = match auth::is_admin(&data.db, claims.sub).await {
+        Ok(is_admin) => is_admin,
+        Err(e) => return Ok(entities::database_error(e)),
+    };
+    if !is_admin {
+        filters.push(("created_by", claims.user_id()));
+    }
+
+    // Filters are pushed twice (count + page), so build both queries in step
+    let mut count_query = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM import_jobs j WHERE 1=1");
+    let mut list_query = QueryBuilder::<Postgres>::new("SELECT to_jsonb(j) FROM import_jobs j WHERE 1=1");
+    for (name, value) in filters {
+        count_query.push(format!(" AND j.{} = ", name)).push_bind(value.clone());
+        list_query.push(format!(" AND j.{} = ", name)).push_bind(value);
+    }
+    list_query
+        .push(" ORDER BY j.started_at DESC, j.id LIMIT ")
+        .push_bind(pagination.limit())
+        .push(" OFFSET ")
+        .push_bind(pagination.offset());
+
+    let total = match count_query.build_query_scalar::<i64>().fetch_one(&data.db).await {
+        Ok(total) => total,
+        Err(e) => return Ok(entities::database_error(e)),
+    };
+    match list_query.build_query_scalar::<Value>().fetch_all(&data.db).await {
+        Ok(jobs) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "pagination": pagination.to_json(total, jobs.len()),
+            "data": jobs
+        }))),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+/// `GET /api/import/jobs/{id}`: the job and how many records are linked to it
+pub async fn get_job(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let job = match find_job(&data.db, id, &claims).await {
+        Ok(job) => job,
+        Err(response) => return Ok(response),
+    };
+    let linked = sqlx::query_as::<_, (String, i64)>(
+        "SELECT action, COUNT(*) FROM import_job_rows WHERE job_id = $1 GROUP BY action",
+    )
+    .bind(id)
+    .fetch_all(&data.db)
+    .await;
+
+    match linked {
+        Ok(counts) => {
+            let count = |action: &str| counts.iter().find(|(a, _)| a == action).map_or(0, |(_, n)| *n);
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": job,
+                "linked_records": {
+                    "inserted": count("insert"),
+                    "updated": count("update")
+                }
+            })))
+        }
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+/// Rejected rows as CSV: the columns rows were given with, in the order they
+/// were first seen, then `REJECTION_COLUMNS`
+fn rejections_csv(rejections: Vec<(i32, Value, Value)>) -> Result<Vec<u8>, String> {
+    let rows: Vec<Rejection> = rejections
+        .into_iter()
+        .map(|(row_number, cells, errors)| Rejection {
+            row: row_number.max(0) as usize,
+            cells: serde_json::from_value(cells).unwrap_or_default(),
+            errors: serde_json::from_value(errors).unwrap_or_default(),
+        })
+        .collect();
+    let mut columns: Vec<&str> = Vec::new();
+    for (name, _) in rows.iter().flat_map(|rejection| &rejection.cells) {
+        if !columns.contains(&name.as_str()) {
+            columns.push(name);
+        }
+    }
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(columns.iter().chain(REJECTION_COLUMNS))
+        .map_err(|e| e.to_string())?;
+    for Rejection { row, cells, errors } in &rows {
+        let mut record: Vec<String> = columns
+            .iter()
+            .map(|column| {
+                cells
+                    .iter()
+                    .find(|(name, _)| name == column)
+                    .map(|(_, value)| export::cell_text(value))
+                    .unwrap_or_default()
+            })
+            .collect();
+        record.push(row.to_string());
+        record.push(errors.iter().map(|e| e.code.as_str()).collect::<Vec<_>>().join(", "));
+        record.push(import_validation::summary(errors));
+        writer.write_record(&record).map_err(|e| e.to_string())?;
+    }
+    writer.into_inner().map_err(|e| e.to_string())
+}
+
+/// `GET /api/import/jobs/{id}/rejected`: the rows the job rejected, as they
+/// were given, as CSV with the row number, error codes and reasons appended.
+/// Fixed rows can be imported again with the same table and mapping; the
+/// appended columns are not mapped.
+pub async fn rejected_rows(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let job = match find_job(&data.db, id, &claims).await {
+        Ok(job) => job,
+        Err(response) => return Ok(response),
+    };
+    let rejections = sqlx::query_as::<_, (i32, Value, Value)>(
+        "SELECT row_number, cells, errors FROM import_job_rejections WHERE job_id = $1 ORDER BY row_number",
+    )
+    .bind(id)
+    .fetch_all(&data.db)
+    .await;
+    let body = match rejections.map_err(|e| e.to_string()).and_then(rejections_csv) {
+        Ok(body) => body,
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "error": format!("Failed to read rejected rows: {}", e)
+            })));
+        }
+    };
+
+    let table = job.get("table_name").and_then(Value::as_str).unwrap_or("import");
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}-{}-rejected.csv\"", table, id),
+        ))
+        .body(body))
+}
+
+fn cannot_roll_back(message: String) -> HttpResponse {
+    HttpResponse::Conflict().json(json!({
+        "success": false,
+        "error": message
+    }))
+}
+
+/// Put an updated record back the way it was before the import
+async fn restore_record(
+    tx: &mut Transaction<'_, Postgres>,
+    entity: &EntityDef,
+    id: Uuid,
+    previous: Value,
+) -> Result<u64, sqlx::Error> {
+    let mut columns: Vec<&str> = entity.fields.iter().map(|f| f.name).collect();
+    if entity.audited {
+        columns.extend(["date_modified", "modified_user_id"]);
+    }
+    let columns = columns.join(", ");
+    let done = sqlx::query(&format!(
+        "UPDATE {table} AS t SET ({columns}) = (SELECT {columns} FROM jsonb_populate_record(NULL::{table}, $1)) WHERE t.id = $2",
+        table = entity.name,
+        columns = columns,
+    ))
+    .bind(previous)
+    .bind(id)
+    .execute(&mut **tx)
+    .await?;
+    Ok(done.rows_affected())
+}
+
+/// `POST /api/import/jobs/{id}/rollback`: delete the records a job inserted
+/// and restore the ones it updated. Records edited since the job finished
+/// are reported and nothing is changed, unless `?force=true`.
+pub async fn rollback_job(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    path: web::Path<Uuid>,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let force = query.get("force").is_some_and(|v| v == "true");
+    let job = match find_job(&data.db, id, &claims).await {
+        Ok(job) => job,
+        Err(response) => return Ok(response),
+    };
+    let table_name = job.get("table_name").and_then(Value::as_str).unwrap_or_default();
+    let Some(entity) = entities::lookup(table_name) else {
+        return Ok(cannot_roll_back(format!("Import job {} targets unknown table {}", id, table_name)));
+    };
+    let finished_at = job
+        .get("finished_at")
+        .and_then(Value::as_str)
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc));
+
+    let result: Result<std::result::Result<(u64, u64, u64), HttpResponse>, sqlx::Error> = async {
+        let mut tx = data.db.begin().await?;
+        // Locking the job keeps two rollbacks of it from running at once
+        let status = sqlx::query_scalar::<_, String>("SELECT status FROM import_jobs WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+        if !matches!(status.as_str(), "completed" | "failed" | "cancelled") {
+            return Ok(Err(cannot_roll_back(format!("Import job {} is {} and cannot be rolled back", id, status))));
+        }
+
+        let rows = sqlx::query_as::<_, (Uuid, String, Option<Value>)>(
+            "SELECT record_id, action, previous FROM import_job_rows WHERE job_id = $1 ORDER BY row_number DESC",
+        )
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if entity.audited && !force {
+            let ids: Vec<Uuid> = rows.iter().map(|(record_id, _, _)| *record_id).collect();
+            let changed = sqlx::query_scalar::<_, Uuid>(&format!(
+                "SELECT id FROM {} WHERE id = ANY($1) AND date_modified > $2",
+                entity.name
+            ))
+            .bind(ids)
+            .bind(finished_at)
+            .fetch_all(&mut *tx)
+            .await?;
+            if !changed.is_empty() {
+                return Ok(Err(HttpResponse::Conflict().json(json!({
+                    "success": false,
+                    "error": format!(
+                        "{} records of import job {} were changed after the import; add ?force=true to roll back anyway",
+                        changed.len(), id
+                    ),
+                    "changed_records": changed
+                }))));
+            }
+        }
+
+        let (mut deleted, mut restored, mut missing) = (0, 0, 0);
+        for (record_id, action, previous) in rows {
+            let affected = match (action.as_str(), previous) {
+                ("update", Some(previous)) => {
+                    let affected = restore_record(&mut tx, entity, record_id, previous).await?;
+                    restored += affected;
+                    affected
+                }
+                _ => {
+                    // Links made after the import would otherwise block the delete
+                    relationships::unlink_all(&mut tx, entity, record_id).await?;
+                    let done = sqlx::query(&format!("DELETE FROM {} WHERE id = $1", entity.name))
+                        .bind(record_id)
+                        .execute(&mut *tx)
+                        .await?;
+                    deleted += done.rows_affected();
+                    done.rows_affected()
+                }
+            };
+            if affected == 0 {
+                missing += 1;
+            }
+        }
+
+        sqlx::query(
+            "UPDATE import_jobs SET status = 'rolled_back', rolled_back_at = $2, rolled_back_by = $3 WHERE id = $1",
+        )
+        .bind(id)
+        .bind(Utc::now())
+        .bind(claims.user_id())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(Ok((deleted, restored, missing)))
+    }
+    .await;
+
+    match result {
+        Ok(Ok((deleted, restored, missing))) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": format!(
+                "Rolled back import job {}: deleted {} and restored {} {} records",
+                id, deleted, restored, entity.name
+            ),
+            "deleted": deleted,
+            "restored": restored,
+            // Records already deleted since the import
+            "missing": missing
+        }))),
+        Ok(Err(response)) => Ok(response),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
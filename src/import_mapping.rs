@@ -0,0 +1,542 @@
+// src/import_mapping.rs
+//
+// Column mappings for spreadsheet imports: which source header feeds which
+// column of the target table. Cells are coerced towards the column's type
+// (Excel dates, "1,250.00", "yes"/"no", ...) and then checked by the same
+// `FieldDef` validation the entity endpoints use. Mappings can be saved as
+// named import profiles under `/api/import/profiles`.
+use actix_web::{web, HttpResponse, Result};
+use calamine::{Data, ExcelDateTime, ExcelDateTimeType};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{Pool, Postgres};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::{self, Claims};
+use crate::entities::{self, EntityDef, FieldDef, FieldType};
+use crate::import_validation::{CellError, ErrorCode};
+use crate::ApiState;
+
+/// Source column header -> target column. An empty target ignores the column.
+pub type ColumnMappings = HashMap<String, String>;
+
+/// Formats tried for text dates, after ISO 8601. Slashed dates are read US-style.
+const DATE_FORMATS: &[&str] = &["%Y/%m/%d", "%m/%d/%Y", "%d.%m.%Y", "%d %b %Y", "%b %d, %Y", "%B %d, %Y"];
+
+/// Formats tried for text timestamps without a UTC offset; they are taken as UTC
+const DATETIME_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%m/%d/%Y %H:%M:%S",
+    "%m/%d/%Y %H:%M",
+];
+
+/// A mapping checked against the sheet headers and the target table
+pub struct ResolvedMapping {
+    pub entity: &'static EntityDef,
+    /// Sheet column index and the field it fills
+    columns: Vec<(usize, &'static FieldDef)>,
+    /// Sheet headers that are not imported
+    pub unmapped: Vec<String>,
+}
+
+/// Tables that spreadsheets can be imported into
+pub fn importable_table(name: &str) -> Result<&'static EntityDef, String> {
+    match entities::lookup(name) {
+        Some(entity) if !auth::ADMIN_TABLES.contains(&entity.name) => Ok(entity),
+        _ => Err(format!("Unsupported table: {}", name)),
+    }
+}
+
+/// Lower-case with runs of other characters turned into `_`, so that
+/// "Phone (Office)" matches `phone_office`
+pub fn normalize_header(header: &str) -> String {
+    let mut normalized = String::new();
+    for c in header.trim().chars() {
+        if c.is_alphanumeric() {
+            normalized.extend(c.to_lowercase());
+        } else if !normalized.ends_with('_') {
+            normalized.push('_');
+        }
+    }
+    normalized.trim_matches('_').to_string()
+}
+
+/// Check that every mapped target is a column of `entity`, without a sheet
+pub fn check_targets(entity: &EntityDef, mappings: &ColumnMappings) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut seen = HashSet::new();
+    for (source, target) in mappings {
+        let target = target.trim();
+        if target.is_empty() {
+            continue;
+        }
+        if entity.field(target).is_none() {
+            errors.push(format!("'{}' is mapped to unknown column {}.{}", source, entity.name, target));
+        } else if !seen.insert(target) {
+            errors.push(format!("More than one column is mapped to {}", target));
+        }
+    }
+    errors.sort();
+    errors
+}
+
+impl ResolvedMapping {
+    /// Match `mappings` against the sheet headers. Without mappings, headers
+    /// that name a column of the table (ignoring case and punctuation) are used.
+    pub fn resolve(
+        entity: &'static EntityDef,
+        headers: &[String],
+        mappings: Option<&ColumnMappings>,
+    ) -> Result<Self, Vec<String>> {
+        let mut columns = Vec::new();
+        let mut errors = Vec::new();
+
+        match mappings {
+            Some(mappings) => {
+                errors.extend(check_targets(entity, mappings));
+                for (source, target) in mappings {
+                    let Some(field) = entity.field(target.trim()) else {
+                        continue;
+                    };
+                    match headers.iter().position(|h| h.trim().eq_ignore_ascii_case(source.trim())) {
+                        Some(index) => columns.push((index, field)),
+                        None => errors.push(format!("Column '{}' not found in the sheet", source)),
+                    }
+                }
+            }
+            None => {
+                for (index, header) in headers.iter().enumerate() {
+                    let Some(field) = entity.field(&normalize_header(header)) else {
+                        continue;
+                    };
+                    if !columns.iter().any(|(_, f): &(usize, &FieldDef)| f.name == field.name) {
+                        columns.push((index, field));
+                    }
+                }
+            }
+        }
+
+        if columns.is_empty() && errors.is_empty() {
+            errors.push(format!("No sheet columns are mapped to {}", entity.name));
+        }
+        for field in entity.fields.iter().filter(|f| f.required) {
+            if !columns.iter().any(|(_, f)| f.name == field.name) {
+                errors.push(format!("Required column {} is not mapped", field.name));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        columns.sort_by_key(|(index, _)| *index);
+        let unmapped = headers
+            .iter()
+            .enumerate()
+            .filter(|(index, header)| !header.trim().is_empty() && !columns.iter().any(|(i, _)| i == index))
+            .map(|(_, header)| header.clone())
+            .collect();
+        Ok(ResolvedMapping { entity, columns, unmapped })
+    }
+
+    /// The mapping in effect, as source header -> target column
+    pub fn describe(&self, headers: &[String]) -> BTreeMap<String, &'static str> {
+        self.columns
+            .iter()
+            .map(|(index, field)| (headers[*index].clone(), field.name))
+            .collect()
+    }
+
+    /// Coerced values of one row keyed by target column, or None when every mapped cell is empty
+    pub fn row_json(&self, row: &[Data]) -> Option<Result<serde_json::Map<String, Value>, Vec<CellError>>> {
+        let mut record = serde_json::Map::new();
+        let mut errors = Vec::new();
+        for (index, field) in &self.columns {
+            match coerce_cell(field, row.get(*index).unwrap_or(&Data::Empty)) {
+                Ok(value) => {
+                    record.insert(field.name.to_string(), value);
+                }
+                Err(e) => errors.push(CellError::new(field.name, ErrorCode::InvalidCell, e)),
+            }
+        }
+
+        if errors.is_empty() && record.values().all(Value::is_null) {
+            return None;
+        }
+        Some(if errors.is_empty() { Ok(record) } else { Err(errors) })
+    }
+}
+
+fn trimmed(s: &str) -> Option<String> {
+    let s = s.trim();
+    if s.is_empty() { None } else { Some(s.to_string()) }
+}
+
+/// Excel stores dates as days since 1899-12-30
+fn serial_to_datetime(serial: f64) -> Option<NaiveDateTime> {
+    ExcelDateTime::new(serial, ExcelDateTimeType::DateTime, false).as_datetime()
+}
+
+/// A sheet row as header -> cell text, in sheet order, for reporting the row
+/// back as it was given
+pub fn source_cells(headers: &[String], row: &[Data]) -> Vec<(String, Value)> {
+    headers
+        .iter()
+        .enumerate()
+        .filter(|(_, header)| !header.trim().is_empty())
+        .map(|(index, header)| {
+            let cell = row.get(index).unwrap_or(&Data::Empty);
+            let text = match cell_text(cell) {
+                Ok(text) => text,
+                Err(_) => Some(cell.to_string()),
+            };
+            (header.clone(), text.map(Value::String).unwrap_or(Value::Null))
+        })
+        .collect()
+}
+
+/// A cell as text, with whole numbers written without ".0"
+fn cell_text(cell: &Data) -> Result<Option<String>, String> {
+    Ok(match cell {
+        Data::Empty => None,
+        Data::Error(e) => return Err(format!("cell contains the error {}", e)),
+        Data::String(s) | Data::DateTimeIso(s) | Data::DurationIso(s) => trimmed(s),
+        Data::Float(f) if f.fract() == 0.0 && f.abs() < 1e15 => Some(format!("{}", *f as i64)),
+        Data::Float(f) => Some(f.to_string()),
+        Data::Int(i) => Some(i.to_string()),
+        Data::Bool(b) => Some(b.to_string()),
+        Data::DateTime(dt) => dt.as_datetime().map(|d| {
+            if d.time() == NaiveTime::MIN {
+                d.date().to_string()
+            } else {
+                d.format("%Y-%m-%dT%H:%M:%S").to_string()
+            }
+        }),
+    })
+}
+
+fn parse_date(cell: &Data, text: &str) -> Option<NaiveDate> {
+    match cell {
+        Data::DateTime(dt) => dt.as_datetime().map(|d| d.date()),
+        Data::Float(f) => serial_to_datetime(*f).map(|d| d.date()),
+        Data::Int(i) => serial_to_datetime(*i as f64).map(|d| d.date()),
+        _ => NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .ok()
+            .or_else(|| DATE_FORMATS.iter().find_map(|f| NaiveDate::parse_from_str(text, f).ok()))
+            .or_else(|| parse_datetime(cell, text).map(|dt| dt.date_naive())),
+    }
+}
+
+fn parse_datetime(cell: &Data, text: &str) -> Option<DateTime<Utc>> {
+    let naive = match cell {
+        Data::DateTime(dt) => dt.as_datetime(),
+        Data::Float(f) => serial_to_datetime(*f),
+        Data::Int(i) => serial_to_datetime(*i as f64),
+        _ => {
+            if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+                return Some(dt.with_timezone(&Utc));
+            }
+            DATETIME_FORMATS
+                .iter()
+                .find_map(|f| NaiveDateTime::parse_from_str(text, f).ok())
+                .or_else(|| {
+                    std::iter::once("%Y-%m-%d")
+                        .chain(DATE_FORMATS.iter().copied())
+                        .find_map(|f| NaiveDate::parse_from_str(text, f).ok())
+                        .map(|d| d.and_time(NaiveTime::MIN))
+                })
+        }
+    };
+    naive.map(|dt| dt.and_utc())
+}
+
+/// Turn a cell into the JSON form `FieldDef::parse` expects for `field`.
+/// Values that cannot be coerced are passed on as text, so validation
+/// reports them with the usual message.
+pub fn coerce_cell(field: &FieldDef, cell: &Data) -> Result<Value, String> {
+    let text = match cell_text(cell) {
+        Ok(Some(text)) => text,
+        Ok(None) => return Ok(Value::Null),
+        Err(e) => return Err(format!("{}: {}", field.name, e)),
+    };
+
+    Ok(match field.kind {
+        FieldType::Text(_) | FieldType::Uuid => Value::String(text),
+        FieldType::Integer => match cell {
+            Data::Int(i) => json!(i),
+            Data::Float(f) if f.fract() == 0.0 => json!(*f as i64),
+            _ => {
+                let cleaned = text.replace([',', ' '], "");
+                cleaned.parse::<i64>().map(|n| json!(n)).unwrap_or(Value::String(text))
+            }
+        },
+        FieldType::Decimal => match cell {
+            Data::Int(i) => json!(i),
+            Data::Float(f) => json!(f),
+            // Thousands separators and currency symbols
+            _ => Value::String(text.replace([',', ' ', '$', '€', '£'], "")),
+        },
+        FieldType::Boolean => match cell {
+            Data::Bool(b) => json!(b),
+            Data::Int(i) => json!(*i != 0),
+            Data::Float(f) => json!(*f != 0.0),
+            _ => match text.to_lowercase().as_str() {
+                "true" | "t" | "yes" | "y" | "1" | "x" => json!(true),
+                "false" | "f" | "no" | "n" | "0" => json!(false),
+                _ => Value::String(text),
+            },
+        },
+        FieldType::Date => parse_date(cell, &text)
+            .map(|d| json!(d.format("%Y-%m-%d").to_string()))
+            .unwrap_or(Value::String(text)),
+        FieldType::Timestamp => parse_datetime(cell, &text)
+            .map(|dt| json!(dt.to_rfc3339()))
+            .unwrap_or(Value::String(text)),
+    })
+}
+
+/// A saved mapping, as stored in `import_profiles`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportProfile {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub table_name: String,
+    pub sheet_name: Option<String>,
+    pub column_mappings: ColumnMappings,
+    pub date_entered: Option<DateTime<Utc>>,
+    pub date_modified: Option<DateTime<Utc>>,
+    pub created_by: Option<String>,
+    pub modified_user_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateProfileRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub table_name: String,
+    pub sheet_name: Option<String>,
+    pub column_mappings: ColumnMappings,
+}
+
+/// Partial update - only fields that are present are changed
+#[derive(Debug, Deserialize)]
+pub struct UpdateProfileRequest {
+    pub description: Option<String>,
+    pub table_name: Option<String>,
+    pub sheet_name: Option<String>,
+    pub column_mappings: Option<ColumnMappings>,
+}
+
+fn profile_from_json(value: Value) -> Result<ImportProfile, sqlx::Error> {
+    serde_json::from_value(value).map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+/// Load a saved profile by name
+pub async fn find_profile(pool: &Pool<Postgres>, name: &str) -> Result<Option<ImportProfile>, sqlx::Error> {
+    sqlx::query_scalar::<_, Value>("SELECT to_jsonb(p) FROM import_profiles p WHERE p.name = $1")
+        .bind(name)
+        .fetch_optional(pool)
+        .await?
+        .map(profile_from_json)
+        .transpose()
+}
+
+/// Check a profile's table and targets before it is stored
+fn validate_profile(table_name: &str, mappings: &ColumnMappings) -> Result<(), Vec<String>> {
+    let entity = importable_table(table_name).map_err(|e| vec![e])?;
+    if mappings.values().all(|target| target.trim().is_empty()) {
+        return Err(vec!["column_mappings must map at least one column".to_string()]);
+    }
+    let errors = check_targets(entity, mappings);
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+fn profile_not_found(name: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "error": format!("Import profile '{}' not found", name)
+    }))
+}
+
+/// Profiles are shared by name, so only the user who saved one, or an
+/// admin, may change or delete it
+async fn check_profile_owner(
+    pool: &Pool<Postgres>,
+    claims: &Claims,
+    profile: &ImportProfile,
+) -> std::result::Result<(), HttpResponse> {
+    if profile.created_by.as_deref() == Some(claims.user_id().as_str()) {
+        return Ok(());
+    }
+    match auth::is_admin(pool, claims.sub).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": format!("Import profile '{}' belongs to another user", profile.name)
+        }))),
+        Err(e) => Err(entities::database_error(e)),
+    }
+}
+
+pub async fn list_profiles(data: web::Data<Arc<ApiState>>) -> Result<HttpResponse> {
+    let result = sqlx::query_scalar::<_, Value>("SELECT to_jsonb(p) FROM import_profiles p ORDER BY p.name")
+        .fetch_all(&data.db)
+        .await;
+    match result {
+        Ok(profiles) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": profiles
+        }))),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+pub async fn get_profile(
+    data: web::Data<Arc<ApiState>>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let name = path.into_inner();
+    match find_profile(&data.db, &name).await {
+        Ok(Some(profile)) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": profile
+        }))),
+        Ok(None) => Ok(profile_not_found(&name)),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+pub async fn create_profile(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    req: web::Json<CreateProfileRequest>,
+) -> Result<HttpResponse> {
+    let name = req.name.trim();
+    if name.is_empty() || name.chars().count() > 150 {
+        return Ok(entities::validation_failed(vec!["name must be 1 to 150 characters".to_string()]));
+    }
+    if let Err(errors) = validate_profile(&req.table_name, &req.column_mappings) {
+        return Ok(entities::validation_failed(errors));
+    }
+
+    let now = Utc::now();
+    let result = sqlx::query_scalar::<_, Value>(
+        r#"
+        INSERT INTO import_profiles AS p (
+            name, description, table_name, sheet_name, column_mappings,
+            date_entered, date_modified, created_by, modified_user_id
+        ) VALUES ($1, $2, $3, $4, $5, $6, $6, $7, $7)
+        RETURNING to_jsonb(p)
+        "#
+    )
+    .bind(name)
+    .bind(&req.description)
+    .bind(&req.table_name)
+    .bind(&req.sheet_name)
+    .bind(json!(req.column_mappings))
+    .bind(now)
+    .bind(claims.user_id())
+    .fetch_one(&data.db)
+    .await;
+
+    match result {
+        Ok(profile) => Ok(HttpResponse::Created().json(json!({
+            "success": true,
+            "message": format!("Import profile '{}' saved", name),
+            "data": profile
+        }))),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+pub async fn update_profile(
+    data: web::Data<Arc<ApiState>>,
+    path: web::Path<String>,
+    claims: Claims,
+    req: web::Json<UpdateProfileRequest>,
+) -> Result<HttpResponse> {
+    let name = path.into_inner();
+    let existing = match find_profile(&data.db, &name).await {
+        Ok(Some(profile)) => profile,
+        Ok(None) => return Ok(profile_not_found(&name)),
+        Err(e) => return Ok(entities::database_error(e)),
+    };
+    if let Err(response) = check_profile_owner(&data.db, &claims, &existing).await {
+        return Ok(response);
+    }
+
+    // The table and mapping are checked together, since either may change
+    let table_name = req.table_name.clone().unwrap_or(existing.table_name);
+    let mappings = req.column_mappings.clone().unwrap_or(existing.column_mappings);
+    if let Err(errors) = validate_profile(&table_name, &mappings) {
+        return Ok(entities::validation_failed(errors));
+    }
+
+    let result = sqlx::query_scalar::<_, Value>(
+        r#"
+        UPDATE import_profiles AS p SET
+            description = COALESCE($2, p.description),
+            table_name = $3,
+            sheet_name = COALESCE($4, p.sheet_name),
+            column_mappings = $5,
+            date_modified = $6,
+            modified_user_id = $7
+        WHERE p.name = $1
+        RETURNING to_jsonb(p)
+        "#
+    )
+    .bind(&name)
+    .bind(&req.description)
+    .bind(&table_name)
+    .bind(&req.sheet_name)
+    .bind(json!(mappings))
+    .bind(Utc::now())
+    .bind(claims.user_id())
+    .fetch_optional(&data.db)
+    .await;
+
+    match result {
+        Ok(Some(profile)) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": format!("Import profile '{}' updated", name),
+            "data": profile
+        }))),
+        Ok(None) => Ok(profile_not_found(&name)),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+pub async fn delete_profile(
+    data: web::Data<Arc<ApiState>>,
+    path: web::Path<String>,
+    claims: Claims,
+) -> Result<HttpResponse> {
+    let name = path.into_inner();
+    let existing = match find_profile(&data.db, &name).await {
+        Ok(Some(profile)) => profile,
+        Ok(None) => return Ok(profile_not_found(&name)),
+        Err(e) => return Ok(entities::database_error(e)),
+    };
+    if let Err(response) = check_profile_owner(&data.db, &claims, &existing).await {
+        return Ok(response);
+    }
+
+    let result = sqlx::query("DELETE FROM import_profiles WHERE name = $1")
+        .bind(&name)
+        .execute(&data.db)
+        .await;
+
+    match result {
+        Ok(done) if done.rows_affected() == 0 => Ok(profile_not_found(&name)),
+        Ok(_) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": format!("Import profile '{}' deleted", name)
+        }))),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
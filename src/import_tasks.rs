@@ -0,0 +1,374 @@
+// src/import_tasks.rs
+//
+// Running imports. Every import runs in a spawned task: the request that
+// started it waits for the outcome or, with `"background": true`, returns
+// its job ID at once. Either way it is tracked here while it runs: progress
+// is published after every batch, can be polled or followed as Server-Sent
+// Events, and the job can be cancelled. Finished jobs are read back from
+// `import_jobs`.
+use actix_web::web::Bytes;
+use actix_web::{web, HttpResponse, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::auth::Claims;
+use crate::entities::{self, EntityDef};
+use crate::import_batch::{self, DuplicateCheck, ImportOptions, ImportOutcome, ImportRow, RowAction, RowReport};
+use crate::import_jobs::{self, NewJob};
+use crate::ApiState;
+
+/// Rejected rows listed in progress updates; the job keeps all of them
+const MAX_PROGRESS_ERRORS: usize = 100;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportProgress {
+    /// `running` or `cancelling`, then the status the job is recorded with
+    pub status: String,
+    pub total_rows: usize,
+    pub processed: usize,
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub rejected: usize,
+    pub batches: usize,
+    pub errors: Vec<String>,
+}
+
+impl ImportProgress {
+    fn finished(&self) -> bool {
+        !matches!(self.status.as_str(), "running" | "cancelling")
+    }
+
+    /// Progress of a job that is no longer running, from its `import_jobs` row
+    fn from_job(job: &Value) -> Self {
+        let count = |key: &str| job.get(key).and_then(Value::as_u64).unwrap_or(0) as usize;
+        let errors: Vec<String> = job
+            .get("errors")
+            .and_then(Value::as_array)
+            .map(|errors| errors.iter().filter_map(|e| e.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+        ImportProgress {
+            status: job.get("status").and_then(Value::as_str).unwrap_or("unknown").to_string(),
+            total_rows: count("records_processed"),
+            processed: count("records_processed"),
+            inserted: count("records_inserted"),
+            updated: count("records_updated"),
+            skipped: count("records_skipped"),
+            rejected: count("records_rejected"),
+            batches: 0,
+            errors: errors.into_iter().take(MAX_PROGRESS_ERRORS).collect(),
+        }
+    }
+}
+
+/// A running import job
+pub struct JobTracker {
+    pub id: Uuid,
+    progress: watch::Sender<ImportProgress>,
+    cancelled: AtomicBool,
+}
+
+impl JobTracker {
+    fn new(id: Uuid, total_rows: usize) -> Self {
+        let (progress, _) = watch::channel(ImportProgress {
+            status: "running".to_string(),
+            total_rows,
+            ..Default::default()
+        });
+        JobTracker { id, progress, cancelled: AtomicBool::new(false) }
+    }
+
+    /// Count rows decided since the last report
+    pub fn report(&self, reports: &[RowReport], batches: usize) {
+        self.progress.send_modify(|progress| {
+            for report in reports {
+                progress.processed += 1;
+                match report.action {
+                    RowAction::Insert => progress.inserted += 1,
+                    RowAction::Update => progress.updated += 1,
+                    RowAction::Skip => progress.skipped += 1,
+                    RowAction::Reject => {
+                        progress.rejected += 1;
+                        if progress.errors.len() < MAX_PROGRESS_ERRORS {
+                            let reason = report.reason.as_deref().unwrap_or("rejected");
+                            progress.errors.push(format!("Row {}: {}", report.row, reason));
+                        }
+                    }
+                }
+            }
+            progress.batches = batches;
+        });
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.progress.send_modify(|progress| progress.status = "cancelling".to_string());
+    }
+
+    /// Publish the final counts, which differ from the running ones when
+    /// the import was rolled back
+    fn finish(&self, status: &str, result: &std::result::Result<ImportOutcome, sqlx::Error>) {
+        self.progress.send_modify(|progress| {
+            progress.status = status.to_string();
+            match result {
+                Ok(outcome) => {
+                    progress.processed = outcome.processed;
+                    progress.inserted = outcome.inserted;
+                    progress.updated = outcome.updated;
+                    progress.skipped = outcome.skipped;
+                    progress.rejected = outcome.rejected;
+                    progress.batches = outcome.batches;
+                }
+                Err(e) => progress.errors.push(e.to_string()),
+            }
+        });
+    }
+}
+
+/// Imports running in this server process
+#[derive(Default)]
+pub struct RunningImports {
+    jobs: Mutex<HashMap<Uuid, Arc<JobTracker>>>,
+}
+
+impl RunningImports {
+    fn register(&self, id: Uuid, total_rows: usize) -> Arc<JobTracker> {
+        let tracker = Arc::new(JobTracker::new(id, total_rows));
+        self.jobs.lock().unwrap().insert(id, tracker.clone());
+        tracker
+    }
+
+    fn get(&self, id: Uuid) -> Option<Arc<JobTracker>> {
+        self.jobs.lock().unwrap().get(&id).cloned()
+    }
+
+    fn remove(&self, id: Uuid) {
+        self.jobs.lock().unwrap().remove(&id);
+    }
+}
+
+/// An import ready to run, with everything the rows need
+pub struct ImportRun {
+    pub job: NewJob,
+    pub entity: &'static EntityDef,
+    pub user_id: String,
+    pub rows: Vec<ImportRow>,
+    pub duplicates: DuplicateCheck,
+    pub options: ImportOptions,
+}
+
+impl ImportRun {
+    async fn start(&self, state: &ApiState) -> std::result::Result<Arc<JobTracker>, sqlx::Error> {
+        let id = import_jobs::start_job(&state.db, &self.job, self.entity, &self.user_id, &self.options).await?;
+        Ok(state.imports.register(id, self.rows.len()))
+    }
+
+    async fn run(self, state: &ApiState, tracker: Arc<JobTracker>) -> std::result::Result<ImportOutcome, sqlx::Error> {
+        let result = import_batch::run_import(
+            &state.db,
+            self.entity,
+            &self.user_id,
+            &self.rows,
+            &self.duplicates,
+            &self.options,
+            Some(&tracker),
+        )
+        .await;
+        let status = import_jobs::job_status(&result, &self.options);
+        if let Err(e) = import_jobs::finish_job(&state.db, tracker.id, status, &result).await {
+            println!("Failed to record the outcome of import job {}: {}", tracker.id, e);
+        }
+        tracker.finish(status, &result);
+        state.imports.remove(tracker.id);
+        result
+    }
+
+    /// Run the import and wait for it to finish. It runs in its own task, so
+    /// the job is still finished and untracked if the waiting request is
+    /// dropped because the client went away.
+    pub async fn execute(self, state: Arc<ApiState>) -> std::result::Result<(Uuid, ImportOutcome), sqlx::Error> {
+        let task = tokio::spawn(async move {
+            let tracker = self.start(&state).await?;
+            let id = tracker.id;
+            self.run(&state, tracker).await.map(|outcome| (id, outcome))
+        });
+        match task.await {
+            Ok(result) => result,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+
+    /// Run the import outside the API server, e.g. from the CLI. The job is
+    /// recorded as usual but cannot be followed or cancelled.
+    pub async fn execute_untracked(self, pool: &Pool<Postgres>) -> std::result::Result<(Uuid, ImportOutcome), sqlx::Error> {
+        let id = import_jobs::start_job(pool, &self.job, self.entity, &self.user_id, &self.options).await?;
+        let result = import_batch::run_import(
+            pool,
+            self.entity,
+            &self.user_id,
+            &self.rows,
+            &self.duplicates,
+            &self.options,
+            None,
+        )
+        .await;
+        let status = import_jobs::job_status(&result, &self.options);
+        import_jobs::finish_job(pool, id, status, &result).await?;
+        result.map(|outcome| (id, outcome))
+    }
+
+    /// Start the import in the background and return its job ID
+    pub async fn spawn(self, state: Arc<ApiState>) -> std::result::Result<Uuid, sqlx::Error> {
+        let tracker = self.start(&state).await?;
+        let id = tracker.id;
+        tokio::spawn(async move {
+            if let Err(e) = self.run(&state, tracker).await {
+                println!("Background import job {} failed: {}", id, e);
+            }
+        });
+        Ok(id)
+    }
+}
+
+/// The `202 Accepted` answer to a background import
+pub fn accepted(job_id: Uuid) -> HttpResponse {
+    HttpResponse::Accepted().json(json!({
+        "success": true,
+        "message": format!("Import job {} started", job_id),
+        "job_id": job_id,
+        "progress_url": format!("/api/import/jobs/{}/progress", job_id),
+        "events_url": format!("/api/import/jobs/{}/events", job_id)
+    }))
+}
+
+/// Current progress of a job the user may see, running or not
+async fn current_progress(
+    state: &ApiState,
+    id: Uuid,
+    claims: &Claims,
+) -> std::result::Result<(Option<Arc<JobTracker>>, ImportProgress), HttpResponse> {
+    let job = import_jobs::find_job(&state.db, id, claims).await?;
+    match state.imports.get(id) {
+        Some(tracker) => {
+            let progress = tracker.progress.borrow().clone();
+            Ok((Some(tracker), progress))
+        }
+        None => Ok((None, ImportProgress::from_job(&job))),
+    }
+}
+
+/// `GET /api/import/jobs/{id}/progress`
+pub async fn job_progress(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    match current_progress(&data, id, &claims).await {
+        Ok((_, progress)) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "job_id": id,
+            "progress": progress
+        }))),
+        Err(response) => Ok(response),
+    }
+}
+
+fn progress_event(progress: &ImportProgress) -> Bytes {
+    let data = serde_json::to_string(progress).unwrap_or_default();
+    Bytes::from(format!("event: progress\ndata: {}\n\n", data))
+}
+
+/// `GET /api/import/jobs/{id}/events`: a `progress` event now and after every
+/// batch, ending with the job's final status
+pub async fn job_events(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let (tracker, progress) = match current_progress(&data, id, &claims).await {
+        Ok(current) => current,
+        Err(response) => return Ok(response),
+    };
+
+    // Subscribe before taking the first event so no update falls in between
+    let (first, updates) = match tracker {
+        Some(tracker) if !progress.finished() => {
+            let mut receiver = tracker.progress.subscribe();
+            let current = receiver.borrow_and_update().clone();
+            (progress_event(&current), (!current.finished()).then_some(receiver))
+        }
+        _ => (progress_event(&progress), None),
+    };
+    // Each update is sent once it arrives; the stream ends with the job
+    let stream = futures_util::stream::unfold((Some(first), updates), |(pending, updates)| async move {
+        if let Some(event) = pending {
+            return Some((Ok::<_, actix_web::Error>(event), (None, updates)));
+        }
+        let mut receiver = updates?;
+        receiver.changed().await.ok()?;
+        let progress = receiver.borrow_and_update().clone();
+        let next = (!progress.finished()).then_some(receiver);
+        Some((Ok(progress_event(&progress)), (None, next)))
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream))
+}
+
+/// `POST /api/import/jobs/{id}/cancel`: stop a running import before its
+/// next batch. Batches already committed stay until the job is rolled back.
+pub async fn cancel_job(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let (tracker, progress) = match current_progress(&data, id, &claims).await {
+        Ok(current) => current,
+        Err(response) => return Ok(response),
+    };
+    match tracker {
+        Some(tracker) if !progress.finished() => {
+            tracker.cancel();
+            Ok(HttpResponse::Accepted().json(json!({
+                "success": true,
+                "message": format!("Import job {} is being cancelled", id)
+            })))
+        }
+        _ => Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "error": format!("Import job {} is {} and cannot be cancelled", id, progress.status)
+        }))),
+    }
+}
+
+/// Run `run` now, or in the background when the request asked for it
+pub async fn start(
+    state: &web::Data<Arc<ApiState>>,
+    run: ImportRun,
+) -> std::result::Result<(Uuid, Option<ImportOutcome>), HttpResponse> {
+    if run.options.background {
+        return match run.spawn(state.get_ref().clone()).await {
+            Ok(id) => Ok((id, None)),
+            Err(e) => Err(entities::database_error(e)),
+        };
+    }
+    match run.execute(state.get_ref().clone()).await {
+        Ok((id, outcome)) => Ok((id, Some(outcome))),
+        Err(e) => Err(entities::database_error(e)),
+    }
+}
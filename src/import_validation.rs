@@ -0,0 +1,237 @@
+// src/import_validation.rs
+//
+// Structured validation of imported rows. Every cell is checked first by the
+// `FieldDef` validation the entity endpoints use and then against the
+// column as the database defines it in `information_schema` (type, length,
+// numeric precision, NOT NULL), so rows the table would refuse are rejected
+// up front. Each problem is reported against its column with an error code.
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::{Pool, Postgres, Row};
+use std::collections::HashMap;
+
+use crate::entities::{EntityDef, FieldDef, FieldType, FieldValue};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The row names a column the table does not have
+    UnknownColumn,
+    /// Empty, but the column needs a value
+    Required,
+    /// Longer than the column allows
+    TooLong,
+    InvalidText,
+    InvalidUuid,
+    InvalidDate,
+    InvalidTimestamp,
+    InvalidInteger,
+    InvalidNumber,
+    InvalidBoolean,
+    /// A number too large for the column
+    OutOfRange,
+    /// The spreadsheet cell could not be read, e.g. `#N/A`
+    InvalidCell,
+    /// A record the row refers to, such as a contact's account, does not exist
+    NotFound,
+    /// The database refused the row
+    Database,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::UnknownColumn => "unknown_column",
+            ErrorCode::Required => "required",
+            ErrorCode::TooLong => "too_long",
+            ErrorCode::InvalidText => "invalid_text",
+            ErrorCode::InvalidUuid => "invalid_uuid",
+            ErrorCode::InvalidDate => "invalid_date",
+            ErrorCode::InvalidTimestamp => "invalid_timestamp",
+            ErrorCode::InvalidInteger => "invalid_integer",
+            ErrorCode::InvalidNumber => "invalid_number",
+            ErrorCode::InvalidBoolean => "invalid_boolean",
+            ErrorCode::OutOfRange => "out_of_range",
+            ErrorCode::InvalidCell => "invalid_cell",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::Database => "database",
+        }
+    }
+}
+
+/// One problem with a row, against the column it concerns when there is one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CellError {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl CellError {
+    pub fn new(column: &str, code: ErrorCode, message: String) -> Self {
+        CellError { column: Some(column.to_string()), code, message }
+    }
+
+    /// A failed `INSERT` or `UPDATE` of the whole row
+    pub fn database(message: String) -> Self {
+        CellError { column: None, code: ErrorCode::Database, message }
+    }
+}
+
+/// A column as `information_schema.columns` describes it
+#[derive(Debug)]
+struct ColumnInfo {
+    data_type: String,
+    max_length: Option<i32>,
+    nullable: bool,
+    has_default: bool,
+    numeric_precision: Option<i32>,
+    numeric_scale: Option<i32>,
+}
+
+/// The columns of an import's target table, read once per import
+#[derive(Debug)]
+pub struct TableSchema {
+    table: String,
+    columns: HashMap<String, ColumnInfo>,
+}
+
+impl TableSchema {
+    pub async fn load(pool: &Pool<Postgres>, table: &str) -> Result<Self, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                column_name::text AS column_name,
+                data_type::text AS data_type,
+                character_maximum_length::int AS max_length,
+                is_nullable = 'YES' AS nullable,
+                column_default IS NOT NULL AS has_default,
+                numeric_precision::int AS numeric_precision,
+                numeric_scale::int AS numeric_scale
+            FROM information_schema.columns
+            WHERE table_schema = 'public' AND table_name = $1
+            "#
+        )
+        .bind(table)
+        .fetch_all(pool)
+        .await?;
+
+        let columns = rows
+            .into_iter()
+            .map(|row| {
+                let info = ColumnInfo {
+                    data_type: row.get("data_type"),
+                    max_length: row.get("max_length"),
+                    nullable: row.get("nullable"),
+                    has_default: row.get("has_default"),
+                    numeric_precision: row.get("numeric_precision"),
+                    numeric_scale: row.get("numeric_scale"),
+                };
+                (row.get::<String, _>("column_name"), info)
+            })
+            .collect();
+        Ok(TableSchema { table: table.to_string(), columns })
+    }
+
+    /// Problems a parsed value would hit in the column
+    fn check(&self, field: &FieldDef, value: &FieldValue) -> Option<CellError> {
+        let name = field.name;
+        let Some(column) = self.columns.get(name) else {
+            return Some(CellError::new(name, ErrorCode::UnknownColumn, format!("{} is not a column of {}", name, self.table)));
+        };
+        match value {
+            FieldValue::Null if !column.nullable && !column.has_default => {
+                Some(CellError::new(name, ErrorCode::Required, format!("{} is required", name)))
+            }
+            FieldValue::Text(s) => column
+                .max_length
+                .filter(|max| s.chars().count() > *max as usize)
+                .map(|max| CellError::new(name, ErrorCode::TooLong, format!("{} must be at most {} characters", name, max))),
+            FieldValue::Integer(i) if column.data_type == "smallint" && i16::try_from(*i).is_err() => {
+                Some(CellError::new(name, ErrorCode::OutOfRange, format!("{} must be between {} and {}", name, i16::MIN, i16::MAX)))
+            }
+            FieldValue::Decimal(s) => {
+                let (Some(precision), Some(scale)) = (column.numeric_precision, column.numeric_scale) else {
+                    return None;
+                };
+                if column.data_type != "numeric" {
+                    return None;
+                }
+                let whole = s.trim_start_matches(['-', '+']).split('.').next().unwrap_or("").trim_start_matches('0');
+                let digits = (precision - scale).max(0) as usize;
+                (whole.len() > digits).then(|| {
+                    CellError::new(name, ErrorCode::OutOfRange, format!("{} must have at most {} digits before the decimal point", name, digits))
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether a column left out of an inserted row needs a value
+    fn requires(&self, name: &str) -> bool {
+        self.columns.get(name).is_some_and(|c| !c.nullable && !c.has_default)
+    }
+}
+
+/// The code for a value `FieldDef::parse` refused
+fn parse_error_code(field: &FieldDef, value: &Value) -> ErrorCode {
+    let blank = value.is_null() || value.as_str().is_some_and(|s| s.trim().is_empty());
+    if blank && field.required {
+        return ErrorCode::Required;
+    }
+    match field.kind {
+        FieldType::Text(_) => match value {
+            Value::String(_) | Value::Number(_) | Value::Bool(_) => ErrorCode::TooLong,
+            _ => ErrorCode::InvalidText,
+        },
+        FieldType::Uuid => ErrorCode::InvalidUuid,
+        FieldType::Date => ErrorCode::InvalidDate,
+        FieldType::Timestamp => ErrorCode::InvalidTimestamp,
+        FieldType::Integer => {
+            let whole = value.as_i64().or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()));
+            if whole.is_some() { ErrorCode::OutOfRange } else { ErrorCode::InvalidInteger }
+        }
+        FieldType::Decimal => ErrorCode::InvalidNumber,
+        FieldType::Boolean => ErrorCode::InvalidBoolean,
+    }
+}
+
+/// Validate a record to be inserted into `entity`, like
+/// `entities::validate_body`, with every problem tied to its column
+pub fn validate_row(
+    entity: &EntityDef,
+    schema: &TableSchema,
+    record: &Map<String, Value>,
+) -> Result<Vec<(&'static str, FieldValue)>, Vec<CellError>> {
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+
+    for key in record.keys() {
+        if entity.field(key).is_none() {
+            errors.push(CellError::new(key, ErrorCode::UnknownColumn, format!("Unknown field: {}", key)));
+        }
+    }
+    for field in entity.fields {
+        match record.get(field.name) {
+            Some(value) => match field.parse(value) {
+                Ok(parsed) => match schema.check(field, &parsed) {
+                    Some(error) => errors.push(error),
+                    None => values.push((field.name, parsed)),
+                },
+                Err(message) => errors.push(CellError::new(field.name, parse_error_code(field, value), message)),
+            },
+            None if field.required || schema.requires(field.name) => {
+                errors.push(CellError::new(field.name, ErrorCode::Required, format!("{} is required", field.name)));
+            }
+            None => {}
+        }
+    }
+
+    if errors.is_empty() { Ok(values) } else { Err(errors) }
+}
+
+/// Messages of `errors` on one line, for the free-form `errors` of responses
+pub fn summary(errors: &[CellError]) -> String {
+    errors.iter().map(|e| e.message.as_str()).collect::<Vec<_>>().join("; ")
+}
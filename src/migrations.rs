@@ -0,0 +1,205 @@
+// src/migrations.rs
+//
+// Numbered schema migrations embedded from the `migrations/` directory.
+// Applied versions are recorded in `schema_migrations` together with a
+// SHA-256 checksum of the up script, so edits to an applied migration are
+// caught instead of silently diverging from the database.
+use anyhow::{bail, Context};
+use colored::Colorize;
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres, Row};
+use std::collections::HashMap;
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+macro_rules! migration {
+    ($version:expr, $name:literal) => {
+        Migration {
+            version: $version,
+            name: $name,
+            up: include_str!(concat!("../migrations/", $name, ".up.sql")),
+            down: include_str!(concat!("../migrations/", $name, ".down.sql")),
+        }
+    };
+}
+
+pub static MIGRATIONS: &[Migration] = &[
+    migration!(1, "0001_initial_schema"),
+    migration!(2, "0002_widen_project_name"),
+    migration!(3, "0003_cleanup_orphaned_records"),
+    migration!(4, "0004_add_foreign_keys"),
+    migration!(5, "0005_user_authentication"),
+    migration!(6, "0006_import_profiles"),
+    migration!(7, "0007_import_jobs"),
+    migration!(8, "0008_project_attributes"),
+    migration!(9, "0009_import_job_rejections"),
+    migration!(10, "0010_fuzzy_duplicates"),
+    migration!(11, "0011_project_sources"),
+    migration!(12, "0012_events_contacts"),
+    migration!(13, "0013_url_imports"),
+    migration!(14, "0014_proxy_requests"),
+    migration!(15, "0015_tag_audit_columns"),
+    migration!(16, "0016_democracylab_project_urls"),
+];
+
+impl Migration {
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.up.as_bytes()))
+    }
+}
+
+struct AppliedMigration {
+    checksum: String,
+    applied_at: chrono::DateTime<chrono::Utc>,
+}
+
+async fn ensure_migrations_table(pool: &Pool<Postgres>) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            name VARCHAR(255) NOT NULL,
+            checksum VARCHAR(64) NOT NULL,
+            applied_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+        )
+        "#
+    ).execute(pool).await.context("Failed to create schema_migrations table")?;
+    Ok(())
+}
+
+async fn applied_migrations(pool: &Pool<Postgres>) -> anyhow::Result<HashMap<i64, AppliedMigration>> {
+    ensure_migrations_table(pool).await?;
+    let rows = sqlx::query("SELECT version, checksum, applied_at FROM schema_migrations")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(|row| {
+        (row.get::<i64, _>("version"), AppliedMigration {
+            checksum: row.get("checksum"),
+            applied_at: row.get("applied_at"),
+        })
+    }).collect())
+}
+
+/// Refuse to run if an applied migration's script has changed since
+fn verify_checksums(applied: &HashMap<i64, AppliedMigration>) -> anyhow::Result<()> {
+    for migration in MIGRATIONS {
+        if let Some(record) = applied.get(&migration.version) {
+            if record.checksum != migration.checksum() {
+                bail!(
+                    "Checksum mismatch for applied migration {}: the script was modified after it ran",
+                    migration.name
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Number of migrations not yet applied
+pub async fn pending_count(pool: &Pool<Postgres>) -> anyhow::Result<usize> {
+    let applied = applied_migrations(pool).await?;
+    Ok(MIGRATIONS.iter().filter(|m| !applied.contains_key(&m.version)).count())
+}
+
+/// Apply pending migrations in order, up to and including `target` if given
+pub async fn migrate_up(pool: &Pool<Postgres>, target: Option<i64>) -> anyhow::Result<()> {
+    let applied = applied_migrations(pool).await?;
+    verify_checksums(&applied)?;
+
+    let pending: Vec<&Migration> = MIGRATIONS.iter()
+        .filter(|m| !applied.contains_key(&m.version))
+        .filter(|m| target.is_none_or(|t| m.version <= t))
+        .collect();
+
+    if pending.is_empty() {
+        println!("Database schema is up to date");
+        return Ok(());
+    }
+
+    for migration in pending {
+        println!("Applying {}...", migration.name);
+        let mut tx = pool.begin().await?;
+        sqlx::raw_sql(migration.up)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Migration {} failed", migration.name))?;
+        sqlx::query("INSERT INTO schema_migrations (version, name, checksum) VALUES ($1, $2, $3)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(migration.checksum())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    }
+
+    println!("Database schema migrated successfully!");
+    Ok(())
+}
+
+/// Revert the most recently applied migrations, newest first
+pub async fn migrate_down(pool: &Pool<Postgres>, steps: usize) -> anyhow::Result<()> {
+    let applied = applied_migrations(pool).await?;
+    verify_checksums(&applied)?;
+
+    let to_revert: Vec<&Migration> = MIGRATIONS.iter()
+        .rev()
+        .filter(|m| applied.contains_key(&m.version))
+        .take(steps)
+        .collect();
+
+    if to_revert.is_empty() {
+        println!("No applied migrations to revert");
+        return Ok(());
+    }
+
+    for migration in to_revert {
+        println!("Reverting {}...", migration.name);
+        let mut tx = pool.begin().await?;
+        sqlx::raw_sql(migration.down)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Reverting {} failed", migration.name))?;
+        sqlx::query("DELETE FROM schema_migrations WHERE version = $1")
+            .bind(migration.version)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    }
+
+    Ok(())
+}
+
+/// Print every known migration with its state
+pub async fn print_status(pool: &Pool<Postgres>) -> anyhow::Result<()> {
+    let applied = applied_migrations(pool).await?;
+
+    let mut table = prettytable::Table::new();
+    table.set_titles(prettytable::row!["Version", "Name", "Status", "Applied At"]);
+    for migration in MIGRATIONS {
+        let (status, applied_at) = match applied.get(&migration.version) {
+            Some(record) if record.checksum != migration.checksum() => {
+                ("modified".red().to_string(), record.applied_at.to_rfc3339())
+            }
+            Some(record) => ("applied".green().to_string(), record.applied_at.to_rfc3339()),
+            None => ("pending".yellow().to_string(), String::new()),
+        };
+        table.add_row(prettytable::row![migration.version, migration.name, status, applied_at]);
+    }
+
+    // Versions recorded in the database that this build does not know about
+    let mut unknown: Vec<&i64> = applied.keys()
+        .filter(|v| !MIGRATIONS.iter().any(|m| m.version == **v))
+        .collect();
+    unknown.sort();
+    for version in unknown {
+        table.add_row(prettytable::row![version, "(unknown to this build)", "applied".green().to_string(), applied[version].applied_at.to_rfc3339()]);
+    }
+
+    table.printstd();
+    Ok(())
+}
@@ -0,0 +1,152 @@
+// src/pg_json.rs
+//
+// Decoding of arbitrary Postgres result rows into JSON, used where the
+// columns are not known ahead of time (the admin SQL endpoint). Each value
+// keeps its JSON type: numbers stay numbers, booleans stay booleans, JSON
+// columns are embedded as-is and temporal values use ISO 8601 strings.
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use serde_json::{json, Value};
+use sqlx::Describe;
+use sqlx::postgres::types::{Oid, PgInterval, PgMoney};
+use sqlx::postgres::{PgRow, PgTypeKind};
+use sqlx::types::BigDecimal;
+use sqlx::{Column, Decode, Postgres, Row, Type, TypeInfo, ValueRef};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Name, Postgres type and nullability of every result column.
+/// `nullable` is null when Postgres cannot tell (e.g. computed expressions).
+pub fn column_metadata(describe: &Describe<Postgres>) -> Vec<Value> {
+    describe
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            json!({
+                "name": column.name(),
+                "type": column.type_info().name(),
+                "nullable": describe.nullable(i)
+            })
+        })
+        .collect()
+}
+
+/// Convert a row into a JSON object keyed by column name
+pub fn row_to_json(row: &PgRow) -> serde_json::Map<String, Value> {
+    row.columns()
+        .iter()
+        .map(|column| (column.name().to_string(), value_to_json(row, column.ordinal())))
+        .collect()
+}
+
+fn float_to_json(value: f64) -> Value {
+    // NaN and the infinities have no JSON number form
+    serde_json::Number::from_f64(value)
+        .map(Value::Number)
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+/// NUMERIC becomes a JSON number when an f64 holds it exactly,
+/// otherwise its exact text so no digits are lost
+fn numeric_to_json(value: BigDecimal) -> Value {
+    // The decoded scale is padded to whole base-10000 digits, so drop the extra zeros
+    let mut text = value.to_string();
+    if text.contains('.') {
+        text = text.trim_end_matches('0').trim_end_matches('.').to_string();
+    }
+    match text.parse::<f64>() {
+        Ok(float) if BigDecimal::from_str(&float.to_string()).is_ok_and(|back| back == value) => float_to_json(float),
+        _ => Value::String(text),
+    }
+}
+
+/// ISO 8601 duration, e.g. `P1M2DT3600.5S`
+fn interval_to_json(interval: PgInterval) -> Value {
+    let mut text = String::from("P");
+    if interval.months != 0 {
+        text.push_str(&format!("{}M", interval.months));
+    }
+    if interval.days != 0 {
+        text.push_str(&format!("{}D", interval.days));
+    }
+    if interval.microseconds != 0 || text == "P" {
+        text.push_str(&format!("T{}S", interval.microseconds as f64 / 1_000_000.0));
+    }
+    Value::String(text)
+}
+
+fn bytea_to_json(bytes: Vec<u8>) -> Value {
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    Value::String(format!("\\x{}", hex))
+}
+
+fn get<'r, T>(row: &'r PgRow, index: usize, convert: impl Fn(T) -> Value) -> Result<Value, sqlx::Error>
+where
+    T: Decode<'r, Postgres> + Type<Postgres>,
+{
+    row.try_get::<T, _>(index).map(convert)
+}
+
+fn get_array<'r, T>(row: &'r PgRow, index: usize, convert: impl Fn(T) -> Value) -> Result<Value, sqlx::Error>
+where
+    Vec<Option<T>>: Decode<'r, Postgres> + Type<Postgres>,
+{
+    row.try_get::<Vec<Option<T>>, _>(index).map(|items| {
+        Value::Array(items.into_iter().map(|item| item.map(&convert).unwrap_or(Value::Null)).collect())
+    })
+}
+
+// Text-like types share the UTF-8 wire format, so they are read without
+// sqlx's type check (which does not list e.g. CITEXT or enums)
+fn get_text(row: &PgRow, index: usize) -> Result<Value, sqlx::Error> {
+    row.try_get_unchecked::<String, _>(index).map(Value::String)
+}
+
+/// Decode one column of a row into its JSON form
+pub fn value_to_json(row: &PgRow, index: usize) -> Value {
+    let type_info = match row.try_get_raw(index) {
+        Ok(raw) if raw.is_null() => return Value::Null,
+        Ok(raw) => raw.type_info().into_owned(),
+        Err(e) => return Value::String(format!("Error reading value: {}", e)),
+    };
+
+    let result = match type_info.name() {
+        "BOOL" => get(row, index, |v: bool| json!(v)),
+        "INT2" => get(row, index, |v: i16| json!(v)),
+        "INT4" => get(row, index, |v: i32| json!(v)),
+        "INT8" => get(row, index, |v: i64| json!(v)),
+        "OID" => get(row, index, |v: Oid| json!(v.0)),
+        "FLOAT4" => get(row, index, |v: f32| float_to_json(v as f64)),
+        "FLOAT8" => get(row, index, float_to_json),
+        "NUMERIC" => get(row, index, numeric_to_json),
+        "MONEY" => get(row, index, |v: PgMoney| numeric_to_json(v.to_bigdecimal(2))),
+        "TEXT" | "VARCHAR" | "CHAR" | "\"CHAR\"" | "NAME" | "CITEXT" | "UNKNOWN" | "XML" => get_text(row, index),
+        "UUID" => get(row, index, |v: Uuid| json!(v)),
+        "DATE" => get(row, index, |v: NaiveDate| json!(v)),
+        "TIME" => get(row, index, |v: NaiveTime| json!(v)),
+        "TIMESTAMP" => get(row, index, |v: NaiveDateTime| json!(v)),
+        "TIMESTAMPTZ" => get(row, index, |v: DateTime<Utc>| json!(v)),
+        "INTERVAL" => get(row, index, interval_to_json),
+        "JSON" | "JSONB" => get(row, index, |v: Value| v),
+        "BYTEA" => get(row, index, bytea_to_json),
+        "BOOL[]" => get_array(row, index, |v: bool| json!(v)),
+        "INT2[]" => get_array(row, index, |v: i16| json!(v)),
+        "INT4[]" => get_array(row, index, |v: i32| json!(v)),
+        "INT8[]" => get_array(row, index, |v: i64| json!(v)),
+        "FLOAT4[]" => get_array(row, index, |v: f32| float_to_json(v as f64)),
+        "FLOAT8[]" => get_array(row, index, float_to_json),
+        "NUMERIC[]" => get_array(row, index, numeric_to_json),
+        "TEXT[]" | "VARCHAR[]" | "CHAR[]" | "NAME[]" => get_array(row, index, Value::String),
+        "UUID[]" => get_array(row, index, |v: Uuid| json!(v)),
+        "DATE[]" => get_array(row, index, |v: NaiveDate| json!(v)),
+        "TIMESTAMP[]" => get_array(row, index, |v: NaiveDateTime| json!(v)),
+        "TIMESTAMPTZ[]" => get_array(row, index, |v: DateTime<Utc>| json!(v)),
+        "JSON[]" | "JSONB[]" => get_array(row, index, |v: Value| v),
+        name => match type_info.kind() {
+            PgTypeKind::Enum(_) => get_text(row, index),
+            _ => Ok(Value::String(format!("<unsupported type {}>", name))),
+        },
+    };
+
+    result.unwrap_or_else(|e| Value::String(format!("Error reading value: {}", e)))
+}
@@ -0,0 +1,452 @@
+// src/projects.rs
+use actix_web::{web, HttpResponse, Result};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{postgres::PgRow, Postgres, QueryBuilder, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::Claims;
+use crate::entities::{self, Pagination, PROJECTS};
+use crate::relationships;
+use crate::ApiState;
+
+const PROJECT_COLUMNS: &str = "id, name, description, status, priority, estimated_start_date, estimated_end_date, \
+    fiscal_year, project_number, committed_amount::float8 AS committed_amount, country, region, department, \
+    framework, naics_sector, profile_url, skills, location, organization, source, source_id, source_modified, \
+    date_entered, date_modified";
+
+/// Free-text attributes of a project, as imported from the DFC sheet or
+/// synced from DemocracyLab
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectAttributes {
+    fiscal_year: Option<String>,
+    project_number: Option<String>,
+    committed_amount: Option<f64>,
+    country: Option<String>,
+    region: Option<String>,
+    department: Option<String>,
+    framework: Option<String>,
+    naics_sector: Option<String>,
+    profile_url: Option<String>,
+    skills: Option<String>,
+    location: Option<String>,
+    organization: Option<String>,
+}
+
+impl ProjectAttributes {
+    /// Text columns with their values, in column order
+    fn text_columns(&self) -> [(&'static str, &Option<String>); 11] {
+        [
+            ("fiscal_year", &self.fiscal_year),
+            ("project_number", &self.project_number),
+            ("country", &self.country),
+            ("region", &self.region),
+            ("department", &self.department),
+            ("framework", &self.framework),
+            ("naics_sector", &self.naics_sector),
+            ("profile_url", &self.profile_url),
+            ("skills", &self.skills),
+            ("location", &self.location),
+            ("organization", &self.organization),
+        ]
+    }
+}
+
+// Request/Response types for projects
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateProjectRequest {
+    name: String,
+    description: Option<String>,
+    status: Option<String>,
+    estimated_start_date: Option<String>,
+    estimated_end_date: Option<String>,
+    #[serde(flatten)]
+    attributes: ProjectAttributes,
+}
+
+/// Partial update - only fields that are present are changed.
+/// An empty string for a date clears it.
+#[derive(Debug, Deserialize)]
+pub struct UpdateProjectRequest {
+    name: Option<String>,
+    description: Option<String>,
+    status: Option<String>,
+    priority: Option<String>,
+    estimated_start_date: Option<String>,
+    estimated_end_date: Option<String>,
+    #[serde(flatten)]
+    attributes: ProjectAttributes,
+}
+
+/// Query string for `GET /api/projects`.
+/// `status`, `priority`, `region`, `department`, `country` and `fiscal_year`
+/// accept comma-separated lists, dates use YYYY-MM-DD.
+#[derive(Debug, Deserialize)]
+pub struct ProjectListQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+    status: Option<String>,
+    priority: Option<String>,
+    region: Option<String>,
+    department: Option<String>,
+    country: Option<String>,
+    fiscal_year: Option<String>,
+    created_from: Option<String>,
+    created_to: Option<String>,
+    modified_from: Option<String>,
+    modified_to: Option<String>,
+}
+
+// Parsed filters shared by the list and count queries
+struct ProjectFilters {
+    status: Option<Vec<String>>,
+    priority: Option<Vec<String>>,
+    region: Option<Vec<String>>,
+    department: Option<Vec<String>>,
+    country: Option<Vec<String>>,
+    fiscal_year: Option<Vec<String>>,
+    created_from: Option<NaiveDate>,
+    created_to: Option<NaiveDate>,
+    modified_from: Option<NaiveDate>,
+    modified_to: Option<NaiveDate>,
+}
+
+impl ProjectListQuery {
+    fn filters(&self) -> Result<ProjectFilters, String> {
+        Ok(ProjectFilters {
+            status: split_list(&self.status),
+            priority: split_list(&self.priority),
+            region: split_list(&self.region),
+            department: split_list(&self.department),
+            country: split_list(&self.country),
+            fiscal_year: split_list(&self.fiscal_year),
+            created_from: parse_date_param("created_from", &self.created_from)?,
+            created_to: parse_date_param("created_to", &self.created_to)?,
+            modified_from: parse_date_param("modified_from", &self.modified_from)?,
+            modified_to: parse_date_param("modified_to", &self.modified_to)?,
+        })
+    }
+}
+
+fn split_list(value: &Option<String>) -> Option<Vec<String>> {
+    let values: Vec<String> = value
+        .as_deref()?
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if values.is_empty() { None } else { Some(values) }
+}
+
+fn parse_date_param(name: &str, value: &Option<String>) -> Result<Option<NaiveDate>, String> {
+    match value.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(s) => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| format!("Invalid {} '{}', expected YYYY-MM-DD", name, s)),
+    }
+}
+
+fn push_project_filters(builder: &mut QueryBuilder<'_, Postgres>, filters: &ProjectFilters) {
+    builder.push(" WHERE 1=1");
+    if let Some(status) = &filters.status {
+        builder.push(" AND status = ANY(").push_bind(status.clone()).push(")");
+    }
+    if let Some(priority) = &filters.priority {
+        builder.push(" AND priority = ANY(").push_bind(priority.clone()).push(")");
+    }
+    if let Some(region) = &filters.region {
+        builder.push(" AND region = ANY(").push_bind(region.clone()).push(")");
+    }
+    if let Some(department) = &filters.department {
+        builder.push(" AND department = ANY(").push_bind(department.clone()).push(")");
+    }
+    if let Some(country) = &filters.country {
+        builder.push(" AND country = ANY(").push_bind(country.clone()).push(")");
+    }
+    if let Some(fiscal_year) = &filters.fiscal_year {
+        builder.push(" AND fiscal_year = ANY(").push_bind(fiscal_year.clone()).push(")");
+    }
+    // Date ranges are inclusive of whole days
+    if let Some(date) = filters.created_from {
+        builder.push(" AND date_entered >= ").push_bind(date);
+    }
+    if let Some(date) = filters.created_to {
+        builder.push(" AND date_entered < ").push_bind(date).push(" + 1");
+    }
+    if let Some(date) = filters.modified_from {
+        builder.push(" AND date_modified >= ").push_bind(date);
+    }
+    if let Some(date) = filters.modified_to {
+        builder.push(" AND date_modified < ").push_bind(date).push(" + 1");
+    }
+}
+
+fn project_to_json(row: &PgRow) -> serde_json::Value {
+    json!({
+        "id": row.get::<Uuid, _>("id"),
+        "name": row.get::<Option<String>, _>("name"),
+        "description": row.get::<Option<String>, _>("description"),
+        "status": row.get::<Option<String>, _>("status"),
+        "priority": row.get::<Option<String>, _>("priority"),
+        "estimated_start_date": row.get::<Option<NaiveDate>, _>("estimated_start_date"),
+        "estimated_end_date": row.get::<Option<NaiveDate>, _>("estimated_end_date"),
+        "fiscal_year": row.get::<Option<String>, _>("fiscal_year"),
+        "project_number": row.get::<Option<String>, _>("project_number"),
+        "committed_amount": row.get::<Option<f64>, _>("committed_amount"),
+        "country": row.get::<Option<String>, _>("country"),
+        "region": row.get::<Option<String>, _>("region"),
+        "department": row.get::<Option<String>, _>("department"),
+        "framework": row.get::<Option<String>, _>("framework"),
+        "naics_sector": row.get::<Option<String>, _>("naics_sector"),
+        "profile_url": row.get::<Option<String>, _>("profile_url"),
+        "skills": row.get::<Option<String>, _>("skills"),
+        "location": row.get::<Option<String>, _>("location"),
+        "organization": row.get::<Option<String>, _>("organization"),
+        "source": row.get::<Option<String>, _>("source"),
+        "source_id": row.get::<Option<String>, _>("source_id"),
+        "source_modified": row.get::<Option<chrono::DateTime<Utc>>, _>("source_modified"),
+        "created_date": row.get::<Option<chrono::DateTime<Utc>>, _>("date_entered"),
+        "modified_date": row.get::<Option<chrono::DateTime<Utc>>, _>("date_modified")
+    })
+}
+
+fn bad_request(error: String) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "error": error
+    }))
+}
+
+fn project_not_found(id: Uuid) -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "error": format!("Project {} not found", id)
+    }))
+}
+
+// Get projects from database with paging and filters
+pub async fn get_projects(
+    data: web::Data<Arc<ApiState>>,
+    query: web::Query<ProjectListQuery>,
+) -> Result<HttpResponse> {
+    let filters = match query.filters() {
+        Ok(filters) => filters,
+        Err(e) => return Ok(bad_request(e)),
+    };
+    let pagination = Pagination { limit: query.limit, offset: query.offset };
+
+    let mut count_query = QueryBuilder::new("SELECT COUNT(*) FROM projects");
+    push_project_filters(&mut count_query, &filters);
+
+    let mut list_query = QueryBuilder::new(format!("SELECT {} FROM projects", PROJECT_COLUMNS));
+    push_project_filters(&mut list_query, &filters);
+    list_query
+        .push(" ORDER BY date_modified DESC, id LIMIT ")
+        .push_bind(pagination.limit())
+        .push(" OFFSET ")
+        .push_bind(pagination.offset());
+
+    let total = count_query.build_query_scalar::<i64>().fetch_one(&data.db).await;
+    let rows = list_query.build().fetch_all(&data.db).await;
+
+    match (total, rows) {
+        (Ok(total), Ok(rows)) => {
+            let projects: Vec<serde_json::Value> = rows.iter().map(project_to_json).collect();
+
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "pagination": pagination.to_json(total, projects.len()),
+                "data": projects
+            })))
+        }
+        (Err(e), _) | (_, Err(e)) => Ok(entities::database_error(e)),
+    }
+}
+
+// Get a single project by id
+pub async fn get_project(
+    data: web::Data<Arc<ApiState>>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let result = sqlx::query(&format!("SELECT {} FROM projects WHERE id = $1", PROJECT_COLUMNS))
+        .bind(id)
+        .fetch_optional(&data.db)
+        .await;
+
+    match result {
+        Ok(Some(row)) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": project_to_json(&row)
+        }))),
+        Ok(None) => Ok(project_not_found(id)),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+// Create a new project
+pub async fn create_project(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    req: web::Json<CreateProjectRequest>,
+) -> Result<HttpResponse> {
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+
+    // Parse date strings into NaiveDate
+    let start_date = req.estimated_start_date.as_ref()
+        .and_then(|s| if s.is_empty() { None } else { Some(s) })
+        .and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok());
+
+    let end_date = req.estimated_end_date.as_ref()
+        .and_then(|s| if s.is_empty() { None } else { Some(s) })
+        .and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok());
+
+    let attributes = &req.attributes;
+    let result = sqlx::query(
+        r#"
+        INSERT INTO projects (
+            id, name, description, status,
+            estimated_start_date, estimated_end_date,
+            date_entered, date_modified, created_by, modified_user_id,
+            fiscal_year, project_number, country, region, department,
+            framework, naics_sector, profile_url, committed_amount,
+            skills, location, organization
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
+                  $11, $12, $13, $14, $15, $16, $17, $18, $19::numeric,
+                  $20, $21, $22)
+        "#
+    )
+    .bind(id)
+    .bind(&req.name)
+    .bind(&req.description)
+    .bind(&req.status)
+    .bind(start_date)
+    .bind(end_date)
+    .bind(now)
+    .bind(now)
+    .bind(claims.user_id())
+    .bind(claims.user_id())
+    .bind(&attributes.fiscal_year)
+    .bind(&attributes.project_number)
+    .bind(&attributes.country)
+    .bind(&attributes.region)
+    .bind(&attributes.department)
+    .bind(&attributes.framework)
+    .bind(&attributes.naics_sector)
+    .bind(&attributes.profile_url)
+    .bind(attributes.committed_amount)
+    .bind(&attributes.skills)
+    .bind(&attributes.location)
+    .bind(&attributes.organization)
+    .execute(&data.db)
+    .await;
+
+    match result {
+        Ok(_) => Ok(HttpResponse::Created().json(json!({
+            "id": id.to_string(),
+            "message": "Project created successfully"
+        }))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(json!({
+            "error": e.to_string()
+        }))),
+    }
+}
+
+// Update the fields present in the request body
+pub async fn update_project(
+    data: web::Data<Arc<ApiState>>,
+    path: web::Path<Uuid>,
+    claims: Claims,
+    req: web::Json<UpdateProjectRequest>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+
+    if req.name.as_deref().is_some_and(|name| name.trim().is_empty()) {
+        return Ok(bad_request("Project name cannot be empty".to_string()));
+    }
+    let start_date = match parse_date_param("estimated_start_date", &req.estimated_start_date) {
+        Ok(date) => date,
+        Err(e) => return Ok(bad_request(e)),
+    };
+    let end_date = match parse_date_param("estimated_end_date", &req.estimated_end_date) {
+        Ok(date) => date,
+        Err(e) => return Ok(bad_request(e)),
+    };
+
+    let mut builder = QueryBuilder::<Postgres>::new("UPDATE projects SET date_modified = ");
+    builder.push_bind(Utc::now());
+    builder.push(", modified_user_id = ").push_bind(claims.user_id());
+
+    if let Some(name) = &req.name {
+        builder.push(", name = ").push_bind(name.clone());
+    }
+    if let Some(description) = &req.description {
+        builder.push(", description = ").push_bind(description.clone());
+    }
+    if let Some(status) = &req.status {
+        builder.push(", status = ").push_bind(status.clone());
+    }
+    if let Some(priority) = &req.priority {
+        builder.push(", priority = ").push_bind(priority.clone());
+    }
+    if req.estimated_start_date.is_some() {
+        builder.push(", estimated_start_date = ").push_bind(start_date);
+    }
+    if req.estimated_end_date.is_some() {
+        builder.push(", estimated_end_date = ").push_bind(end_date);
+    }
+    for (column, value) in req.attributes.text_columns() {
+        if let Some(value) = value {
+            builder.push(format!(", {} = ", column)).push_bind(value.clone());
+        }
+    }
+    if let Some(amount) = req.attributes.committed_amount {
+        builder.push(", committed_amount = ").push_bind(amount).push("::numeric");
+    }
+    builder.push(" WHERE id = ").push_bind(id);
+    builder.push(format!(" RETURNING {}", PROJECT_COLUMNS));
+
+    match builder.build().fetch_optional(&data.db).await {
+        Ok(Some(row)) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": "Project updated successfully",
+            "data": project_to_json(&row)
+        }))),
+        Ok(None) => Ok(project_not_found(id)),
+        Err(e) => Ok(bad_request(e.to_string())),
+    }
+}
+
+// Delete a project along with its links to contacts and accounts
+pub async fn delete_project(
+    data: web::Data<Arc<ApiState>>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+
+    let result: Result<u64, sqlx::Error> = async {
+        let mut tx = data.db.begin().await?;
+        relationships::unlink_all(&mut tx, &PROJECTS, id).await?;
+        let deleted = sqlx::query("DELETE FROM projects WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+        Ok(deleted)
+    }
+    .await;
+
+    match result {
+        Ok(0) => Ok(project_not_found(id)),
+        Ok(_) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": "Project deleted successfully"
+        }))),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
@@ -0,0 +1,555 @@
+// src/proxy.rs
+//
+// /api/proxy fetches external URLs for the browser, which cannot read RSS
+// feeds and JSON APIs across origins itself. It only reaches public hosts:
+// the host must be on PROXY_ALLOWED_HOSTS when that is set, every address it
+// resolves to must be public, and the connection is pinned to the addresses
+// that were checked so a second DNS answer cannot point it elsewhere.
+// Redirects are followed by hand and checked the same way. Headers that
+// could impersonate the server or a metadata client are dropped, responses
+// are cut off at PROXY_MAX_RESPONSE_MB, and every call is logged.
+use actix_web::{web, HttpResponse, Result};
+use reqwest::header::{self, HeaderMap, HeaderName, HeaderValue};
+use reqwest::{redirect, Method, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use url::Host;
+
+use crate::auth::Claims;
+use crate::config::Config;
+use crate::entities::{self, Pagination};
+use crate::ApiState;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_REDIRECTS: usize = 5;
+
+/// Request headers never forwarded: hop-by-hop headers, the browser's
+/// cookies, client address headers and cloud metadata tokens
+const BLOCKED_HEADERS: &[&str] = &[
+    "host",
+    "connection",
+    "keep-alive",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "content-length",
+    "cookie",
+    "forwarded",
+    "x-real-ip",
+    "metadata",
+    "metadata-flavor",
+    "x-aws-ec2-metadata-token",
+    "x-aws-ec2-metadata-token-ttl-seconds",
+    "x-google-metadata-request",
+];
+
+#[derive(Debug, Deserialize)]
+pub struct ProxyRequest {
+    url: String,
+    method: Option<String>,
+    headers: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Serialize)]
+struct ProxyResponse {
+    success: bool,
+    /// Status of the external response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    data: Option<Value>,
+    error: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ProxyError {
+    /// Refused by the allowlist or because the host is not public
+    Blocked(String),
+    Invalid(String),
+    /// The external host failed or answered with too much
+    Upstream(String),
+}
+
+impl ProxyError {
+    pub fn message(&self) -> &str {
+        match self {
+            ProxyError::Blocked(e) | ProxyError::Invalid(e) | ProxyError::Upstream(e) => e,
+        }
+    }
+
+    fn to_response(&self) -> HttpResponse {
+        let body = ProxyResponse { success: false, status: None, data: None, error: Some(self.message().to_string()) };
+        match self {
+            ProxyError::Blocked(_) => HttpResponse::Forbidden().json(body),
+            ProxyError::Invalid(_) => HttpResponse::BadRequest().json(body),
+            ProxyError::Upstream(_) => HttpResponse::BadGateway().json(body),
+        }
+    }
+}
+
+/// What a proxied call reached, for the audit log
+#[derive(Default)]
+pub struct ProxyAudit {
+    host: Option<String>,
+    addresses: Vec<String>,
+    status_code: Option<u16>,
+    response_bytes: Option<usize>,
+}
+
+struct Fetched {
+    status: StatusCode,
+    content_type: String,
+    body: Vec<u8>,
+}
+
+fn parse_method(method: &str) -> Result<Method, ProxyError> {
+    match method {
+        "GET" => Ok(Method::GET),
+        "POST" => Ok(Method::POST),
+        "PUT" => Ok(Method::PUT),
+        "DELETE" => Ok(Method::DELETE),
+        "PATCH" => Ok(Method::PATCH),
+        other => Err(ProxyError::Invalid(format!("Method {} cannot be proxied", other))),
+    }
+}
+
+/// The browser's headers that may be passed on
+fn forwarded_headers(headers: Option<&HashMap<String, String>>) -> Result<HeaderMap, ProxyError> {
+    let mut forwarded = HeaderMap::new();
+    for (name, value) in headers.into_iter().flatten() {
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|_| ProxyError::Invalid(format!("'{}' is not a valid header name", name)))?;
+        let lower = name.as_str();
+        if BLOCKED_HEADERS.contains(&lower) || lower.starts_with("proxy-") || lower.starts_with("x-forwarded-") {
+            continue;
+        }
+        let value = HeaderValue::from_str(value)
+            .map_err(|_| ProxyError::Invalid(format!("Header {} has an invalid value", name)))?;
+        forwarded.insert(name, value);
+    }
+    Ok(forwarded)
+}
+
+/// Whether a request header carries credentials or an API key
+fn is_credential_header(name: &HeaderName) -> bool {
+    let name = name.as_str();
+    matches!(name, "authorization" | "proxy-authorization" | "cookie" | "cookie2" | "www-authenticate")
+        || ["auth", "key", "token", "secret", "session"].iter().any(|part| name.contains(part))
+}
+
+/// Drop credentials before following a redirect to another host, as
+/// reqwest's own redirect policy does
+fn strip_cross_host_credentials(headers: &mut HeaderMap, from: &Url, to: &Url) {
+    let same_host = from.host_str() == to.host_str() && from.port_or_known_default() == to.port_or_known_default();
+    if !same_host {
+        let names: Vec<HeaderName> = headers.keys().filter(|name| is_credential_header(name)).cloned().collect();
+        for name in names {
+            headers.remove(name);
+        }
+    }
+}
+
+/// `host` is one of the allowed hosts or a subdomain of one
+fn host_allowed(host: &str, allowed_hosts: &[String]) -> bool {
+    let host = host.trim_end_matches('.').to_lowercase();
+    allowed_hosts.iter().any(|allowed| {
+        host == *allowed || host.strip_suffix(allowed.as_str()).is_some_and(|sub| sub.ends_with('.'))
+    })
+}
+
+fn check_url(url: &Url, allowed_hosts: &[String]) -> Result<(), ProxyError> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(ProxyError::Blocked(format!("Only http and https URLs can be proxied, got {}", url.scheme())));
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err(ProxyError::Blocked("URLs with credentials cannot be proxied".to_string()));
+    }
+    let host = url.host_str().ok_or_else(|| ProxyError::Invalid(format!("{} has no host", url)))?;
+    if !allowed_hosts.is_empty() && !host_allowed(host, allowed_hosts) {
+        return Err(ProxyError::Blocked(format!("{} is not in PROXY_ALLOWED_HOSTS", host)));
+    }
+    Ok(())
+}
+
+fn is_blocked_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // "This network", shared address space (carrier-grade NAT), IETF
+        // protocol assignments, benchmarking and reserved ranges
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 192 && b == 0 && c == 0)
+        || (a == 198 && (b == 18 || b == 19))
+        || a >= 240
+}
+
+fn is_blocked_ipv6(ip: Ipv6Addr) -> bool {
+    // IPv4-mapped and IPv4-compatible addresses reach the IPv4 host
+    if let Some(v4) = ip.to_ipv4() {
+        return is_blocked_ipv4(v4);
+    }
+    let [first, second, ..] = ip.segments();
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // Unique local fc00::/7, link-local fe80::/10, site-local fec0::/10
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+        || (first & 0xffc0) == 0xfec0
+        // NAT64 and 6to4 embed an IPv4 address that is not checked here
+        || (first == 0x64 && second == 0xff9b)
+        || first == 0x2002
+        // Documentation 2001:db8::/32
+        || (first == 0x2001 && second == 0x0db8)
+}
+
+fn is_blocked_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_blocked_ipv4(ip),
+        IpAddr::V6(ip) => is_blocked_ipv6(ip),
+    }
+}
+
+/// Resolve the URL's host, refusing it when any address is not public
+async fn resolve_public(url: &Url) -> Result<Vec<SocketAddr>, ProxyError> {
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addrs: Vec<SocketAddr> = match url.host() {
+        Some(Host::Ipv4(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(Host::Ipv6(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(Host::Domain(domain)) => tokio::net::lookup_host((domain, port))
+            .await
+            .map_err(|e| ProxyError::Upstream(format!("Cannot resolve {}: {}", domain, e)))?
+            .collect(),
+        None => return Err(ProxyError::Invalid(format!("{} has no host", url))),
+    };
+    if addrs.is_empty() {
+        return Err(ProxyError::Upstream(format!("{} did not resolve to any address", url.host_str().unwrap_or(""))));
+    }
+    if let Some(addr) = addrs.iter().find(|addr| is_blocked_ip(addr.ip())) {
+        return Err(ProxyError::Blocked(format!(
+            "{} resolves to {}, which is not a public address",
+            url.host_str().unwrap_or(""),
+            addr.ip()
+        )));
+    }
+    Ok(addrs)
+}
+
+/// A client that connects only to the checked addresses and leaves
+/// redirects to the caller
+fn pinned_client(url: &Url, addrs: &[SocketAddr], timeout: Duration) -> Result<reqwest::Client, ProxyError> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(timeout)
+        .redirect(redirect::Policy::none())
+        .no_proxy();
+    if let Some(Host::Domain(domain)) = url.host() {
+        builder = builder.resolve_to_addrs(domain, addrs);
+    }
+    builder.build().map_err(|e| ProxyError::Upstream(format!("Failed to build HTTP client: {}", e)))
+}
+
+/// Read the body, refusing one longer than `max_mb` from its
+/// `Content-Length` or before the chunk that would exceed it is kept
+pub async fn read_limited(response: &mut reqwest::Response, max_mb: u64) -> Result<Vec<u8>, ProxyError> {
+    let max_bytes = max_mb.saturating_mul(1024 * 1024);
+    let too_large = || ProxyError::Upstream(format!("Response is larger than {} MB", max_mb));
+    if response.content_length().is_some_and(|len| len > max_bytes) {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| ProxyError::Upstream(format!("Failed to read response: {}", e)))?
+    {
+        if (body.len() + chunk.len()) as u64 > max_bytes {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Send a request to a public host. Redirects are followed by hand, each
+/// hop checked like the first and without credentials once the host
+/// changes, up to `MAX_REDIRECTS`; an empty `allowed_hosts` allows any
+/// public host. `timeout` applies to each hop,
+/// including reading its body, and `audit` records the last hop.
+pub async fn send_checked(
+    mut url: Url,
+    mut method: Method,
+    headers: &HeaderMap,
+    allowed_hosts: &[String],
+    timeout: Duration,
+    audit: &mut ProxyAudit,
+) -> Result<reqwest::Response, ProxyError> {
+    let mut headers = headers.clone();
+    for _ in 0..=MAX_REDIRECTS {
+        check_url(&url, allowed_hosts)?;
+        audit.host = url.host_str().map(str::to_string);
+        audit.addresses.clear();
+        let addrs = resolve_public(&url).await?;
+        audit.addresses = addrs.iter().map(|addr| addr.ip().to_string()).collect();
+
+        let response = pinned_client(&url, &addrs, timeout)?
+            .request(method.clone(), url.clone())
+            .headers(headers.clone())
+            .send()
+            .await
+            .map_err(|e| ProxyError::Upstream(format!("Request failed: {}", e)))?;
+        let status = response.status();
+        audit.status_code = Some(status.as_u16());
+
+        let location = response.headers().get(header::LOCATION).and_then(|l| l.to_str().ok());
+        if let (true, Some(location)) = (status.is_redirection(), location) {
+            let next = url
+                .join(location)
+                .map_err(|e| ProxyError::Upstream(format!("Invalid redirect to '{}': {}", location, e)))?;
+            strip_cross_host_credentials(&mut headers, &url, &next);
+            url = next;
+            if status == StatusCode::SEE_OTHER {
+                method = Method::GET;
+            }
+            continue;
+        }
+        return Ok(response);
+    }
+    Err(ProxyError::Upstream(format!("Stopped after {} redirects", MAX_REDIRECTS)))
+}
+
+async fn fetch(
+    url: Url,
+    method: Method,
+    headers: HeaderMap,
+    config: &Config,
+    audit: &mut ProxyAudit,
+) -> Result<Fetched, ProxyError> {
+    let mut response = send_checked(url, method, &headers, &config.proxy_allowed_hosts, REQUEST_TIMEOUT, audit).await?;
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|ct| ct.to_str().ok())
+        .unwrap_or("")
+        .to_lowercase();
+    let body = read_limited(&mut response, config.proxy_max_response_mb).await?;
+    audit.response_bytes = Some(body.len());
+    Ok(Fetched { status, content_type, body })
+}
+
+/// XML and RSS stay text for the browser's feed parser; anything else is
+/// passed on as JSON when it parses
+fn response_data(fetched: &Fetched) -> Value {
+    let text = String::from_utf8_lossy(&fetched.body).into_owned();
+    let is_feed = fetched.content_type.contains("xml")
+        || fetched.content_type.contains("rss")
+        || text.trim_start().starts_with("<?xml")
+        || text.contains("<rss")
+        || text.contains("<feed");
+    if is_feed {
+        return Value::String(text);
+    }
+    serde_json::from_str(&text).unwrap_or(Value::String(text))
+}
+
+async fn log_request(
+    pool: &Pool<Postgres>,
+    claims: &Claims,
+    method: &str,
+    url: &str,
+    audit: &ProxyAudit,
+    error: Option<&ProxyError>,
+    started: Instant,
+) {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO proxy_requests
+            (user_id, method, url, host, addresses, status_code, response_bytes, error, blocked, duration_ms)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        "#
+    )
+    .bind(claims.user_id())
+    .bind(method.chars().take(10).collect::<String>())
+    .bind(url)
+    .bind(&audit.host)
+    .bind(&audit.addresses)
+    .bind(audit.status_code.map(i32::from))
+    .bind(audit.response_bytes.map(|bytes| bytes as i64))
+    .bind(error.map(ProxyError::message))
+    .bind(matches!(error, Some(ProxyError::Blocked(_))))
+    .bind(i32::try_from(started.elapsed().as_millis()).unwrap_or(i32::MAX))
+    .execute(pool)
+    .await;
+
+    if let Err(e) = result {
+        eprintln!("Failed to log proxy request to {}: {}", url, e);
+    }
+}
+
+// Proxy external requests to bypass CORS restrictions
+pub async fn proxy_external_request(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    req: web::Json<ProxyRequest>,
+) -> Result<HttpResponse> {
+    let started = Instant::now();
+    let method = req.method.as_deref().unwrap_or("GET").trim().to_uppercase();
+    println!("Proxy {} request to: {}", method, req.url);
+
+    let mut audit = ProxyAudit::default();
+    let prepared = Url::parse(req.url.trim())
+        .map_err(|e| ProxyError::Invalid(format!("'{}' is not a valid URL: {}", req.url, e)))
+        .and_then(|url| Ok((url, parse_method(&method)?, forwarded_headers(req.headers.as_ref())?)));
+    let result = match prepared {
+        Ok((url, method, headers)) => fetch(url, method, headers, &data.config, &mut audit).await,
+        Err(e) => Err(e),
+    };
+
+    log_request(&data.db, &claims, &method, &req.url, &audit, result.as_ref().err(), started).await;
+
+    match result {
+        Ok(fetched) => {
+            println!("Proxy request successful, returning {} bytes", fetched.body.len());
+            Ok(HttpResponse::Ok().json(ProxyResponse {
+                success: true,
+                status: Some(fetched.status.as_u16()),
+                data: Some(response_data(&fetched)),
+                error: None,
+            }))
+        }
+        Err(e) => {
+            eprintln!("Proxy request to {} failed: {}", req.url, e.message());
+            Ok(e.to_response())
+        }
+    }
+}
+
+/// Audit log of proxied calls, newest first, optionally only refused ones
+pub async fn list_requests(
+    data: web::Data<Arc<ApiState>>,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    let pagination = match Pagination::from_query(&query) {
+        Ok(p) => p,
+        Err(e) => return Ok(entities::validation_failed(vec![e])),
+    };
+    let blocked = match query.get("blocked").map(String::as_str) {
+        None => None,
+        Some("true") => Some(true),
+        Some("false") => Some(false),
+        Some(other) => {
+            return Ok(entities::validation_failed(vec![format!("blocked must be true or false, got '{}'", other)]))
+        }
+    };
+    let user_id = query.get("user_id");
+
+    let total = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM proxy_requests WHERE ($1::boolean IS NULL OR blocked = $1) AND ($2::text IS NULL OR user_id = $2)"
+    )
+    .bind(blocked)
+    .bind(user_id)
+    .fetch_one(&data.db)
+    .await;
+    let total = match total {
+        Ok(total) => total,
+        Err(e) => return Ok(entities::database_error(e)),
+    };
+    let requests = sqlx::query_scalar::<_, Value>(
+        r#"
+        SELECT to_jsonb(p)
+        FROM proxy_requests p
+        WHERE ($1::boolean IS NULL OR p.blocked = $1) AND ($2::text IS NULL OR p.user_id = $2)
+        ORDER BY p.requested_at DESC
+        LIMIT $3 OFFSET $4
+        "#
+    )
+    .bind(blocked)
+    .bind(user_id)
+    .bind(pagination.limit())
+    .bind(pagination.offset())
+    .fetch_all(&data.db)
+    .await;
+
+    match requests {
+        Ok(requests) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "pagination": pagination.to_json(total, requests.len()),
+            "data": requests
+        }))),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocked(ip: &str) -> bool {
+        is_blocked_ip(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn internal_addresses_are_blocked() {
+        for ip in [
+            "169.254.169.254",
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.5.4",
+            "192.168.1.1",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::ffff:10.0.0.1",
+            "::ffff:169.254.169.254",
+            "fd00::1",
+            "fe80::1",
+        ] {
+            assert!(blocked(ip), "{} should be blocked", ip);
+        }
+    }
+
+    #[test]
+    fn public_addresses_are_allowed() {
+        for ip in ["93.184.216.34", "8.8.8.8", "100.128.0.1", "2606:4700::1111", "::ffff:8.8.8.8"] {
+            assert!(!blocked(ip), "{} should be allowed", ip);
+        }
+    }
+
+    #[test]
+    fn allowlist_takes_subdomains_but_not_look_alikes() {
+        let allowed = vec!["example.org".to_string()];
+        assert!(host_allowed("example.org", &allowed));
+        assert!(host_allowed("feeds.example.org", &allowed));
+        assert!(host_allowed("Feeds.Example.org.", &allowed));
+        assert!(!host_allowed("evil-example.org", &allowed));
+        assert!(!host_allowed("example.org.evil.com", &allowed));
+        assert!(!host_allowed("org", &allowed));
+    }
+
+    #[test]
+    fn credentials_are_dropped_when_a_redirect_changes_host() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        headers.insert("x-api-key", HeaderValue::from_static("secret"));
+        headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+        let from = Url::parse("https://api.example.org/feed").unwrap();
+
+        let mut same_host = headers.clone();
+        strip_cross_host_credentials(&mut same_host, &from, &Url::parse("https://api.example.org/v2/feed").unwrap());
+        assert_eq!(same_host.len(), 3);
+
+        strip_cross_host_credentials(&mut headers, &from, &Url::parse("https://cdn.example.net/feed").unwrap());
+        assert_eq!(headers.keys().map(HeaderName::as_str).collect::<Vec<_>>(), ["accept"]);
+    }
+}
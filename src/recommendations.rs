@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
-use calamine::{open_workbook, Reader, Xlsx, DataType};
+use calamine::DataType;
 use std::collections::HashMap;
 
+use crate::spreadsheet;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Project {
     pub id: f64,
@@ -59,38 +61,34 @@ fn get_preference_to_filter_mappings() -> HashMap<String, serde_json::Value> {
 }
 
 pub fn get_recommendations(preferences: &[String], excel_file_path: &str) -> Result<Vec<Project>, anyhow::Error> {
-    let mut excel: Xlsx<_> = open_workbook(excel_file_path)?;
+    let sheet = spreadsheet::read_sheet(excel_file_path, None).map_err(anyhow::Error::msg)?;
+    let headers = sheet.headers;
     let mut projects = Vec::new();
 
-    if let Some(Ok(range)) = excel.worksheet_range_at(0) {
-        let mut rows = range.rows();
-        let headers = rows.next().unwrap().iter().map(|c| c.to_string()).collect::<Vec<String>>();
-
-        for (i, row) in rows.enumerate() {
-            let project_name = row.get(headers.iter().position(|h| h == "Project Name").unwrap()).and_then(|c| c.as_string()).unwrap_or_default();
-            if project_name.is_empty() {
-                continue;
-            }
-            let project = Project {
-                id: (i + 1) as f64,
-                project_name: project_name.to_string(),
-                project_description: row.get(headers.iter().position(|h| h == "Project Description").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
-                country: row.get(headers.iter().position(|h| h == "Country").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
-                naics_sector: row.get(headers.iter().position(|h| h == "NAICS Sector").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
-                committed: row.get(headers.iter().position(|h| h == "Committed").unwrap()).and_then(|c| c.as_f64()).unwrap_or_default(),
-                department: row.get(headers.iter().position(|h| h == "Department").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
-                project_type: row.get(headers.iter().position(|h| h == "Project Type").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
-                region: row.get(headers.iter().position(|h| h == "Region").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
-                fiscal_year: row.get(headers.iter().position(|h| h == "Fiscal Year").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
-                project_number: row.get(headers.iter().position(|h| h == "Project Number").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
-                framework: row.get(headers.iter().position(|h| h == "Framework").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
-                project_profile_url: row.get(headers.iter().position(|h| h == "Project Profile URL").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
-                tags: vec![], // Simplified for now
-                starred: false,
-                comment: "".to_string(),
-            };
-            projects.push(project);
+    for (i, row) in sheet.rows.iter().enumerate() {
+        let project_name = row.get(headers.iter().position(|h| h == "Project Name").unwrap()).and_then(|c| c.as_string()).unwrap_or_default();
+        if project_name.is_empty() {
+            continue;
         }
+        let project = Project {
+            id: (i + 1) as f64,
+            project_name: project_name.to_string(),
+            project_description: row.get(headers.iter().position(|h| h == "Project Description").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
+            country: row.get(headers.iter().position(|h| h == "Country").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
+            naics_sector: row.get(headers.iter().position(|h| h == "NAICS Sector").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
+            committed: row.get(headers.iter().position(|h| h == "Committed").unwrap()).and_then(|c| c.as_f64()).unwrap_or_default(),
+            department: row.get(headers.iter().position(|h| h == "Department").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
+            project_type: row.get(headers.iter().position(|h| h == "Project Type").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
+            region: row.get(headers.iter().position(|h| h == "Region").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
+            fiscal_year: row.get(headers.iter().position(|h| h == "Fiscal Year").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
+            project_number: row.get(headers.iter().position(|h| h == "Project Number").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
+            framework: row.get(headers.iter().position(|h| h == "Framework").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
+            project_profile_url: row.get(headers.iter().position(|h| h == "Project Profile URL").unwrap()).and_then(|c| c.as_string()).unwrap_or_default().to_string(),
+            tags: vec![], // Simplified for now
+            starred: false,
+            comment: "".to_string(),
+        };
+        projects.push(project);
     }
 
     let mappings = get_preference_to_filter_mappings();
@@ -0,0 +1,354 @@
+// src/relationships.rs
+//
+// Link, unlink and list endpoints for the many-to-many join tables created by
+// the schema migrations. Every join table is usable from both sides, e.g.
+// `/projects/{id}/contacts` and `/contacts/{id}/projects`.
+use actix_web::{middleware, web, HttpResponse, Result};
+use serde_json::json;
+use sqlx::{Postgres, Transaction};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth;
+use crate::entities::{self, EntityDef, Pagination};
+use crate::ApiState;
+
+#[derive(Debug)]
+pub struct RelationshipDef {
+    pub table: &'static str,
+    pub left: &'static EntityDef,
+    pub left_column: &'static str,
+    pub right: &'static EntityDef,
+    pub right_column: &'static str,
+}
+
+pub static RELATIONSHIPS: &[RelationshipDef] = &[
+    RelationshipDef {
+        table: "users_roles",
+        left: &entities::USERS,
+        left_column: "user_id",
+        right: &entities::ROLES,
+        right_column: "role_id",
+    },
+    RelationshipDef {
+        table: "accounts_contacts",
+        left: &entities::ACCOUNTS,
+        left_column: "account_id",
+        right: &entities::CONTACTS,
+        right_column: "contact_id",
+    },
+    RelationshipDef {
+        table: "accounts_opportunities",
+        left: &entities::ACCOUNTS,
+        left_column: "account_id",
+        right: &entities::OPPORTUNITIES,
+        right_column: "opportunity_id",
+    },
+    RelationshipDef {
+        table: "contacts_opportunities",
+        left: &entities::CONTACTS,
+        left_column: "contact_id",
+        right: &entities::OPPORTUNITIES,
+        right_column: "opportunity_id",
+    },
+    RelationshipDef {
+        table: "campaigns_leads",
+        left: &entities::CAMPAIGNS,
+        left_column: "campaign_id",
+        right: &entities::LEADS,
+        right_column: "lead_id",
+    },
+    RelationshipDef {
+        table: "projects_contacts",
+        left: &entities::PROJECTS,
+        left_column: "project_id",
+        right: &entities::CONTACTS,
+        right_column: "contact_id",
+    },
+    RelationshipDef {
+        table: "projects_accounts",
+        left: &entities::PROJECTS,
+        left_column: "project_id",
+        right: &entities::ACCOUNTS,
+        right_column: "account_id",
+    },
+    RelationshipDef {
+        table: "events_contacts",
+        left: &entities::EVENTS,
+        left_column: "event_id",
+        right: &entities::CONTACTS,
+        right_column: "contact_id",
+    },
+];
+
+/// One direction of a relationship, as seen from the `from` entity
+#[derive(Debug, Clone)]
+struct RelationSide {
+    table: &'static str,
+    from: &'static EntityDef,
+    from_column: &'static str,
+    to: &'static EntityDef,
+    to_column: &'static str,
+}
+
+impl RelationshipDef {
+    fn sides(&self) -> [RelationSide; 2] {
+        [
+            RelationSide {
+                table: self.table,
+                from: self.left,
+                from_column: self.left_column,
+                to: self.right,
+                to_column: self.right_column,
+            },
+            RelationSide {
+                table: self.table,
+                from: self.right,
+                from_column: self.right_column,
+                to: self.left,
+                to_column: self.left_column,
+            },
+        ]
+    }
+}
+
+async fn record_exists(
+    pool: &sqlx::Pool<Postgres>,
+    entity: &EntityDef,
+    id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar::<_, bool>(&format!("SELECT EXISTS(SELECT 1 FROM {} WHERE id = $1)", entity.name))
+        .bind(id)
+        .fetch_one(pool)
+        .await
+}
+
+/// Remove every join-table row that points at the given record.
+/// Called before deleting the record itself.
+pub async fn unlink_all(
+    tx: &mut Transaction<'_, Postgres>,
+    entity: &EntityDef,
+    id: Uuid,
+) -> Result<(), sqlx::Error> {
+    for side in RELATIONSHIPS.iter().flat_map(|r| r.sides()) {
+        if side.from.name == entity.name {
+            sqlx::query(&format!("DELETE FROM {} WHERE {} = $1", side.table, side.from_column))
+                .bind(id)
+                .execute(&mut **tx)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Move every join-table row of `from` to `to`, dropping links `to` already
+/// has. Returns the number of rows moved. Used when merging records.
+pub async fn relink_all(
+    tx: &mut Transaction<'_, Postgres>,
+    entity: &EntityDef,
+    from: Uuid,
+    to: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let mut moved = 0;
+    for side in RELATIONSHIPS.iter().flat_map(|r| r.sides()) {
+        if side.from.name != entity.name {
+            continue;
+        }
+        sqlx::query(&format!(
+            "DELETE FROM {table} d WHERE d.{from} = $1 AND EXISTS \
+             (SELECT 1 FROM {table} s WHERE s.{from} = $2 AND s.{to} = d.{to})",
+            table = side.table,
+            from = side.from_column,
+            to = side.to_column,
+        ))
+        .bind(from)
+        .bind(to)
+        .execute(&mut **tx)
+        .await?;
+        moved += sqlx::query(&format!("UPDATE {} SET {} = $2 WHERE {} = $1", side.table, side.from_column, side.from_column))
+            .bind(from)
+            .bind(to)
+            .execute(&mut **tx)
+            .await?
+            .rows_affected();
+    }
+    Ok(moved)
+}
+
+/// Link `id` to each of `related_ids` of `related`, keeping links that
+/// already exist. Returns the number of links added.
+pub async fn link_many(
+    pool: &sqlx::Pool<Postgres>,
+    entity: &EntityDef,
+    id: Uuid,
+    related: &EntityDef,
+    related_ids: &[Uuid],
+) -> Result<u64, sqlx::Error> {
+    let Some(side) = RELATIONSHIPS
+        .iter()
+        .flat_map(|r| r.sides())
+        .find(|side| side.from.name == entity.name && side.to.name == related.name)
+    else {
+        return Err(sqlx::Error::Protocol(format!("{} and {} are not related", entity.name, related.name)));
+    };
+    let result = sqlx::query(&format!(
+        "INSERT INTO {table} ({from}, {to}) SELECT $1, unnest($2::uuid[]) ON CONFLICT DO NOTHING",
+        table = side.table,
+        from = side.from_column,
+        to = side.to_column,
+    ))
+    .bind(id)
+    .bind(related_ids)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+// List the records linked to `{id}`
+async fn list_related(
+    side: web::Data<RelationSide>,
+    data: web::Data<Arc<ApiState>>,
+    path: web::Path<Uuid>,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let pagination = match Pagination::from_query(&query) {
+        Ok(p) => p,
+        Err(e) => return Ok(entities::validation_failed(vec![e])),
+    };
+
+    match record_exists(&data.db, side.from, id).await {
+        Ok(true) => {}
+        Ok(false) => return Ok(entities::not_found(side.from, id)),
+        Err(e) => return Ok(entities::database_error(e)),
+    }
+
+    let total = sqlx::query_scalar::<_, i64>(
+        &format!("SELECT COUNT(*) FROM {} WHERE {} = $1", side.table, side.from_column)
+    )
+    .bind(id)
+    .fetch_one(&data.db)
+    .await;
+    let total = match total {
+        Ok(total) => total,
+        Err(e) => return Ok(entities::database_error(e)),
+    };
+
+    let records = sqlx::query_scalar::<_, serde_json::Value>(&format!(
+        r#"
+        SELECT (to_jsonb(t) - 'password_hash') || jsonb_build_object('linked_at', j.date_entered)
+        FROM {to} t
+        JOIN {table} j ON j.{to_column} = t.id
+        WHERE j.{from_column} = $1
+        ORDER BY j.date_entered DESC, t.id
+        LIMIT $2 OFFSET $3
+        "#,
+        to = side.to.name,
+        table = side.table,
+        to_column = side.to_column,
+        from_column = side.from_column,
+    ))
+    .bind(id)
+    .bind(pagination.limit())
+    .bind(pagination.offset())
+    .fetch_all(&data.db)
+    .await;
+
+    match records {
+        Ok(records) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "pagination": pagination.to_json(total, records.len()),
+            "data": records
+        }))),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+// Link `{id}` to `{related_id}`; linking twice is not an error
+async fn link_records(
+    side: web::Data<RelationSide>,
+    data: web::Data<Arc<ApiState>>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse> {
+    let (id, related_id) = path.into_inner();
+
+    for (entity, record_id) in [(side.from, id), (side.to, related_id)] {
+        match record_exists(&data.db, entity, record_id).await {
+            Ok(true) => {}
+            Ok(false) => return Ok(entities::not_found(entity, record_id)),
+            Err(e) => return Ok(entities::database_error(e)),
+        }
+    }
+
+    let result = sqlx::query(&format!(
+        "INSERT INTO {} ({}, {}) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        side.table, side.from_column, side.to_column
+    ))
+    .bind(id)
+    .bind(related_id)
+    .execute(&data.db)
+    .await;
+
+    match result {
+        Ok(r) if r.rows_affected() == 0 => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": format!("{} is already linked to {}", side.to.label, side.from.label.to_lowercase())
+        }))),
+        Ok(_) => Ok(HttpResponse::Created().json(json!({
+            "success": true,
+            "message": format!("{} linked to {}", side.to.label, side.from.label.to_lowercase())
+        }))),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+async fn unlink_records(
+    side: web::Data<RelationSide>,
+    data: web::Data<Arc<ApiState>>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse> {
+    let (id, related_id) = path.into_inner();
+    let result = sqlx::query(&format!(
+        "DELETE FROM {} WHERE {} = $1 AND {} = $2",
+        side.table, side.from_column, side.to_column
+    ))
+    .bind(id)
+    .bind(related_id)
+    .execute(&data.db)
+    .await;
+
+    match result {
+        Ok(r) if r.rows_affected() == 0 => Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": format!("{} {} is not linked to {} {}", side.to.label, related_id, side.from.label.to_lowercase(), id)
+        }))),
+        Ok(_) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": format!("{} unlinked from {}", side.to.label, side.from.label.to_lowercase())
+        }))),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+/// Register `/{entity}/{id}/{related}` and `/{entity}/{id}/{related}/{related_id}`
+/// for both sides of every relationship. Must be configured before the entity
+/// scopes so these paths are matched first.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    for side in RELATIONSHIPS.iter().flat_map(|r| r.sides()) {
+        let admin_only = auth::ADMIN_TABLES.contains(&side.table);
+        cfg.service(
+            web::resource(format!("/{}/{{id}}/{}", side.from.name, side.to.name))
+                .wrap(middleware::Condition::new(admin_only, middleware::from_fn(auth::require_admin)))
+                .app_data(web::Data::new(side.clone()))
+                .route(web::get().to(list_related))
+        );
+        cfg.service(
+            web::resource(format!("/{}/{{id}}/{}/{{related_id}}", side.from.name, side.to.name))
+                .wrap(middleware::Condition::new(admin_only, middleware::from_fn(auth::require_admin)))
+                .app_data(web::Data::new(side))
+                .route(web::post().to(link_records))
+                .route(web::delete().to(unlink_records))
+        );
+    }
+}
@@ -0,0 +1,260 @@
+// src/spreadsheet.rs
+//
+// One reader for every file the import endpoints accept. Workbooks (XLSX,
+// XLS, XLSB, ODS) go through calamine; CSV files are decoded to UTF-8 and
+// split on whichever delimiter the first lines use, and appear as a single
+// sheet named after the file.
+use calamine::{open_workbook, Data, Ods, Reader, Sheets, Xls, Xlsb, Xlsx};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, WINDOWS_1252};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// Delimiters tried when sniffing a CSV file, in order of preference
+const CSV_DELIMITERS: &[u8] = b",;\t|";
+
+/// Lines looked at when choosing a delimiter
+const SNIFF_LINES: usize = 20;
+
+/// A worksheet as a header row plus data rows
+pub struct SheetData {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<Data>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    Xlsx,
+    Xls,
+    Xlsb,
+    Ods,
+    Csv,
+}
+
+impl FileFormat {
+    /// Extension a file of this format is stored under
+    pub fn extension(self) -> &'static str {
+        match self {
+            FileFormat::Xlsx => "xlsx",
+            FileFormat::Xls => "xls",
+            FileFormat::Xlsb => "xlsb",
+            FileFormat::Ods => "ods",
+            FileFormat::Csv => "csv",
+        }
+    }
+
+    fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "xlsx" | "xlsm" | "xltx" | "xltm" | "xlam" => Some(FileFormat::Xlsx),
+            "xls" | "xla" | "xlt" => Some(FileFormat::Xls),
+            "xlsb" => Some(FileFormat::Xlsb),
+            "ods" | "ots" => Some(FileFormat::Ods),
+            "csv" | "tsv" | "tab" | "txt" => Some(FileFormat::Csv),
+            _ => None,
+        }
+    }
+
+    /// Format for a MIME type such as an upload's `Content-Type`
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        let essence = mime.split(';').next().unwrap_or_default().trim().to_lowercase();
+        match essence.as_str() {
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+            | "application/vnd.ms-excel.sheet.macroenabled.12" => Some(FileFormat::Xlsx),
+            "application/vnd.ms-excel" => Some(FileFormat::Xls),
+            "application/vnd.ms-excel.sheet.binary.macroenabled.12" => Some(FileFormat::Xlsb),
+            "application/vnd.oasis.opendocument.spreadsheet" => Some(FileFormat::Ods),
+            "text/csv" | "text/tab-separated-values" | "application/csv" | "text/plain" => Some(FileFormat::Csv),
+            _ => None,
+        }
+    }
+
+    /// Format for a file name such as an upload's original name
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        Path::new(name).extension().and_then(|e| e.to_str()).and_then(Self::from_extension)
+    }
+
+    /// Detect the format from the file extension, then from the MIME type
+    /// `mime_guess` gives it, then from the first bytes of the file
+    pub fn detect(path: &Path) -> Result<Self, String> {
+        if let Some(format) = path.to_str().and_then(Self::from_file_name) {
+            return Ok(format);
+        }
+        if let Some(format) = mime_guess::from_path(path).iter().find_map(|m| Self::from_mime(m.essence_str())) {
+            return Ok(format);
+        }
+        Self::sniff(path)
+    }
+
+    /// Detect the format from the first bytes of the file
+    pub fn sniff(path: &Path) -> Result<Self, String> {
+        let mut head = [0u8; 512];
+        let read = File::open(path)
+            .and_then(|mut file| file.read(&mut head))
+            .map_err(|e| format!("File not found at: {} - {}", path.display(), e))?;
+        let head = &head[..read];
+
+        if head.starts_with(b"PK\x03\x04") {
+            // ODS stores its MIME type uncompressed as the first zip entry
+            let is_ods = head.windows(46).any(|w| w == b"application/vnd.oasis.opendocument.spreadsheet");
+            return Ok(if is_ods { FileFormat::Ods } else { FileFormat::Xlsx });
+        }
+        if head.starts_with(&[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1]) {
+            return Ok(FileFormat::Xls);
+        }
+        if !head.is_empty() && !head.contains(&0) {
+            return Ok(FileFormat::Csv);
+        }
+        Err(format!("Unrecognised spreadsheet format: {}", path.display()))
+    }
+}
+
+fn open_workbook_as(path: &Path, format: FileFormat) -> Result<Sheets<BufReader<File>>, String> {
+    let opened = match format {
+        FileFormat::Xlsx => open_workbook::<Xlsx<_>, _>(path).map(Sheets::Xlsx).map_err(|e| e.to_string()),
+        FileFormat::Xls => open_workbook::<Xls<_>, _>(path).map(Sheets::Xls).map_err(|e| e.to_string()),
+        FileFormat::Xlsb => open_workbook::<Xlsb<_>, _>(path).map(Sheets::Xlsb).map_err(|e| e.to_string()),
+        FileFormat::Ods => open_workbook::<Ods<_>, _>(path).map(Sheets::Ods).map_err(|e| e.to_string()),
+        FileFormat::Csv => unreachable!("CSV files are not workbooks"),
+    };
+    opened.map_err(|e| format!("File not found at: {} - {}", path.display(), e))
+}
+
+/// The sheet a CSV file is presented as
+fn csv_sheet_name(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Sheet1")
+        .to_string()
+}
+
+/// Names of the sheets in the file, in workbook order
+pub fn sheet_names(path: impl AsRef<Path>) -> Result<Vec<String>, String> {
+    let path = path.as_ref();
+    match FileFormat::detect(path)? {
+        FileFormat::Csv => Ok(vec![csv_sheet_name(path)]),
+        format => Ok(open_workbook_as(path, format)?.sheet_names()),
+    }
+}
+
+/// Read one sheet, or the first when `sheet_name` is None. The first row is
+/// taken as the header row.
+pub fn read_sheet(path: impl AsRef<Path>, sheet_name: Option<&str>) -> Result<SheetData, String> {
+    let path = path.as_ref();
+    let rows = match FileFormat::detect(path)? {
+        FileFormat::Csv => {
+            if let Some(name) = sheet_name {
+                if name != csv_sheet_name(path) {
+                    return Err(format!("Error reading sheet: CSV files have no sheet named '{}'", name));
+                }
+            }
+            read_csv(path)?
+        }
+        format => {
+            let mut workbook = open_workbook_as(path, format)?;
+            let sheet_name = match sheet_name {
+                Some(name) => name.to_string(),
+                None => workbook.sheet_names().first().cloned().unwrap_or_else(|| "Sheet1".to_string()),
+            };
+            let range = workbook.worksheet_range(&sheet_name)
+                .map_err(|e| format!("Error reading sheet: {}", e))?;
+            range.rows().map(|row| row.to_vec()).collect()
+        }
+    };
+    Ok(with_header_row(rows))
+}
+
+/// A CSV document fetched rather than uploaded, such as a Google Sheets
+/// export, as a sheet whose first row is the header row
+pub fn parse_csv(bytes: &[u8]) -> Result<SheetData, String> {
+    Ok(with_header_row(csv_rows(&decode_text(bytes))?))
+}
+
+fn with_header_row(rows: Vec<Vec<Data>>) -> SheetData {
+    let mut rows = rows.into_iter();
+    let headers = rows.next()
+        .map(|first_row| first_row.iter().map(|cell| cell.to_string().trim().to_string()).collect())
+        .unwrap_or_default();
+    SheetData { headers, rows: rows.collect() }
+}
+
+/// Decode to UTF-8: a byte order mark wins, then valid UTF-8, and anything
+/// else is read as Windows-1252, which is what Excel writes on Windows
+fn decode_text(bytes: &[u8]) -> String {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+        return text.into_owned();
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+    // UTF-16 without a BOM shows up as a NUL in every other byte
+    let nul_at = |parity: usize| bytes.iter().skip(parity).step_by(2).take(256).all(|b| *b == 0);
+    if bytes.len() >= 2 && nul_at(1) {
+        return UTF_16LE.decode_without_bom_handling(bytes).0.into_owned();
+    }
+    if bytes.len() >= 2 && nul_at(0) {
+        return UTF_16BE.decode_without_bom_handling(bytes).0.into_owned();
+    }
+    WINDOWS_1252.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+/// Occurrences of `delimiter` in `line`, ignoring quoted text
+fn count_delimiter(line: &str, delimiter: u8) -> usize {
+    let mut quoted = false;
+    let mut count = 0;
+    for b in line.bytes() {
+        if b == b'"' {
+            quoted = !quoted;
+        } else if b == delimiter && !quoted {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// The delimiter that splits the first lines into the same, largest number
+/// of fields. Falls back to a comma.
+fn detect_delimiter(text: &str) -> u8 {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).take(SNIFF_LINES).collect();
+    let mut best = (b',', 0, 0);
+    for &delimiter in CSV_DELIMITERS {
+        let counts: Vec<usize> = lines.iter().map(|line| count_delimiter(line, delimiter)).collect();
+        let Some(&first) = counts.first() else {
+            continue;
+        };
+        if first == 0 {
+            continue;
+        }
+        let consistent = counts.iter().filter(|&&c| c == first).count();
+        if (consistent, first) > (best.1, best.2) {
+            best = (delimiter, consistent, first);
+        }
+    }
+    best.0
+}
+
+fn read_csv(path: &Path) -> Result<Vec<Vec<Data>>, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("File not found at: {} - {}", path.display(), e))?;
+    csv_rows(&decode_text(&bytes))
+}
+
+fn csv_rows(text: &str) -> Result<Vec<Vec<Data>>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(detect_delimiter(text))
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let mut rows = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| format!("Error reading CSV line {}: {}", index + 1, e))?;
+        rows.push(
+            record.iter()
+                .map(|field| if field.trim().is_empty() { Data::Empty } else { Data::String(field.to_string()) })
+                .collect(),
+        );
+    }
+    Ok(rows)
+}
@@ -0,0 +1,209 @@
+// src/sql_safety.rs
+//
+// Validation for ad-hoc SQL sent to `/api/db/query`. The text is parsed and
+// only a single plain query is accepted; the caller still runs it inside a
+// READ ONLY transaction, which stays the final safeguard.
+use sqlparser::ast::{Expr, ObjectName, Query, SetExpr, Statement, TableFactor, Visit, Visitor};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use sqlx::{Pool, Postgres, Transaction};
+use std::ops::ControlFlow;
+
+/// Functions that change server state, reach outside the database or run
+/// SQL passed in as a string
+const BLOCKED_FUNCTIONS: &[&str] = &[
+    "pg_sleep",
+    "pg_sleep_for",
+    "pg_sleep_until",
+    "pg_terminate_backend",
+    "pg_cancel_backend",
+    "pg_reload_conf",
+    "pg_rotate_logfile",
+    "pg_promote",
+    "pg_switch_wal",
+    "pg_stop_backup",
+    "pg_notify",
+    "set_config",
+    "nextval",
+    "setval",
+    "pg_read_file",
+    "pg_read_binary_file",
+    "pg_stat_file",
+    "query_to_xml",
+    "query_to_xmlschema",
+    "query_to_xml_and_xmlschema",
+    "cursor_to_xml",
+];
+
+const BLOCKED_FUNCTION_PREFIXES: &[&str] = &[
+    "pg_advisory_",
+    "pg_try_advisory_",
+    "pg_create_",
+    "pg_drop_",
+    "pg_logical_",
+    "pg_replication_",
+    "pg_backup_",
+    "pg_wal_replay_",
+    "pg_ls_",
+    "pg_stat_reset",
+    "pg_file_",
+    "dblink",
+    "lo_",
+];
+
+fn is_blocked_function(name: &ObjectName) -> bool {
+    // Schema-qualified names are checked by their last part
+    let Some(function) = name.0.last().map(|ident| ident.value.to_lowercase()) else {
+        return false;
+    };
+    BLOCKED_FUNCTIONS.contains(&function.as_str())
+        || BLOCKED_FUNCTION_PREFIXES.iter().any(|prefix| function.starts_with(prefix))
+}
+
+// Reject SELECT ... INTO anywhere in a set expression
+fn check_set_expr(body: &SetExpr) -> Result<(), String> {
+    match body {
+        SetExpr::Select(select) if select.into.is_some() => {
+            Err("SELECT INTO is not allowed".to_string())
+        }
+        SetExpr::SetOperation { left, right, .. } => {
+            check_set_expr(left)?;
+            check_set_expr(right)
+        }
+        SetExpr::Insert(_) | SetExpr::Update(_) => {
+            Err("Data-modifying statements are not allowed".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+struct ReadOnlyVisitor;
+
+impl Visitor for ReadOnlyVisitor {
+    type Break = String;
+
+    // Nested statements appear in data-modifying CTEs
+    fn pre_visit_statement(&mut self, statement: &Statement) -> ControlFlow<String> {
+        match statement {
+            Statement::Query(_) => ControlFlow::Continue(()),
+            _ => ControlFlow::Break("Only SELECT queries are allowed".to_string()),
+        }
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<String> {
+        if !query.locks.is_empty() {
+            return ControlFlow::Break("Row locking clauses (FOR UPDATE/SHARE) are not allowed".to_string());
+        }
+        match check_set_expr(&query.body) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(e) => ControlFlow::Break(e),
+        }
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<String> {
+        match expr {
+            Expr::Function(function) if is_blocked_function(&function.name) => {
+                ControlFlow::Break(format!("Function {} is not allowed", function.name))
+            }
+            _ => ControlFlow::Continue(()),
+        }
+    }
+
+    // Set-returning functions used as tables, e.g. FROM pg_sleep(10)
+    fn pre_visit_table_factor(&mut self, table_factor: &TableFactor) -> ControlFlow<String> {
+        match table_factor {
+            TableFactor::Table { name, args: Some(_), .. } | TableFactor::Function { name, .. }
+                if is_blocked_function(name) =>
+            {
+                ControlFlow::Break(format!("Function {} is not allowed", name))
+            }
+            _ => ControlFlow::Continue(()),
+        }
+    }
+}
+
+/// Parse `sql` and accept it only if it is exactly one read-only query.
+/// Returns the statement re-rendered from the parsed tree, so what runs is
+/// exactly what was checked.
+pub fn validate_read_only(sql: &str) -> Result<String, String> {
+    let statements = Parser::parse_sql(&PostgreSqlDialect {}, sql)
+        .map_err(|e| format!("Could not parse query: {}", e))?;
+
+    let statement = match statements.as_slice() {
+        [statement] => statement,
+        [] => return Err("Query is empty".to_string()),
+        _ => return Err("Only a single statement is allowed".to_string()),
+    };
+
+    match statement.visit(&mut ReadOnlyVisitor) {
+        ControlFlow::Continue(()) => Ok(statement.to_string()),
+        ControlFlow::Break(e) => Err(e),
+    }
+}
+
+/// Start a READ ONLY transaction whose statements are cancelled after `timeout_secs`.
+/// Callers roll it back when done.
+pub async fn begin_read_only(
+    pool: &Pool<Postgres>,
+    timeout_secs: u64,
+) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("SET TRANSACTION READ ONLY").execute(&mut *tx).await?;
+    sqlx::query(&format!("SET LOCAL statement_timeout = {}", timeout_secs * 1000))
+        .execute(&mut *tx)
+        .await?;
+    Ok(tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejected(sql: &str) -> bool {
+        validate_read_only(sql).is_err()
+    }
+
+    #[test]
+    fn plain_select_passes() {
+        assert!(validate_read_only("SELECT id, name FROM accounts WHERE deleted = false LIMIT 10").is_ok());
+        assert!(validate_read_only("WITH a AS (SELECT id FROM accounts) SELECT count(*) FROM a").is_ok());
+    }
+
+    #[test]
+    fn second_statement_is_rejected() {
+        assert!(rejected("select 1; drop table users"));
+    }
+
+    #[test]
+    fn data_modifying_cte_is_rejected() {
+        assert!(rejected("with d as (delete from users returning id) select * from d"));
+        assert!(rejected("with u as (update users set status = 'x' returning id) select * from u"));
+    }
+
+    #[test]
+    fn select_into_is_rejected() {
+        assert!(rejected("SELECT * INTO stolen FROM users"));
+    }
+
+    #[test]
+    fn row_locks_are_rejected() {
+        assert!(rejected("SELECT * FROM users FOR UPDATE"));
+    }
+
+    #[test]
+    fn blocked_functions_are_rejected() {
+        for sql in [
+            "SELECT pg_sleep(10)",
+            "SELECT pg_catalog.pg_sleep(10)",
+            "SELECT * FROM pg_sleep(10)",
+            "SELECT * FROM pg_ls_logdir()",
+            "SELECT * FROM pg_catalog.pg_ls_waldir()",
+            "SELECT pg_ls_tmpdir()",
+            "SELECT pg_stat_reset()",
+            "SELECT pg_stat_reset_shared('bgwriter')",
+            "SELECT pg_file_write('x', 'y', false)",
+        ] {
+            assert!(rejected(sql), "{} should be rejected", sql);
+        }
+    }
+}
@@ -0,0 +1,329 @@
+// src/uploads.rs
+//
+// Staging area for spreadsheets uploaded to `/api/import/uploads`. Each
+// upload gets its own directory holding the file under its original name,
+// plus a `{id}.json` record of its owner and expiry. The import endpoints
+// refer to uploads by ID only, so clients never name a path on the server.
+// Expired uploads are removed by a background sweep.
+use actix_multipart::{Field, Multipart};
+use actix_web::{web, HttpResponse, Result};
+use chrono::{DateTime, Duration, Utc};
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::Claims;
+use crate::spreadsheet::{self, FileFormat};
+use crate::ApiState;
+
+/// How often the background task looks for expired uploads
+const SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// What is known about a staged upload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadInfo {
+    pub id: Uuid,
+    pub file_name: String,
+    pub format: FileFormat,
+    pub size: u64,
+    /// Hex SHA-256 of the file, recorded with the imports made from it
+    #[serde(default)]
+    pub sha256: Option<String>,
+    pub uploaded_by: Uuid,
+    pub uploaded_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub enum UploadError {
+    NotFound(Uuid),
+    TooLarge(u64),
+    Invalid(String),
+    Io(std::io::Error),
+}
+
+impl From<std::io::Error> for UploadError {
+    fn from(e: std::io::Error) -> Self {
+        UploadError::Io(e)
+    }
+}
+
+impl UploadError {
+    pub fn to_response(&self) -> HttpResponse {
+        match self {
+            // Someone else's upload is reported as missing too
+            UploadError::NotFound(id) => HttpResponse::NotFound().json(json!({
+                "success": false,
+                "error": format!("Upload {} not found or expired", id)
+            })),
+            UploadError::TooLarge(max_mb) => HttpResponse::PayloadTooLarge().json(json!({
+                "success": false,
+                "error": format!("Uploads are limited to {} MB", max_mb)
+            })),
+            UploadError::Invalid(e) => HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "error": e
+            })),
+            UploadError::Io(e) => HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "error": format!("Could not store upload: {}", e)
+            })),
+        }
+    }
+}
+
+/// Keep the original name readable but free of path separators
+fn safe_file_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let cleaned: String = base
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | ' ') { c } else { '_' })
+        .collect();
+    let cleaned = cleaned.trim_matches(|c: char| c == '.' || c.is_whitespace());
+    if cleaned.is_empty() { "upload".to_string() } else { cleaned.chars().take(120).collect() }
+}
+
+pub struct UploadStore {
+    dir: PathBuf,
+    max_mb: u64,
+    ttl: Duration,
+}
+
+impl UploadStore {
+    pub fn new(dir: PathBuf, max_mb: u64, ttl_minutes: u64) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(UploadStore { dir, max_mb, ttl: Duration::minutes(ttl_minutes as i64) })
+    }
+
+    fn max_bytes(&self) -> u64 {
+        self.max_mb * 1024 * 1024
+    }
+
+    fn record_path(&self, id: Uuid) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn upload_dir(&self, id: Uuid) -> PathBuf {
+        self.dir.join(id.to_string())
+    }
+
+    /// Stream one multipart field to disk, enforcing the size limit
+    async fn save(&self, mut field: Field, uploaded_by: Uuid) -> Result<UploadInfo, UploadError> {
+        let original = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename())
+            .map(str::to_string)
+            .ok_or_else(|| UploadError::Invalid("The file field must include a file name".to_string()))?;
+        let content_type = field.content_type().map(|m| m.essence_str().to_string());
+
+        let id = Uuid::new_v4();
+        let dir = self.upload_dir(id);
+        std::fs::create_dir_all(&dir)?;
+        let partial = dir.join("upload.part");
+
+        let written: Result<(u64, String), UploadError> = async {
+            let mut file = std::fs::File::create(&partial)?;
+            let mut hasher = Sha256::new();
+            let mut size = 0u64;
+            while let Some(chunk) = field.try_next().await.map_err(|e| UploadError::Invalid(e.to_string()))? {
+                size += chunk.len() as u64;
+                if size > self.max_bytes() {
+                    return Err(UploadError::TooLarge(self.max_mb));
+                }
+                hasher.update(&chunk);
+                file.write_all(&chunk)?;
+            }
+            Ok((size, format!("{:x}", hasher.finalize())))
+        }
+        .await;
+        let stored = written.and_then(|(size, sha256)| {
+            if size == 0 {
+                return Err(UploadError::Invalid("The uploaded file is empty".to_string()));
+            }
+            // The original name decides the format, then the declared type, then the content
+            let format = match FileFormat::from_file_name(&original)
+                .or_else(|| content_type.as_deref().and_then(FileFormat::from_mime))
+            {
+                Some(format) => format,
+                None => FileFormat::sniff(&partial).map_err(UploadError::Invalid)?,
+            };
+            Ok((size, sha256, format))
+        });
+        let (size, sha256, format) = match stored {
+            Ok(stored) => stored,
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(&dir);
+                return Err(e);
+            }
+        };
+
+        // Stored under the original name so a CSV file's sheet keeps it
+        let mut file_name = safe_file_name(&original);
+        if FileFormat::from_file_name(&file_name) != Some(format) {
+            file_name = format!("{}.{}", file_name, format.extension());
+        }
+        std::fs::rename(&partial, dir.join(&file_name))?;
+
+        let now = Utc::now();
+        let info = UploadInfo {
+            id,
+            file_name,
+            format,
+            size,
+            sha256: Some(sha256),
+            uploaded_by,
+            uploaded_at: now,
+            expires_at: now + self.ttl,
+        };
+        std::fs::write(self.record_path(id), serde_json::to_vec(&info).map_err(std::io::Error::other)?)?;
+        Ok(info)
+    }
+
+    fn info(&self, id: Uuid) -> Option<UploadInfo> {
+        let bytes = std::fs::read(self.record_path(id)).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// An unexpired upload owned by `user`, with the path of its file
+    pub fn get(&self, id: Uuid, user: Uuid) -> Result<(UploadInfo, PathBuf), UploadError> {
+        match self.info(id) {
+            Some(info) if info.uploaded_by == user && info.expires_at > Utc::now() => {
+                let path = self.upload_dir(id).join(&info.file_name);
+                Ok((info, path))
+            }
+            _ => Err(UploadError::NotFound(id)),
+        }
+    }
+
+    pub fn remove(&self, id: Uuid) -> std::io::Result<()> {
+        let ignore_missing = |result: std::io::Result<()>| match result {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+        ignore_missing(std::fs::remove_file(self.record_path(id)))?;
+        ignore_missing(std::fs::remove_dir_all(self.upload_dir(id)))
+    }
+
+    /// Whether a directory without a readable record, left over from an
+    /// interrupted upload, is older than the expiry time
+    fn is_abandoned(&self, id: Uuid) -> bool {
+        let ttl = self.ttl.to_std().unwrap_or_default();
+        std::fs::metadata(self.upload_dir(id))
+            .and_then(|m| m.modified())
+            .map(|modified| modified.elapsed().is_ok_and(|age| age > ttl))
+            .unwrap_or(true)
+    }
+
+    /// Delete expired uploads and abandoned partial ones
+    pub fn purge_expired(&self) -> usize {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return 0;
+        };
+        let ids: HashSet<Uuid> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                name.to_str().and_then(|n| Uuid::parse_str(n.trim_end_matches(".json")).ok())
+            })
+            .collect();
+
+        let now = Utc::now();
+        let mut removed = 0;
+        for id in ids {
+            let expired = match self.info(id) {
+                Some(info) => info.expires_at <= now,
+                None => self.is_abandoned(id),
+            };
+            if expired && self.remove(id).is_ok() {
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// Run `purge_expired` in the background for as long as the server lives
+    pub fn spawn_sweeper(state: Arc<ApiState>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                let removed = state.uploads.purge_expired();
+                if removed > 0 {
+                    println!("Removed {} expired upload(s)", removed);
+                }
+            }
+        });
+    }
+}
+
+/// `POST /api/import/uploads`: stage the multipart `file` field and list its sheets
+pub async fn upload_file(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    mut payload: Multipart,
+) -> Result<HttpResponse> {
+    let mut uploaded = None;
+    while let Some(field) = payload.try_next().await? {
+        if field.name() != Some("file") || uploaded.is_some() {
+            continue;
+        }
+        match data.uploads.save(field, claims.sub).await {
+            Ok(info) => uploaded = Some(info),
+            Err(e) => return Ok(e.to_response()),
+        }
+    }
+    let Some(info) = uploaded else {
+        return Ok(UploadError::Invalid("Send the spreadsheet in a multipart field named 'file'".to_string()).to_response());
+    };
+
+    let path = data.uploads.upload_dir(info.id).join(&info.file_name);
+    match spreadsheet::sheet_names(&path) {
+        Ok(sheets) => Ok(HttpResponse::Created().json(json!({
+            "success": true,
+            "upload": info,
+            "sheets": sheets
+        }))),
+        Err(e) => {
+            let _ = data.uploads.remove(info.id);
+            Ok(UploadError::Invalid(format!("Failed to read spreadsheet '{}': {}", info.file_name, e)).to_response())
+        }
+    }
+}
+
+pub async fn get_upload(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    match data.uploads.get(path.into_inner(), claims.sub) {
+        Ok((info, _)) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "upload": info
+        }))),
+        Err(e) => Ok(e.to_response()),
+    }
+}
+
+pub async fn delete_upload(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    if let Err(e) = data.uploads.get(id, claims.sub) {
+        return Ok(e.to_response());
+    }
+    match data.uploads.remove(id) {
+        Ok(()) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": format!("Upload {} deleted", id)
+        }))),
+        Err(e) => Ok(UploadError::Io(e).to_response()),
+    }
+}
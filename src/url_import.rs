@@ -0,0 +1,1058 @@
+// src/url_import.rs
+//
+// Imports from a URL: a Google Sheets tab, fetched as its CSV export, or any
+// CSV file. One request fetches and parses the sheet, maps its columns like
+// an uploaded file and previews or imports it. Every pull that returns a
+// different CSV is kept as a snapshot, so the next pull of the same URL can
+// list the rows added, removed and changed since. Saved sources can be
+// pulled again on request or on their own schedule.
+use actix_web::{web, HttpResponse, Result};
+use chrono::{DateTime, Utc};
+use reqwest::header::{self, HeaderMap};
+use reqwest::{Method, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sqlx::{Pool, Postgres};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::auth::{self, Claims};
+use crate::config::Config;
+use crate::entities::{self, EntityDef, Pagination};
+use crate::import::{self, ImportResponse};
+use crate::import_batch::{DuplicateCheck, ImportOptions, ImportOutcome};
+use crate::import_jobs::NewJob;
+use crate::import_mapping::{self, ColumnMappings};
+use crate::import_tasks::{self, ImportRun};
+use crate::proxy::{self, ProxyAudit};
+use crate::spreadsheet::{self, SheetData};
+use crate::ApiState;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often saved sources are checked for a scheduled pull
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// `created_by` of scheduled pulls of sources saved without a user
+const SCHEDULE_USER: &str = "url-import-schedule";
+
+/// Rows listed per kind of change; the counts cover all of them
+const CHANGES_LISTED: usize = 100;
+
+/// A sheet to import from a URL. `column_mappings` and `profile` work as for
+/// uploaded files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlImportRequest {
+    /// Google Sheets link, published-to-the-web link or CSV file URL
+    pub url: String,
+    /// Tab of a Google Sheet; the link's own `gid`, else the first tab
+    pub gid: Option<String>,
+    pub table_name: String,
+    pub column_mappings: Option<ColumnMappings>,
+    pub profile: Option<String>,
+    /// Column that identifies a row when pulls are compared, the first by default
+    pub key_column: Option<String>,
+    #[serde(flatten)]
+    pub options: ImportOptions,
+}
+
+fn is_google_sheet(url: &Url) -> bool {
+    url.host_str() == Some("docs.google.com") && url.path().starts_with("/spreadsheets/")
+}
+
+/// The tab a Google Sheets link points at, from `?gid=` or `#gid=`
+fn link_gid(url: &Url) -> Option<String> {
+    url.query_pairs()
+        .find(|(name, _)| name == "gid")
+        .map(|(_, gid)| gid.into_owned())
+        .or_else(|| url.fragment().and_then(|f| f.strip_prefix("gid=")).map(str::to_string))
+}
+
+/// The CSV to fetch for `url`. Google Sheets links become the CSV export of
+/// tab `gid`; other links are fetched as they are.
+pub fn csv_url(url: &str, gid: Option<&str>) -> Result<String, String> {
+    let url = url.trim();
+    let parsed = Url::parse(url).map_err(|e| format!("'{}' is not a valid URL: {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("'{}' is not an http or https URL", url));
+    }
+    if !is_google_sheet(&parsed) {
+        return Ok(url.to_string());
+    }
+
+    let gid = gid.map(|g| g.trim().to_string()).filter(|g| !g.is_empty()).or_else(|| link_gid(&parsed));
+    if let Some(gid) = &gid {
+        if !gid.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("'{}' is not a sheet tab ID (gid)", gid));
+        }
+    }
+    let gid_param = gid.map(|g| format!("&gid={}", g)).unwrap_or_default();
+    let segments: Vec<&str> = parsed.path_segments().map(|s| s.collect()).unwrap_or_default();
+    match segments.as_slice() {
+        ["spreadsheets", "d", "e", id, ..] => {
+            Ok(format!("https://docs.google.com/spreadsheets/d/e/{}/pub?output=csv{}", id, gid_param))
+        }
+        ["spreadsheets", "d", id, ..] if !id.is_empty() => {
+            Ok(format!("https://docs.google.com/spreadsheets/d/{}/export?format=csv{}", id, gid_param))
+        }
+        _ => Err(format!("'{}' is not a link to a Google Sheet", url)),
+    }
+}
+
+/// Download a CSV, refusing web pages, which is what Google serves for
+/// sheets that are not shared publicly. Like /api/proxy, only public
+/// addresses are reached, also after redirects.
+async fn fetch_csv_bytes(url: &str, max_mb: u64) -> Result<Vec<u8>, String> {
+    let url = Url::parse(url).map_err(|e| format!("'{}' is not a valid URL: {}", url, e))?;
+    let mut response = proxy::send_checked(url, Method::GET, &HeaderMap::new(), &[], REQUEST_TIMEOUT, &mut ProxyAudit::default())
+        .await
+        .map_err(|e| e.message().to_string())?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "HTTP {}: The spreadsheet may not be publicly accessible or the URL is incorrect",
+            response.status()
+        ));
+    }
+    let html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    if html {
+        return Err("The URL returned a web page, not CSV. Is the spreadsheet shared publicly?".to_string());
+    }
+
+    let bytes = proxy::read_limited(&mut response, max_mb).await.map_err(|e| e.message().to_string())?;
+    if bytes.iter().all(u8::is_ascii_whitespace) {
+        return Err("The spreadsheet appears to be empty or not publicly accessible".to_string());
+    }
+    Ok(bytes)
+}
+
+/// A fetched and parsed CSV
+pub struct Pull {
+    /// The CSV URL that was fetched
+    pub url: String,
+    pub content_hash: String,
+    pub sheet: SheetData,
+}
+
+async fn fetch_pull(config: &Config, url: &str) -> Result<Pull, String> {
+    let bytes = fetch_csv_bytes(url, config.upload_max_mb).await?;
+    let sheet = spreadsheet::parse_csv(&bytes)?;
+    Ok(Pull { url: url.to_string(), content_hash: NewJob::hash_of(&bytes), sheet })
+}
+
+/// Whether the user may have the server fetch `url`. Links other than
+/// Google Sheets need the admin role, as the server fetches whatever they
+/// point at.
+async fn may_fetch(pool: &Pool<Postgres>, user_id: Uuid, url: &str) -> Result<bool, sqlx::Error> {
+    if Url::parse(url).is_ok_and(|url| is_google_sheet(&url)) {
+        return Ok(true);
+    }
+    auth::is_admin(pool, user_id).await
+}
+
+async fn check_fetch_allowed(pool: &Pool<Postgres>, claims: &Claims, url: &str) -> std::result::Result<(), HttpResponse> {
+    match may_fetch(pool, claims.sub, url).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": "Admin role required to import from URLs other than Google Sheets"
+        }))),
+        Err(e) => Err(entities::database_error(e)),
+    }
+}
+
+/// The user whose sources and snapshots a list shows: None for admins, who
+/// see everyone's
+async fn list_owner(pool: &Pool<Postgres>, claims: &Claims) -> std::result::Result<Option<String>, HttpResponse> {
+    match auth::is_admin(pool, claims.sub).await {
+        Ok(true) => Ok(None),
+        Ok(false) => Ok(Some(claims.user_id())),
+        Err(e) => Err(entities::database_error(e)),
+    }
+}
+
+/// Whether the user may see a source or snapshot: their own, or any for
+/// admins. Other users' are reported as missing.
+async fn check_owner(
+    pool: &Pool<Postgres>,
+    claims: &Claims,
+    created_by: Option<&str>,
+    not_found: HttpResponse,
+) -> std::result::Result<(), HttpResponse> {
+    if created_by == Some(claims.user_id().as_str()) {
+        return Ok(());
+    }
+    match auth::is_admin(pool, claims.sub).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(not_found),
+        Err(e) => Err(entities::database_error(e)),
+    }
+}
+
+/// Fetch the CSV behind a request's URL for the signed-in user
+async fn pull_for(data: &ApiState, claims: &Claims, url: &str, gid: Option<&str>) -> std::result::Result<Pull, HttpResponse> {
+    let url = csv_url(url, gid).map_err(|e| import::import_error(e.clone(), vec![e]))?;
+    check_fetch_allowed(&data.db, claims, &url).await?;
+    fetch_pull(&data.config, &url).await.map_err(|e| {
+        HttpResponse::BadGateway().json(json!({
+            "success": false,
+            "error": format!("Failed to fetch {}: {}", url, e)
+        }))
+    })
+}
+
+/// The sheet's cells as text, as snapshots keep them
+fn sheet_cells(sheet: &SheetData) -> Vec<Vec<String>> {
+    sheet.rows.iter().map(|row| row.iter().map(|cell| cell.to_string()).collect()).collect()
+}
+
+/// A pull as it was kept
+#[derive(Debug, Deserialize)]
+struct Snapshot {
+    id: Uuid,
+    url: String,
+    content_hash: String,
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+    fetched_at: Option<DateTime<Utc>>,
+    created_by: Option<String>,
+}
+
+fn snapshot_from_json(value: Value) -> Result<Snapshot, sqlx::Error> {
+    serde_json::from_value(value).map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+/// The last snapshot `created_by` kept of `url`, or the last one before
+/// `before`. Pulls are only compared with the same user's pulls.
+async fn latest_snapshot(
+    pool: &Pool<Postgres>,
+    url: &str,
+    created_by: &str,
+    before: Option<DateTime<Utc>>,
+) -> Result<Option<Snapshot>, sqlx::Error> {
+    sqlx::query_scalar::<_, Value>(
+        r#"
+        SELECT to_jsonb(s) FROM url_import_snapshots s
+        WHERE s.url = $1 AND s.created_by = $2 AND ($3::timestamptz IS NULL OR s.fetched_at < $3)
+        ORDER BY s.fetched_at DESC
+        LIMIT 1
+        "#
+    )
+    .bind(url)
+    .bind(created_by)
+    .bind(before)
+    .fetch_optional(pool)
+    .await?
+    .map(snapshot_from_json)
+    .transpose()
+}
+
+/// A pull to keep, with the import it fed
+struct NewSnapshot<'a> {
+    url: &'a str,
+    content_hash: &'a str,
+    headers: &'a [String],
+    rows: &'a [Vec<String>],
+    source_id: Option<Uuid>,
+    job_id: Uuid,
+    created_by: &'a str,
+}
+
+async fn save_snapshot(pool: &Pool<Postgres>, snapshot: &NewSnapshot<'_>) -> Result<Uuid, sqlx::Error> {
+    sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO url_import_snapshots (
+            url, source_id, job_id, content_hash, headers, rows, row_count, created_by, fetched_at
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id
+        "#
+    )
+    .bind(snapshot.url)
+    .bind(snapshot.source_id)
+    .bind(snapshot.job_id)
+    .bind(snapshot.content_hash)
+    .bind(json!(snapshot.headers))
+    .bind(json!(snapshot.rows))
+    .bind(snapshot.rows.len() as i32)
+    .bind(snapshot.created_by)
+    .bind(Utc::now())
+    .fetch_one(pool)
+    .await
+}
+
+#[derive(Debug, Serialize)]
+pub struct CellChange {
+    pub before: Value,
+    pub after: Value,
+}
+
+/// A row whose key is in both pulls, with the cells that differ
+#[derive(Debug, Serialize)]
+pub struct RowChange {
+    pub key: String,
+    pub columns: BTreeMap<String, CellChange>,
+}
+
+/// What changed in a sheet since the previous pull. Only the first rows of
+/// each kind are listed.
+#[derive(Debug, Default, Serialize)]
+pub struct SnapshotChanges {
+    /// None on the first pull of a URL, when every row counts as added
+    pub previous_snapshot_id: Option<Uuid>,
+    pub previous_fetched_at: Option<DateTime<Utc>>,
+    pub key_column: Option<String>,
+    pub added_count: usize,
+    pub removed_count: usize,
+    pub changed_count: usize,
+    pub unchanged_count: usize,
+    pub added_columns: Vec<String>,
+    pub removed_columns: Vec<String>,
+    pub added: Vec<Map<String, Value>>,
+    pub removed: Vec<Map<String, Value>>,
+    pub changed: Vec<RowChange>,
+}
+
+/// Non-empty rows as header -> cell records, keyed by their `key_column`
+/// cell. Rows without one, or pulls without the column, are keyed by their
+/// whole content; repeated keys are numbered.
+fn keyed_rows(headers: &[String], rows: &[Vec<String>], key_column: &str) -> Vec<(String, Map<String, Value>)> {
+    let key_index = headers.iter().position(|h| h == key_column);
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    rows.iter()
+        .filter(|row| row.iter().any(|cell| !cell.trim().is_empty()))
+        .map(|row| {
+            let record: Map<String, Value> = headers
+                .iter()
+                .enumerate()
+                .filter(|(_, header)| !header.is_empty())
+                .map(|(i, header)| (header.clone(), json!(row.get(i).map(String::as_str).unwrap_or(""))))
+                .collect();
+            let key = key_index
+                .and_then(|i| row.get(i))
+                .map(|key| key.trim())
+                .filter(|key| !key.is_empty())
+                .map(str::to_string)
+                .unwrap_or_else(|| Value::Object(record.clone()).to_string());
+            let count = seen.entry(key.clone()).or_insert(0);
+            *count += 1;
+            let key = if *count == 1 { key } else { format!("{} #{}", key, count) };
+            (key, record)
+        })
+        .collect()
+}
+
+/// Compare a pull with the one before it
+fn compare(
+    previous: Option<&Snapshot>,
+    headers: &[String],
+    rows: &[Vec<String>],
+    key_column: Option<&str>,
+) -> Result<SnapshotChanges, String> {
+    let key_column = match key_column {
+        Some(column) if headers.iter().any(|h| h == column) => Some(column.to_string()),
+        Some(column) => return Err(format!("Key column '{}' is not a column of the sheet", column)),
+        None => headers.first().filter(|h| !h.is_empty()).cloned(),
+    };
+    let key = key_column.as_deref().unwrap_or("");
+    let current = keyed_rows(headers, rows, key);
+    let previous_rows = previous.map(|p| keyed_rows(&p.headers, &p.rows, key)).unwrap_or_default();
+    let mut previous_by_key: HashMap<&str, &Map<String, Value>> =
+        previous_rows.iter().map(|(key, record)| (key.as_str(), record)).collect();
+
+    let mut changes = SnapshotChanges {
+        previous_snapshot_id: previous.map(|p| p.id),
+        previous_fetched_at: previous.and_then(|p| p.fetched_at),
+        key_column,
+        ..Default::default()
+    };
+    if let Some(previous) = previous {
+        changes.added_columns = headers.iter().filter(|h| !h.is_empty() && !previous.headers.contains(h)).cloned().collect();
+        changes.removed_columns = previous.headers.iter().filter(|h| !h.is_empty() && !headers.contains(h)).cloned().collect();
+    }
+
+    for (key, record) in &current {
+        let Some(before) = previous_by_key.remove(key.as_str()) else {
+            changes.added_count += 1;
+            if changes.added.len() < CHANGES_LISTED {
+                changes.added.push(record.clone());
+            }
+            continue;
+        };
+        // Only columns in both pulls are compared
+        let columns: BTreeMap<String, CellChange> = record
+            .iter()
+            .filter_map(|(column, after)| {
+                let before = before.get(column)?;
+                (before != after).then(|| (column.clone(), CellChange { before: before.clone(), after: after.clone() }))
+            })
+            .collect();
+        if columns.is_empty() {
+            changes.unchanged_count += 1;
+        } else {
+            changes.changed_count += 1;
+            if changes.changed.len() < CHANGES_LISTED {
+                changes.changed.push(RowChange { key: key.clone(), columns });
+            }
+        }
+    }
+    for (key, record) in &previous_rows {
+        if previous_by_key.contains_key(key.as_str()) {
+            changes.removed_count += 1;
+            if changes.removed.len() < CHANGES_LISTED {
+                changes.removed.push(record.clone());
+            }
+        }
+    }
+    Ok(changes)
+}
+
+/// The table and column mapping of a URL import
+async fn settle_target(
+    pool: &Pool<Postgres>,
+    req: &UrlImportRequest,
+) -> std::result::Result<(&'static EntityDef, Option<ColumnMappings>), HttpResponse> {
+    let entity = import_mapping::importable_table(&req.table_name).map_err(|e| import::import_error(e.clone(), vec![e]))?;
+    let profile = import::load_profile(pool, req.profile.as_deref(), &req.table_name).await?;
+    Ok((entity, req.column_mappings.clone().or_else(|| profile.map(|p| p.column_mappings))))
+}
+
+/// The user's last snapshot of `url` and what changed since
+async fn changes_since_latest(
+    pool: &Pool<Postgres>,
+    url: &str,
+    created_by: &str,
+    headers: &[String],
+    cells: &[Vec<String>],
+    key_column: Option<&str>,
+) -> std::result::Result<(Option<Snapshot>, SnapshotChanges), HttpResponse> {
+    let previous = latest_snapshot(pool, url, created_by, None).await.map_err(entities::database_error)?;
+    let changes = compare(previous.as_ref(), headers, cells, key_column)
+        .map_err(|e| import::import_error(e.clone(), vec![e]))?;
+    Ok((previous, changes))
+}
+
+/// What importing a pull did
+struct PulledImport {
+    entity: &'static EntityDef,
+    job_id: Uuid,
+    /// None when the import runs in the background
+    outcome: Option<ImportOutcome>,
+    duplicate_check_columns: Option<String>,
+    url: String,
+    /// None for dry runs and when the CSV is the same as last time
+    snapshot_id: Option<Uuid>,
+    changes: SnapshotChanges,
+}
+
+#[derive(Serialize)]
+struct UrlImportResponse {
+    #[serde(flatten)]
+    import: ImportResponse,
+    url: String,
+    snapshot_id: Option<Uuid>,
+    changes: SnapshotChanges,
+}
+
+impl PulledImport {
+    fn into_response(self, options: &ImportOptions) -> HttpResponse {
+        let Some(outcome) = self.outcome else {
+            return import_tasks::accepted(self.job_id);
+        };
+        HttpResponse::Ok().json(UrlImportResponse {
+            import: ImportResponse::from_outcome(self.job_id, outcome, self.entity.name, self.duplicate_check_columns, options),
+            url: self.url,
+            snapshot_id: self.snapshot_id,
+            changes: self.changes,
+        })
+    }
+}
+
+/// Import a pull and keep it as a snapshot when it differs from the last one
+async fn import_pull(
+    state: &web::Data<Arc<ApiState>>,
+    user_id: String,
+    req: &UrlImportRequest,
+    pull: Pull,
+    source_id: Option<Uuid>,
+) -> std::result::Result<PulledImport, HttpResponse> {
+    let (entity, mappings) = settle_target(&state.db, req).await?;
+    let Pull { url, content_hash, sheet } = pull;
+    let headers = sheet.headers.clone();
+    let cells = sheet_cells(&sheet);
+    let (previous, changes) =
+        changes_since_latest(&state.db, &url, &user_id, &headers, &cells, req.key_column.as_deref()).await?;
+    let unchanged = previous.is_some_and(|p| p.content_hash == content_hash);
+
+    let mut job = NewJob {
+        source: "url".to_string(),
+        file_name: Some(url.clone()),
+        file_hash: Some(content_hash.clone()),
+        profile: req.profile.clone(),
+        column_mappings: None,
+    };
+    let prepared = import::prepare_sheet(entity, sheet, mappings)?;
+    let (entity, rows, default_check) = import::prepared_rows(prepared, &mut job);
+    let duplicates = DuplicateCheck::for_import(entity, default_check, &req.options)
+        .map_err(|errors| import::import_error(format!("Match columns for {} are not valid", entity.name), errors))?;
+
+    let duplicate_check_columns = duplicates.label();
+    let run = ImportRun {
+        job,
+        entity,
+        user_id: user_id.clone(),
+        rows,
+        duplicates,
+        options: req.options.clone(),
+    };
+    let (job_id, outcome) = import_tasks::start(state, run).await?;
+
+    let snapshot_id = if req.options.dry_run || unchanged {
+        None
+    } else {
+        let snapshot = NewSnapshot {
+            url: &url,
+            content_hash: &content_hash,
+            headers: &headers,
+            rows: &cells,
+            source_id,
+            job_id,
+            created_by: &user_id,
+        };
+        Some(save_snapshot(&state.db, &snapshot).await.map_err(entities::database_error)?)
+    };
+    Ok(PulledImport {
+        entity,
+        job_id,
+        outcome,
+        duplicate_check_columns,
+        url,
+        snapshot_id,
+        changes,
+    })
+}
+
+/// Preview a sheet from a URL as it would be imported, with what changed
+/// since the last pull
+pub async fn preview_url_import(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    req: web::Json<UrlImportRequest>,
+) -> Result<HttpResponse> {
+    let pull = match pull_for(&data, &claims, &req.url, req.gid.as_deref()).await {
+        Ok(pull) => pull,
+        Err(response) => return Ok(response),
+    };
+    let (entity, mappings) = match settle_target(&data.db, &req).await {
+        Ok(target) => target,
+        Err(response) => return Ok(response),
+    };
+    let cells = sheet_cells(&pull.sheet);
+    let changes = match changes_since_latest(
+        &data.db,
+        &pull.url,
+        &claims.user_id(),
+        &pull.sheet.headers,
+        &cells,
+        req.key_column.as_deref(),
+    )
+    .await
+    {
+        Ok((_, changes)) => changes,
+        Err(response) => return Ok(response),
+    };
+    let prepared = match import::prepare_sheet(entity, pull.sheet, mappings) {
+        Ok(prepared) => prepared,
+        Err(response) => return Ok(response),
+    };
+
+    match import::preview_json(&data.db, prepared).await {
+        Ok(mut preview) => {
+            if let Value::Object(fields) = &mut preview {
+                fields.insert("url".to_string(), json!(pull.url));
+                fields.insert("changes".to_string(), json!(changes));
+            }
+            Ok(HttpResponse::Ok().json(preview))
+        }
+        Err(response) => Ok(response),
+    }
+}
+
+/// Fetch, map and import a sheet from a URL in one request
+pub async fn import_from_url(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    req: web::Json<UrlImportRequest>,
+) -> Result<HttpResponse> {
+    let pull = match pull_for(&data, &claims, &req.url, req.gid.as_deref()).await {
+        Ok(pull) => pull,
+        Err(response) => return Ok(response),
+    };
+    match import_pull(&data, claims.user_id(), &req, pull, None).await {
+        Ok(pulled) => Ok(pulled.into_response(&req.options)),
+        Err(response) => Ok(response),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FetchCsvRequest {
+    url: String,
+    gid: Option<String>,
+}
+
+// Fetch a public Google Sheet as CSV text (proxy for CORS)
+pub async fn fetch_csv(data: web::Data<Arc<ApiState>>, req: web::Json<FetchCsvRequest>) -> Result<HttpResponse> {
+    if !Url::parse(req.url.trim()).is_ok_and(|url| is_google_sheet(&url)) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Only Google Sheets URLs are allowed"
+        })));
+    }
+    let fetched = match csv_url(&req.url, req.gid.as_deref()) {
+        Ok(url) => fetch_csv_bytes(&url, data.config.upload_max_mb * 1024 * 1024).await,
+        Err(e) => Err(e),
+    };
+    match fetched {
+        Ok(bytes) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": String::from_utf8_lossy(&bytes)
+        }))),
+        Err(e) => Ok(HttpResponse::Ok().json(json!({
+            "success": false,
+            "error": e
+        }))),
+    }
+}
+
+/// Snapshots, newest first, of one URL (`url`, with `gid` for a Google
+/// Sheet) or of all of them. Cells are left out; see `/changes`. Admins see
+/// every user's snapshots.
+pub async fn list_snapshots(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    let pagination = match Pagination::from_query(&query) {
+        Ok(p) => p,
+        Err(e) => return Ok(entities::validation_failed(vec![e])),
+    };
+    let url = match query.get("url") {
+        Some(url) => match csv_url(url, query.get("gid").map(String::as_str)) {
+            Ok(url) => Some(url),
+            Err(e) => return Ok(entities::validation_failed(vec![e])),
+        },
+        None => None,
+    };
+    let owner = match list_owner(&data.db, &claims).await {
+        Ok(owner) => owner,
+        Err(response) => return Ok(response),
+    };
+
+    let total = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM url_import_snapshots WHERE ($1::text IS NULL OR url = $1) AND ($2::text IS NULL OR created_by = $2)"
+    )
+    .bind(&url)
+    .bind(&owner)
+    .fetch_one(&data.db)
+    .await;
+    let total = match total {
+        Ok(total) => total,
+        Err(e) => return Ok(entities::database_error(e)),
+    };
+    let snapshots = sqlx::query_scalar::<_, Value>(
+        r#"
+        SELECT to_jsonb(s) - 'headers' - 'rows'
+        FROM url_import_snapshots s
+        WHERE ($1::text IS NULL OR s.url = $1) AND ($2::text IS NULL OR s.created_by = $2)
+        ORDER BY s.fetched_at DESC
+        LIMIT $3 OFFSET $4
+        "#
+    )
+    .bind(&url)
+    .bind(&owner)
+    .bind(pagination.limit())
+    .bind(pagination.offset())
+    .fetch_all(&data.db)
+    .await;
+
+    match snapshots {
+        Ok(snapshots) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "pagination": pagination.to_json(total, snapshots.len()),
+            "data": snapshots
+        }))),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+fn snapshot_not_found(id: Uuid) -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "error": format!("Snapshot {} not found", id)
+    }))
+}
+
+/// What a snapshot changed compared with the pull of the same URL before
+/// it by the same user. `key_column` picks the column that identifies a row.
+pub async fn snapshot_changes(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    path: web::Path<Uuid>,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let snapshot = sqlx::query_scalar::<_, Value>("SELECT to_jsonb(s) FROM url_import_snapshots s WHERE s.id = $1")
+        .bind(id)
+        .fetch_optional(&data.db)
+        .await
+        .and_then(|found| found.map(snapshot_from_json).transpose());
+    let snapshot = match snapshot {
+        Ok(Some(snapshot)) => snapshot,
+        Ok(None) => return Ok(snapshot_not_found(id)),
+        Err(e) => return Ok(entities::database_error(e)),
+    };
+    if let Err(response) = check_owner(&data.db, &claims, snapshot.created_by.as_deref(), snapshot_not_found(id)).await {
+        return Ok(response);
+    }
+    let created_by = snapshot.created_by.as_deref().unwrap_or_default();
+    let previous = match latest_snapshot(&data.db, &snapshot.url, created_by, snapshot.fetched_at).await {
+        Ok(previous) => previous,
+        Err(e) => return Ok(entities::database_error(e)),
+    };
+
+    match compare(previous.as_ref(), &snapshot.headers, &snapshot.rows, query.get("key_column").map(String::as_str)) {
+        Ok(changes) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "snapshot_id": snapshot.id,
+            "url": snapshot.url,
+            "fetched_at": snapshot.fetched_at,
+            "data": changes
+        }))),
+        Err(e) => Ok(entities::validation_failed(vec![e])),
+    }
+}
+
+/// A saved URL import, as stored in `url_import_sources`
+#[derive(Debug, Deserialize)]
+struct SavedSource {
+    id: Uuid,
+    name: String,
+    url: String,
+    gid: Option<String>,
+    table_name: String,
+    profile: Option<String>,
+    column_mappings: Option<ColumnMappings>,
+    key_column: Option<String>,
+    options: ImportOptions,
+    created_by: Option<String>,
+}
+
+impl SavedSource {
+    fn request(&self) -> UrlImportRequest {
+        UrlImportRequest {
+            url: self.url.clone(),
+            gid: self.gid.clone(),
+            table_name: self.table_name.clone(),
+            column_mappings: self.column_mappings.clone(),
+            profile: self.profile.clone(),
+            key_column: self.key_column.clone(),
+            options: self.options.clone(),
+        }
+    }
+}
+
+fn source_from_json(value: Value) -> Result<SavedSource, sqlx::Error> {
+    serde_json::from_value(value).map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+fn source_not_found(id: Uuid) -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "error": format!("URL import source {} not found", id)
+    }))
+}
+
+/// A source the user may see: their own, or any for admins
+async fn find_source(pool: &Pool<Postgres>, id: Uuid, claims: &Claims) -> std::result::Result<SavedSource, HttpResponse> {
+    let source = sqlx::query_scalar::<_, Value>("SELECT to_jsonb(s) FROM url_import_sources s WHERE s.id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .and_then(|found| found.map(source_from_json).transpose())
+        .map_err(entities::database_error)?
+        .ok_or_else(|| source_not_found(id))?;
+    check_owner(pool, claims, source.created_by.as_deref(), source_not_found(id)).await?;
+    Ok(source)
+}
+
+/// Record that a source was pulled, so the schedule counts from now
+async fn mark_pulled(pool: &Pool<Postgres>, id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE url_import_sources SET last_pulled_at = $2 WHERE id = $1")
+        .bind(id)
+        .bind(Utc::now())
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSourceRequest {
+    pub name: String,
+    /// Minutes between scheduled pulls; without it the source is pulled only on request
+    pub schedule_minutes: Option<i32>,
+    #[serde(flatten)]
+    pub import: UrlImportRequest,
+}
+
+/// Saved sources by name; admins see every user's
+pub async fn list_sources(data: web::Data<Arc<ApiState>>, claims: Claims) -> Result<HttpResponse> {
+    let owner = match list_owner(&data.db, &claims).await {
+        Ok(owner) => owner,
+        Err(response) => return Ok(response),
+    };
+    let sources = sqlx::query_scalar::<_, Value>(
+        "SELECT to_jsonb(s) FROM url_import_sources s WHERE $1::text IS NULL OR s.created_by = $1 ORDER BY s.name"
+    )
+    .bind(&owner)
+    .fetch_all(&data.db)
+    .await;
+    match sources {
+        Ok(sources) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": sources
+        }))),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+/// Save a URL import to pull again later, checking it as an import would
+pub async fn create_source(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    req: web::Json<CreateSourceRequest>,
+) -> Result<HttpResponse> {
+    let name = req.name.trim();
+    let mut errors = Vec::new();
+    if name.is_empty() || name.chars().count() > 150 {
+        errors.push("name must be 1 to 150 characters".to_string());
+    }
+    if req.schedule_minutes.is_some_and(|minutes| minutes < 1) {
+        errors.push("schedule_minutes must be at least 1".to_string());
+    }
+    let url = match csv_url(&req.import.url, req.import.gid.as_deref()) {
+        Ok(url) => Some(url),
+        Err(e) => {
+            errors.push(e);
+            None
+        }
+    };
+    let (entity, mappings) = match settle_target(&data.db, &req.import).await {
+        Ok(target) => target,
+        Err(response) => return Ok(response),
+    };
+    if let Some(mappings) = &mappings {
+        errors.extend(import_mapping::check_targets(entity, mappings));
+    }
+    if let Err(match_errors) = DuplicateCheck::for_import(entity, DuplicateCheck::None, &req.import.options) {
+        errors.extend(match_errors);
+    }
+    if !errors.is_empty() {
+        return Ok(entities::validation_failed(errors));
+    }
+    if let Some(url) = &url {
+        if let Err(response) = check_fetch_allowed(&data.db, &claims, url).await {
+            return Ok(response);
+        }
+    }
+
+    let result = sqlx::query_scalar::<_, Value>(
+        r#"
+        INSERT INTO url_import_sources AS s (
+            name, url, gid, table_name, profile, column_mappings, key_column,
+            options, schedule_minutes, date_entered, created_by
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        RETURNING to_jsonb(s)
+        "#
+    )
+    .bind(name)
+    .bind(req.import.url.trim())
+    .bind(&req.import.gid)
+    .bind(&req.import.table_name)
+    .bind(&req.import.profile)
+    .bind(req.import.column_mappings.as_ref().map(|m| json!(m)))
+    .bind(&req.import.key_column)
+    .bind(json!(req.import.options))
+    .bind(req.schedule_minutes)
+    .bind(Utc::now())
+    .bind(claims.user_id())
+    .fetch_one(&data.db)
+    .await;
+
+    match result {
+        Ok(source) => Ok(HttpResponse::Created().json(json!({
+            "success": true,
+            "message": format!("URL import source '{}' saved", name),
+            "data": source
+        }))),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "error": format!("A URL import source named '{}' already exists", name)
+        }))),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+pub async fn delete_source(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    if let Err(response) = find_source(&data.db, id, &claims).await {
+        return Ok(response);
+    }
+    let result = sqlx::query("DELETE FROM url_import_sources WHERE id = $1")
+        .bind(id)
+        .execute(&data.db)
+        .await;
+    match result {
+        Ok(r) if r.rows_affected() == 0 => Ok(source_not_found(id)),
+        Ok(_) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": format!("URL import source {} deleted; its snapshots are kept", id)
+        }))),
+        Err(e) => Ok(entities::database_error(e)),
+    }
+}
+
+/// Pull a saved source now
+pub async fn pull_source(
+    data: web::Data<Arc<ApiState>>,
+    claims: Claims,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let source = match find_source(&data.db, path.into_inner(), &claims).await {
+        Ok(source) => source,
+        Err(response) => return Ok(response),
+    };
+
+    let req = source.request();
+    let pull = match pull_for(&data, &claims, &req.url, req.gid.as_deref()).await {
+        Ok(pull) => pull,
+        Err(response) => return Ok(response),
+    };
+    if let Err(e) = mark_pulled(&data.db, source.id).await {
+        return Ok(entities::database_error(e));
+    }
+    match import_pull(&data, claims.user_id(), &req, pull, Some(source.id)).await {
+        Ok(pulled) => Ok(pulled.into_response(&req.options)),
+        Err(response) => Ok(response),
+    }
+}
+
+/// Sources with a schedule whose next pull is due
+async fn due_sources(pool: &Pool<Postgres>) -> Result<Vec<SavedSource>, sqlx::Error> {
+    sqlx::query_scalar::<_, Value>(
+        r#"
+        SELECT to_jsonb(s) FROM url_import_sources s
+        WHERE s.schedule_minutes IS NOT NULL
+          AND (s.last_pulled_at IS NULL OR s.last_pulled_at + make_interval(mins => s.schedule_minutes) <= now())
+        ORDER BY s.last_pulled_at NULLS FIRST
+        "#
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(source_from_json)
+    .collect()
+}
+
+/// Pull a source on its schedule. The import is skipped when the CSV is the
+/// same as at the last pull.
+async fn scheduled_pull(state: &Arc<ApiState>, source: SavedSource) {
+    // Marked first, so a failing source is retried next period rather than every check
+    if let Err(e) = mark_pulled(&state.db, source.id).await {
+        println!("URL import '{}' failed: {}", source.name, e);
+        return;
+    }
+    let req = source.request();
+    let url = match csv_url(&req.url, req.gid.as_deref()) {
+        Ok(url) => url,
+        Err(e) => {
+            println!("URL import '{}' failed: {}", source.name, e);
+            return;
+        }
+    };
+    // The creator's role is checked again on every pull, as it may have been
+    // taken away since the source was saved
+    let creator = source.created_by.as_deref().and_then(|id| Uuid::parse_str(id).ok());
+    let allowed = match creator {
+        Some(creator) => may_fetch(&state.db, creator, &url).await,
+        None => Ok(Url::parse(&url).is_ok_and(|url| is_google_sheet(&url))),
+    };
+    match allowed {
+        Ok(true) => {}
+        Ok(false) => {
+            println!("URL import '{}' skipped: its creator may no longer import from {}", source.name, url);
+            return;
+        }
+        Err(e) => {
+            println!("URL import '{}' failed: {}", source.name, e);
+            return;
+        }
+    }
+    let fetched = fetch_pull(&state.config, &url).await;
+    let pull = match fetched {
+        Ok(pull) => pull,
+        Err(e) => {
+            println!("URL import '{}' failed: {}", source.name, e);
+            return;
+        }
+    };
+    let user_id = source.created_by.clone().unwrap_or_else(|| SCHEDULE_USER.to_string());
+    match latest_snapshot(&state.db, &pull.url, &user_id, None).await {
+        Ok(Some(latest)) if latest.content_hash == pull.content_hash => return,
+        Ok(_) => {}
+        Err(e) => {
+            println!("URL import '{}' failed: {}", source.name, e);
+            return;
+        }
+    }
+
+    match import_pull(&web::Data::new(state.clone()), user_id, &req, pull, Some(source.id)).await {
+        Ok(pulled) => {
+            let message = match &pulled.outcome {
+                Some(outcome) => outcome.message(pulled.entity.name, &req.options),
+                None => "started in the background".to_string(),
+            };
+            println!(
+                "URL import '{}' (job {}): {}; {} rows added, {} removed, {} changed since the last pull",
+                source.name, pulled.job_id, message,
+                pulled.changes.added_count, pulled.changes.removed_count, pulled.changes.changed_count
+            );
+        }
+        Err(response) => println!("URL import '{}' failed with status {}", source.name, response.status()),
+    }
+}
+
+/// Pull saved sources whose schedule is due while the server runs
+pub fn spawn_schedule(state: Arc<ApiState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SCHEDULE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match due_sources(&state.db).await {
+                Ok(sources) => {
+                    for source in sources {
+                        scheduled_pull(&state, source).await;
+                    }
+                }
+                Err(e) => println!("Cannot read URL import sources: {}", e),
+            }
+        }
+    });
+}